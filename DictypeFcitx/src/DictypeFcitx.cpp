@@ -81,6 +81,14 @@ DictypeFcitx::DictypeFcitx(fcitx::AddonManager* addonManager)
             }
         }));
 
+    eventHandlers_.emplace_back(instance_->watchEvent(
+        fcitx::EventType::InputContextFocusIn,
+        fcitx::EventWatcherPhase::Default, [this](fcitx::Event& /*event*/) {
+            if (!running_.load(std::memory_order_acquire)) {
+                prepare_();
+            }
+        }));
+
     const auto cred = grpc::experimental::LocalCredentials(UDS);
     auto channelArgs = grpc::ChannelArguments();
     channelArgs.SetString(GRPC_ARG_DEFAULT_AUTHORITY, "localhost");
@@ -366,6 +374,28 @@ void DictypeFcitx::stop_() const {
     }
 }
 
+void DictypeFcitx::prepare_() const {
+    try {
+        auto* ctx = new grpc::ClientContext();
+        auto* req = new Dictype::PrepareRequest();
+        auto* resp = new Dictype::PrepareResponse();
+
+        stub->async()->Prepare(ctx, req, resp,
+                               [ctx, req, resp](const grpc::Status& s) {
+                                   if (!s.ok()) {
+                                       DICTYPE_WARN() << "Prepare RPC failed: "
+                                                      << s.error_message();
+                                   }
+
+                                   delete ctx;
+                                   delete req;
+                                   delete resp;
+                               });
+    } catch (...) {
+        DICTYPE_WARN() << "Failed to dispatch Prepare RPC.";
+    }
+}
+
 class DictypeFcitxFactory final : public fcitx::AddonFactory {
     fcitx::AddonInstance* create(fcitx::AddonManager* manager) override {
         return new DictypeFcitx(manager);
@@ -63,6 +63,12 @@ class DictypeFcitx final : public fcitx::AddonInstance,
      */
     void stop_() const;
 
+    /**
+     * hints the daemon that a session may start soon, so that it can connect
+     * to the backends ahead of time.
+     */
+    void prepare_() const;
+
     std::vector<std::unique_ptr<fcitx::HandlerTableEntry<fcitx::EventHandler>>>
         eventHandlers_;
 };
@@ -0,0 +1,288 @@
+//! API keys that stay out of logs, and the places a key may be read from instead of the config.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// A secret that is redacted in `Debug`.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    #[must_use]
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// The key itself, e.g. for an `Authorization` header.
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiKey(<redacted>)")
+    }
+}
+
+impl From<&str> for ApiKey {
+    fn from(key: &str) -> Self {
+        Self::new(key)
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(key: String) -> Self {
+        Self::new(key)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiKeyError {
+    #[error("no API key is configured")]
+    Missing,
+
+    #[error("more than one API key source is configured")]
+    Ambiguous,
+
+    #[error("failed to read API key from {path}: {source}")]
+    File { path: String, source: io::Error },
+
+    #[error("environment variable {0} is not set")]
+    Env(String),
+
+    #[error("API key command `{command}` failed: {message}")]
+    Command { command: String, message: String },
+
+    #[error("API key from {0} is empty")]
+    Empty(String),
+}
+
+/// Where a profile's API key comes from. Exactly one of them is configured.
+#[derive(Debug, Clone, Copy)]
+pub enum ApiKeySource<'a> {
+    Key(&'a ApiKey),
+    /// A file holding the key, e.g. one decrypted by a secret manager. `~/` is the home directory.
+    File(&'a str),
+    Env(&'a str),
+    /// A shell command printing the key, e.g. `pass show dashscope`. It is run anew each time.
+    Command(&'a str),
+}
+
+impl<'a> ApiKeySource<'a> {
+    pub fn new(
+        key: Option<&'a ApiKey>,
+        file: Option<&'a str>,
+        env: Option<&'a str>,
+        command: Option<&'a str>,
+    ) -> Result<Self, ApiKeyError> {
+        let sources = [
+            key.map(Self::Key),
+            file.map(Self::File),
+            env.map(Self::Env),
+            command.map(Self::Command),
+        ];
+        let mut sources = sources.into_iter().flatten();
+        match (sources.next(), sources.next()) {
+            (Some(source), None) => Ok(source),
+            (None, _) => Err(ApiKeyError::Missing),
+            (Some(_), Some(_)) => Err(ApiKeyError::Ambiguous),
+        }
+    }
+
+    /// Reads the key. Surrounding whitespace, like a trailing newline, is dropped.
+    pub async fn resolve(self) -> Result<ApiKey, ApiKeyError> {
+        let key = match self {
+            Self::Key(key) => return Ok(key.clone()),
+            Self::File(path) => {
+                tokio::fs::read_to_string(expand_home(path))
+                    .await
+                    .map_err(|source| ApiKeyError::File {
+                        path: path.to_string(),
+                        source,
+                    })?
+            }
+            Self::Env(name) => {
+                std::env::var(name).map_err(|_| ApiKeyError::Env(name.to_string()))?
+            }
+            Self::Command(command) => {
+                let failed = |message: String| ApiKeyError::Command {
+                    command: command.to_string(),
+                    message,
+                };
+                let output = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .kill_on_drop(true)
+                    .output()
+                    .await
+                    .map_err(|e| failed(e.to_string()))?;
+                if !output.status.success() {
+                    return Err(failed(format!(
+                        "{}: {}",
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                String::from_utf8(output.stdout).map_err(|e| failed(e.to_string()))?
+            }
+        };
+
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(ApiKeyError::Empty(self.describe()));
+        }
+        Ok(ApiKey::new(key))
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Self::Key(_) => "the config".to_string(),
+            Self::File(path) => path.to_string(),
+            Self::Env(name) => format!("${name}"),
+            Self::Command(command) => format!("`{command}`"),
+        }
+    }
+}
+
+/// The `dashscope_api_key*` settings of a profile, flattened into its config. Exactly one of
+/// them is given; a file, variable or command is read anew at the start of each session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DashscopeApiKey {
+    pub dashscope_api_key: Option<ApiKey>,
+    /// A file holding the key.
+    pub dashscope_api_key_file: Option<String>,
+    /// An environment variable holding the key.
+    pub dashscope_api_key_env: Option<String>,
+    /// A shell command printing the key.
+    pub dashscope_api_key_command: Option<String>,
+}
+
+impl DashscopeApiKey {
+    /// Where the key comes from, or why the settings name no single source.
+    pub fn source(&self) -> Result<ApiKeySource<'_>, ApiKeyError> {
+        ApiKeySource::new(
+            self.dashscope_api_key.as_ref(),
+            self.dashscope_api_key_file.as_deref(),
+            self.dashscope_api_key_env.as_deref(),
+            self.dashscope_api_key_command.as_deref(),
+        )
+    }
+
+    pub async fn resolve(&self) -> Result<ApiKey, ApiKeyError> {
+        self.source()?.resolve().await
+    }
+}
+
+impl From<ApiKey> for DashscopeApiKey {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            dashscope_api_key: Some(key),
+            ..Self::default()
+        }
+    }
+}
+
+/// The `api_key*` settings of a profile, flattened into its config, e.g. for a bearer token. At
+/// most one of them is given; a file, variable or command is read anew at the start of each
+/// session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BearerApiKey {
+    pub api_key: Option<ApiKey>,
+    /// A file holding the key.
+    pub api_key_file: Option<String>,
+    /// An environment variable holding the key.
+    pub api_key_env: Option<String>,
+    /// A shell command printing the key.
+    pub api_key_command: Option<String>,
+}
+
+impl BearerApiKey {
+    /// Where the key comes from, or why the settings name no single source.
+    pub fn source(&self) -> Result<ApiKeySource<'_>, ApiKeyError> {
+        ApiKeySource::new(
+            self.api_key.as_ref(),
+            self.api_key_file.as_deref(),
+            self.api_key_env.as_deref(),
+            self.api_key_command.as_deref(),
+        )
+    }
+
+    pub async fn resolve(&self) -> Result<ApiKey, ApiKeyError> {
+        self.source()?.resolve().await
+    }
+}
+
+impl From<ApiKey> for BearerApiKey {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            api_key: Some(key),
+            ..Self::default()
+        }
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_is_redacted() {
+        let key = ApiKey::new("sk-secret");
+        assert_eq!(format!("{key:?}"), "ApiKey(<redacted>)");
+        assert_eq!(format!("{:?}", Some(&key)), "Some(ApiKey(<redacted>))");
+    }
+
+    #[test]
+    fn exactly_one_source() {
+        let key = ApiKey::new("sk-secret");
+        assert!(matches!(
+            ApiKeySource::new(None, None, None, None),
+            Err(ApiKeyError::Missing)
+        ));
+        assert!(matches!(
+            ApiKeySource::new(Some(&key), None, Some("DASHSCOPE_API_KEY"), None),
+            Err(ApiKeyError::Ambiguous)
+        ));
+        assert!(matches!(
+            ApiKeySource::new(None, None, None, Some("pass show dashscope")),
+            Ok(ApiKeySource::Command("pass show dashscope"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reads_key_from_file_and_command() {
+        let path = std::env::temp_dir().join(format!("dictype-api-key-{}", std::process::id()));
+        std::fs::write(&path, "sk-from-file\n").unwrap();
+        let file = ApiKeySource::File(path.to_str().unwrap()).resolve().await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(file.unwrap().expose(), "sk-from-file");
+
+        let command = ApiKeySource::Command("echo sk-from-command")
+            .resolve()
+            .await;
+        assert_eq!(command.unwrap().expose(), "sk-from-command");
+
+        assert!(matches!(
+            ApiKeySource::Command("exit 3").resolve().await,
+            Err(ApiKeyError::Command { .. })
+        ));
+        assert!(matches!(
+            ApiKeySource::Command("true").resolve().await,
+            Err(ApiKeyError::Empty(_))
+        ));
+    }
+}
@@ -11,4 +11,9 @@ pub trait AsrClient {
         &self,
         audio_stream: AudioStream,
     ) -> Result<Self::TranscriptionStream, anyhow::Error>;
+
+    /// Opens a connection ahead of the next `create`, where the backend has one.
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
 }
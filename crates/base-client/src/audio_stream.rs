@@ -3,6 +3,7 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use futures_util::Stream;
+use serde::{Deserialize, Serialize};
 use tokio_util::bytes::Bytes;
 use tokio_util::sync::CancellationToken;
 
@@ -16,6 +17,17 @@ impl Stream for AudioStream {
     }
 }
 
+/// Where a session captures its audio from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioSource {
+    /// A recording device, e.g. a microphone.
+    #[default]
+    Microphone,
+
+    /// The monitor of an output device, i.e. whatever is being played back.
+    Monitor,
+}
+
 pub trait AudioCapture {
     type CaptureOption;
 
@@ -23,5 +35,9 @@ pub trait AudioCapture {
     where
         Self: Sized;
 
-    fn create(&self, cancellation_token: CancellationToken) -> io::Result<AudioStream>;
+    fn create(
+        &self,
+        source: AudioSource,
+        cancellation_token: CancellationToken,
+    ) -> io::Result<AudioStream>;
 }
@@ -4,4 +4,7 @@ pub mod proto {
 }
 
 pub use proto::dictype_server::{Dictype, DictypeServer};
-pub use proto::{StopRequest, StopResponse, TranscribeRequest, TranscribeResponse};
+pub use proto::{
+    PrepareRequest, PrepareResponse, ProfileUsage, StopRequest, StopResponse, TranscribeRequest,
+    TranscribeResponse, UsageRequest, UsageResponse,
+};
@@ -1,4 +1,12 @@
+pub mod api_key;
 pub mod asr_client;
 pub mod audio_stream;
 pub mod grpc_server;
+pub mod prepared_connection;
+pub mod proxy;
+pub mod reconnect;
+pub mod run_task;
+pub mod traffic_log;
 pub mod transcribe_stream;
+pub mod vad;
+pub mod websocket_session;
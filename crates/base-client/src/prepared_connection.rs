@@ -0,0 +1,74 @@
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// A connection opened ahead of a session, so that the session can start without waiting for a
+/// handshake.
+pub struct PreparedConnection<W> {
+    slot: Mutex<Option<(Instant, W)>>,
+}
+
+impl<W> PreparedConnection<W> {
+    /// Older connections are dropped instead of used, as servers close idle ones.
+    pub const MAX_AGE: Duration = Duration::from_secs(30);
+
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            slot: Mutex::new(None),
+        }
+    }
+
+    /// Keeps `connection` for the next session, replacing any kept before.
+    pub fn put(&self, connection: W) {
+        self.lock().replace((Instant::now(), connection));
+    }
+
+    /// Takes the kept connection, if it is still fresh.
+    pub fn take(&self) -> Option<W> {
+        self.lock()
+            .take()
+            .filter(|(prepared_at, _)| prepared_at.elapsed() < Self::MAX_AGE)
+            .map(|(_, connection)| connection)
+    }
+
+    /// Whether a fresh connection is kept, so that preparing another is not needed.
+    pub fn is_fresh(&self) -> bool {
+        self.lock()
+            .as_ref()
+            .is_some_and(|(prepared_at, _)| prepared_at.elapsed() < Self::MAX_AGE)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<(Instant, W)>> {
+        self.slot.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<W> Default for PreparedConnection<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W> fmt::Debug for PreparedConnection<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreparedConnection")
+            .field("fresh", &self.is_fresh())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_is_taken_once() {
+        let prepared = PreparedConnection::new();
+        assert!(!prepared.is_fresh());
+        prepared.put(1);
+        assert!(prepared.is_fresh());
+        assert_eq!(prepared.take(), Some(1));
+        assert_eq!(prepared.take(), None);
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::vad::ms_to_samples;
+
+/// Delays between attempts to reconnect a session that lost its connection.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    /// Attempts made before giving up.
+    pub const MAX_ATTEMPTS: u32 = 5;
+    const INITIAL_DELAY: Duration = Duration::from_millis(250);
+    const MAX_DELAY: Duration = Duration::from_secs(4);
+
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { attempt: 0 }
+    }
+
+    /// The delay before the next attempt, or `None` once all attempts are used up.
+    /// The first attempt is made right away.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if self.attempt >= Self::MAX_ATTEMPTS {
+            return None;
+        }
+        let delay = match self.attempt {
+            0 => Duration::ZERO,
+            n => Self::INITIAL_DELAY
+                .saturating_mul(1 << (n - 1).min(16))
+                .min(Self::MAX_DELAY),
+        };
+        self.attempt += 1;
+        Some(delay)
+    }
+
+    /// Called once a connection is established and working again.
+    pub const fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whatever was sent to a service since its last final result, to be sent again to a new
+/// connection if the current one is lost. Positions are counted in 16 kHz samples from the start
+/// of the session.
+#[derive(Debug)]
+pub struct ReplayBuffer<T> {
+    /// Entries with the position right after each of them.
+    entries: VecDeque<(usize, T)>,
+    /// Position of the first entry.
+    start: usize,
+    max_samples: usize,
+}
+
+impl<T> ReplayBuffer<T> {
+    /// Keeps at most `max_ms` of audio; older entries are dropped as new ones arrive.
+    #[must_use]
+    pub const fn new(max_ms: u32) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            start: 0,
+            max_samples: ms_to_samples(max_ms),
+        }
+    }
+
+    /// Appends an entry ending at `end`.
+    pub fn push(&mut self, end: usize, entry: T) {
+        self.entries.push_back((end, entry));
+        while end - self.start > self.max_samples {
+            let Some((front_end, _)) = self.entries.pop_front() else {
+                break;
+            };
+            self.start = front_end;
+        }
+    }
+
+    /// Drops the entries that end at or before `position`, as they were finalized.
+    pub fn discard_before(&mut self, position: usize) {
+        while let Some((front_end, _)) = self.entries.front() {
+            if *front_end > position {
+                break;
+            }
+            self.start = *front_end;
+            self.entries.pop_front();
+        }
+    }
+
+    /// Drops everything, e.g. once a connection was finished cleanly at `position`.
+    pub fn clear(&mut self, position: usize) {
+        self.entries.clear();
+        self.start = position;
+    }
+
+    /// Position of the first entry to replay.
+    #[must_use]
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().map(|(_, entry)| entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_gives_up() {
+        let mut backoff = Backoff::new();
+        let delays: Vec<_> = std::iter::from_fn(|| backoff.next_delay()).collect();
+        assert_eq!(delays, [0, 250, 500, 1000, 2000].map(Duration::from_millis));
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn replay_buffer_discards_finalized() {
+        let mut buffer = ReplayBuffer::new(1000);
+        for chunk in 1..=5 {
+            buffer.push(chunk * 1600, chunk);
+        }
+        buffer.discard_before(3200);
+        assert_eq!(buffer.start(), 3200);
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), [3, 4, 5]);
+
+        // Positions in the middle of an entry keep it.
+        buffer.discard_before(5000);
+        assert_eq!(buffer.start(), 4800);
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), [4, 5]);
+    }
+
+    #[test]
+    fn replay_buffer_is_capped() {
+        let mut buffer = ReplayBuffer::new(200);
+        for chunk in 1..=5 {
+            buffer.push(chunk * 1600, chunk);
+        }
+        assert_eq!(buffer.start(), 4800);
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), [4, 5]);
+    }
+}
@@ -0,0 +1,442 @@
+//! Sessions with Aliyun's duplex models, e.g. Paraformer, Fun-ASR or Gummy. They share the
+//! `run-task` protocol, and differ in the `parameters` of a task and the results it sends.
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use futures_util::{Sink, Stream, StreamExt};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio_tungstenite::tungstenite::{Bytes, Message};
+use tracing::{error, info, trace, warn};
+
+use crate::api_key::{ApiKey, ApiKeyError, DashscopeApiKey};
+use crate::asr_client::AsrClient;
+use crate::audio_stream::AudioStream;
+use crate::grpc_server::TranscribeResponse;
+use crate::prepared_connection::PreparedConnection;
+use crate::reconnect::ReplayBuffer;
+use crate::traffic_log::{Recorded, TrafficLog};
+use crate::transcribe_stream::TranscribeStream;
+use crate::vad::{ms_to_samples, samples_to_ms};
+use crate::websocket_session::{self, Output, Protocol, SessionError, WebSocket};
+
+mod types;
+
+pub use types::SentenceOutput;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RunTaskError {
+    #[error("websocket error: {0}")]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("audio error: {0}")]
+    Audio(#[from] io::Error),
+
+    /// A header value could not be encoded using HTTP header rules.
+    #[error("invalid header value for `{0}`")]
+    InvalidHeaderValue(&'static str),
+
+    /// The API key could not be read from its configured source.
+    #[error("api key error: {0}")]
+    ApiKey(#[from] ApiKeyError),
+
+    /// The service failed the task, e.g. for an invalid API key or unsupported audio.
+    #[error("task failed ({code}): {message}")]
+    TaskFailed { code: String, message: String },
+
+    #[error("connection error")]
+    Connection,
+
+    #[error("connection closed: {0}")]
+    Closed(String),
+
+    #[error("invalid proxy `{0}`")]
+    InvalidProxy(String),
+
+    /// The proxy could not be reached or refused the tunnel.
+    #[error("proxy error: {0}")]
+    Proxy(String),
+}
+
+impl From<SessionError> for RunTaskError {
+    fn from(error: SessionError) -> Self {
+        match error {
+            SessionError::WebSocket(error) => Self::WebSocket(error),
+            SessionError::InvalidHeaderValue(header) => Self::InvalidHeaderValue(header),
+            SessionError::Connection => Self::Connection,
+            SessionError::Closed(reason) => Self::Closed(reason),
+            SessionError::InvalidProxy(proxy) => Self::InvalidProxy(proxy),
+            SessionError::Proxy(error) => Self::Proxy(error),
+        }
+    }
+}
+
+/// A model run as `run-task` tasks. It is implemented by the model's config.
+pub trait Task: fmt::Debug + Clone + Send + Sync + 'static {
+    /// `model` of the `run-task` request.
+    const MODEL: &'static str;
+    /// Names the traffic logs of its sessions.
+    const NAME: &'static str;
+
+    /// The `parameters` of a task, besides the audio format.
+    type Parameters: Serialize;
+    /// The `output` of a result.
+    type Output: DeserializeOwned;
+    /// What is kept between the results of a task, e.g. to merge results that arrive apart.
+    type Results: Default + Send;
+    /// The backend's error, which `RunTaskError`s are reported as.
+    type Error: From<RunTaskError> + std::error::Error + Send + Sync + 'static;
+
+    fn api_key(&self) -> &DashscopeApiKey;
+
+    fn websocket_url(&self) -> &str;
+
+    fn proxy(&self) -> Option<&str>;
+
+    fn traffic_log_dir(&self) -> Option<&str>;
+
+    fn max_task_duration_secs(&self) -> u32;
+
+    fn parameters(&self) -> Self::Parameters;
+
+    /// The sentences a result starts, updates or ends.
+    fn results(&self, results: &mut Self::Results, output: Self::Output) -> Vec<Sentence>;
+
+    /// The sentences `results` holds back when the task finishes.
+    fn finish(&self, _results: &mut Self::Results) -> Vec<Sentence> {
+        Vec::new()
+    }
+}
+
+/// A result as it is emitted, with times relative to the start of its task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sentence {
+    pub response: TranscribeResponse,
+    /// Where a final sentence ends. The audio before it is not replayed after a reconnect.
+    pub end_time: Option<u32>,
+}
+
+impl From<SentenceOutput> for Sentence {
+    fn from(output: SentenceOutput) -> Self {
+        let sentence = output.sentence;
+        Self {
+            end_time: sentence.end_time.filter(|_| sentence.sentence_end),
+            response: TranscribeResponse {
+                text: sentence.text,
+                begin_time: sentence.begin_time,
+                sentence_end: sentence.sentence_end,
+                translation: None,
+            },
+        }
+    }
+}
+
+#[allow(clippy::enum_variant_names)]
+enum Stage {
+    AwaitTaskStarted,
+    AwaitResultGenerated,
+    AwaitTaskFinished,
+}
+
+/// Unfinished audio kept for replay after a reconnect.
+const MAX_REPLAY_MS: u32 = 60_000;
+
+/// How long a sentence may hold back a due rollover before the task is finished anyway.
+const ROLLOVER_GRACE_MS: u32 = 30_000;
+
+async fn connect<T: Task>(config: &T, api_key: &ApiKey) -> Result<WebSocket, RunTaskError> {
+    Ok(
+        websocket_session::connect(config.websocket_url(), api_key.expose(), config.proxy())
+            .await?,
+    )
+}
+
+/// A log for the traffic of a new session, if `traffic_log_dir` is set.
+fn traffic_log<T: Task>(config: &T, api_key: &ApiKey) -> Option<Arc<TrafficLog>> {
+    let dir = config.traffic_log_dir()?;
+    match TrafficLog::create(dir, T::NAME, [api_key.expose().to_string()]) {
+        Ok(log) => {
+            info!("recording websocket traffic to {}", log.path().display());
+            Some(Arc::new(log))
+        }
+        Err(e) => {
+            error!("failed to create traffic log in {dir}: {e}");
+            None
+        }
+    }
+}
+
+/// Runs the session as one task per connection. A task is finished and replaced by a new one
+/// when it nears `max_task_duration_secs`. When the connection is lost instead, the audio since
+/// the last final sentence is sent again to the next task. `begin_time` of later tasks is
+/// rebased onto the session timeline.
+struct Transcription<T: Task> {
+    config: T,
+    max_task_duration_ms: u32,
+    stage: Stage,
+    task_id: String,
+    results: T::Results,
+    // Audio sent in the whole session, and where the current task started in it.
+    sent_samples: usize,
+    task_start_samples: usize,
+    task_offset: u32,
+    replay: ReplayBuffer<Bytes>,
+    /// Set when the connection was lost and the buffered audio has to be sent again.
+    replaying: bool,
+    audio_ended: bool,
+    /// The last result of a sentence that has not ended yet.
+    partial: Option<TranscribeResponse>,
+}
+
+impl<T: Task> Transcription<T> {
+    fn new(config: T) -> Self {
+        Self {
+            max_task_duration_ms: config.max_task_duration_secs().saturating_mul(1000),
+            config,
+            stage: Stage::AwaitTaskStarted,
+            task_id: String::new(),
+            results: T::Results::default(),
+            sent_samples: 0,
+            task_start_samples: 0,
+            task_offset: 0,
+            replay: ReplayBuffer::new(MAX_REPLAY_MS),
+            replaying: false,
+            audio_ended: false,
+            partial: None,
+        }
+    }
+
+    fn finish(&mut self, out: &mut Output<RunTaskError>) {
+        out.send(&types::finish_task::request::Request::new(&self.task_id));
+        self.stage = Stage::AwaitTaskFinished;
+    }
+
+    fn emit(&mut self, sentences: Vec<Sentence>, out: &mut Output<RunTaskError>) {
+        for Sentence {
+            mut response,
+            end_time,
+        } in sentences
+        {
+            response.begin_time = response.begin_time.saturating_add(self.task_offset);
+            self.partial = (!response.sentence_end).then(|| response.clone());
+            if let Some(end_time) = end_time {
+                self.replay
+                    .discard_before(ms_to_samples(self.task_offset.saturating_add(end_time)));
+            }
+            out.emit(Ok(response));
+        }
+    }
+}
+
+impl<T: Task> Protocol for Transcription<T> {
+    type Event = types::ServerEvent<T::Output>;
+    type Error = RunTaskError;
+
+    fn open(&mut self, out: &mut Output<Self::Error>) {
+        self.stage = Stage::AwaitTaskStarted;
+        self.partial = None;
+        self.results = T::Results::default();
+        self.task_offset = samples_to_ms(self.task_start_samples);
+        let run_task_req =
+            types::run_task::request::Request::new(T::MODEL, self.config.parameters());
+        self.task_id.clone_from(&run_task_req.header.task_id);
+        out.send(&run_task_req);
+    }
+
+    fn wants_audio(&self) -> bool {
+        matches!(self.stage, Stage::AwaitResultGenerated)
+    }
+
+    fn audio(&mut self, chunk: Option<io::Result<Bytes>>, out: &mut Output<Self::Error>) {
+        match chunk {
+            Some(Ok(chunk)) => {
+                self.sent_samples += chunk.len() / 2;
+                self.replay.push(self.sent_samples, chunk.clone());
+                out.send_binary(chunk);
+                let task_ms = samples_to_ms(self.sent_samples - self.task_start_samples);
+                let max_ms = self.max_task_duration_ms;
+                if task_ms >= max_ms
+                    && (self.partial.is_none()
+                        || task_ms >= max_ms.saturating_add(ROLLOVER_GRACE_MS))
+                {
+                    info!("rolling over task {}.", self.task_id);
+                    out.rolling_over();
+                    self.finish(out);
+                }
+            }
+            Some(Err(err)) => out.emit(Err(RunTaskError::Audio(err))),
+            None => {
+                self.audio_ended = true;
+                self.finish(out);
+            }
+        }
+    }
+
+    fn event(&mut self, event: Self::Event, out: &mut Output<Self::Error>) {
+        match event {
+            types::ServerEvent::TaskFailed(response) => {
+                error!("TaskFailed {response:?}");
+                out.fail(RunTaskError::TaskFailed {
+                    code: response.header.error_code,
+                    message: response.header.error_message,
+                });
+            }
+            types::ServerEvent::TaskStarted(response) => {
+                trace!("TaskStarted {response:?}");
+                out.started();
+                self.stage = Stage::AwaitResultGenerated;
+                if std::mem::take(&mut self.replaying) {
+                    info!(
+                        "replaying {}ms of audio.",
+                        samples_to_ms(self.sent_samples - self.task_start_samples)
+                    );
+                    for chunk in self.replay.iter() {
+                        out.send_binary(chunk.clone());
+                    }
+                    if self.audio_ended {
+                        self.finish(out);
+                    }
+                }
+            }
+            types::ServerEvent::ResultGenerated(response) => {
+                let sentences = self
+                    .config
+                    .results(&mut self.results, response.payload.output);
+                self.emit(sentences, out);
+            }
+            types::ServerEvent::TaskFinished(response) => {
+                info!("TaskFinished {response:?}");
+                let sentences = self.config.finish(&mut self.results);
+                self.emit(sentences, out);
+                if !matches!(self.stage, Stage::AwaitTaskFinished) {
+                    // Finished before we asked for it; carry on in a new task.
+                    warn!("task {} finished early, starting a new one.", self.task_id);
+                    out.rolling_over();
+                    self.stage = Stage::AwaitTaskFinished;
+                }
+                out.finished();
+            }
+        }
+    }
+
+    fn rolled_over(&mut self) {
+        info!("task {} rolled over, starting a new one.", self.task_id);
+        self.replay.clear(self.sent_samples);
+        self.task_start_samples = self.sent_samples;
+    }
+
+    fn lost(&mut self, out: &mut Output<Self::Error>) {
+        if let Some(partial) = self.partial.take() {
+            // The sentence is recognized again from the replayed audio, and may change.
+            out.emit(Ok(TranscribeResponse {
+                text: String::new(),
+                begin_time: partial.begin_time,
+                sentence_end: false,
+                translation: None,
+            }));
+        }
+        self.task_start_samples = self.replay.start();
+        self.replaying = true;
+    }
+}
+
+/// Runs a session on `web_socket_stream`, reconnecting with `connect`.
+pub fn transcribe<T, C, F, W>(
+    connect: C,
+    web_socket_stream: W,
+    audio_stream: AudioStream,
+    config: T,
+) -> impl Stream<Item = Result<TranscribeResponse, RunTaskError>>
+where
+    T: Task,
+    C: FnMut() -> F,
+    F: Future<Output = Result<W, RunTaskError>>,
+    W: Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Sink<Message>,
+{
+    websocket_session::drive(
+        Transcription::new(config),
+        connect,
+        web_socket_stream,
+        audio_stream,
+    )
+}
+
+/// A client of the model configured by `T`.
+#[derive(Debug)]
+pub struct RunTaskClient<T> {
+    config: T,
+    prepared: PreparedConnection<WebSocket>,
+}
+
+#[async_trait::async_trait]
+impl<T: Task> AsrClient for RunTaskClient<T> {
+    type Config = T;
+    type TranscriptionStream = TranscribeStream<anyhow::Error>;
+
+    fn new(config: impl Into<Self::Config>) -> Self {
+        Self {
+            config: config.into(),
+            prepared: PreparedConnection::new(),
+        }
+    }
+
+    async fn create(
+        &self,
+        audio_stream: AudioStream,
+    ) -> Result<Self::TranscriptionStream, anyhow::Error> {
+        let config = self.config.clone();
+        let api_key = config
+            .api_key()
+            .resolve()
+            .await
+            .map_err(|error| T::Error::from(error.into()))?;
+        let ws_stream = match self.prepared.take() {
+            Some(ws_stream) => ws_stream,
+            None => connect(&config, &api_key).await.map_err(T::Error::from)?,
+        };
+
+        let log = traffic_log(&config, &api_key);
+        let ws_stream = Recorded::new(ws_stream, config.websocket_url(), log.clone());
+
+        let connect_config = config.clone();
+        let transcribe_stream = transcribe(
+            move || {
+                let config = connect_config.clone();
+                let api_key = api_key.clone();
+                let log = log.clone();
+                async move {
+                    let ws_stream = connect(&config, &api_key).await?;
+                    Ok(Recorded::new(ws_stream, config.websocket_url(), log))
+                }
+            },
+            ws_stream,
+            audio_stream,
+            config,
+        )
+        .map(|item| item.map_err(|error| anyhow::Error::from(T::Error::from(error))));
+
+        Ok(TranscribeStream::new(Box::pin(transcribe_stream)))
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        if !self.prepared.is_fresh() {
+            let api_key = self
+                .config
+                .api_key()
+                .resolve()
+                .await
+                .map_err(|error| T::Error::from(error.into()))?;
+            self.prepared.put(
+                connect(&self.config, &api_key)
+                    .await
+                    .map_err(T::Error::from)?,
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,386 @@
+//! The `run-task` protocol of Aliyun's duplex models. The `parameters` of a task and the
+//! `output` of its results differ by model, and are type parameters here.
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct EmptyObj {}
+
+#[derive(Debug, serde::Serialize)]
+pub enum Streaming {
+    #[serde(rename = "duplex")]
+    Duplex,
+}
+
+#[allow(dead_code)]
+pub mod run_task {
+    pub mod request {
+        use super::super::{EmptyObj, Streaming};
+        use uuid::Uuid;
+
+        #[derive(Debug, serde::Serialize)]
+        pub struct RequestHeader {
+            action: &'static str,
+            pub task_id: String,
+            streaming: Streaming,
+        }
+
+        #[derive(Debug, serde::Serialize)]
+        enum Format {
+            #[serde(rename = "pcm")]
+            Pcm,
+        }
+
+        #[derive(Debug, serde::Serialize)]
+        struct RequestPayloadParameters<P> {
+            format: Format,
+            sample_rate: u32,
+            #[serde(flatten)]
+            model: P,
+        }
+
+        #[derive(Debug, serde::Serialize)]
+        struct RequestPayload<P> {
+            task_group: &'static str,
+            task: &'static str,
+            function: &'static str,
+            model: &'static str,
+            parameters: RequestPayloadParameters<P>,
+            input: EmptyObj,
+        }
+
+        #[derive(Debug, serde::Serialize)]
+        pub struct Request<P> {
+            pub header: RequestHeader,
+            payload: RequestPayload<P>,
+        }
+
+        impl<P> Request<P> {
+            #[must_use]
+            pub fn new(model: &'static str, parameters: P) -> Self {
+                Self {
+                    header: RequestHeader {
+                        action: "run-task",
+                        task_id: Uuid::new_v4().into(),
+                        streaming: Streaming::Duplex,
+                    },
+                    payload: RequestPayload {
+                        task_group: "audio",
+                        task: "asr",
+                        function: "recognition",
+                        model,
+                        parameters: RequestPayloadParameters {
+                            format: Format::Pcm,
+                            sample_rate: 16000,
+                            model: parameters,
+                        },
+                        input: EmptyObj::default(),
+                    },
+                }
+            }
+        }
+    }
+
+    pub mod response {
+        use super::super::EmptyObj;
+
+        #[derive(Debug, serde::Deserialize)]
+        enum Event {
+            #[serde(rename = "task-started")]
+            TaskStarted,
+        }
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Header {
+            task_id: String,
+            event: Event,
+            attributes: EmptyObj,
+        }
+
+        #[derive(Debug, serde::Deserialize)]
+        pub struct Response {
+            header: Header,
+            payload: EmptyObj,
+        }
+    }
+}
+
+#[allow(dead_code)]
+pub mod result_generated {
+    use super::EmptyObj;
+
+    #[derive(Debug, serde::Deserialize)]
+    pub enum Event {
+        #[serde(rename = "result-generated")]
+        ResultGenerated,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    pub struct Header {
+        pub task_id: String,
+        pub event: Event,
+        attributes: EmptyObj,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    pub struct Response<O> {
+        pub header: Header,
+        pub payload: Payload<O>,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    pub struct Usage {
+        pub duration: u32,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    pub struct Payload<O> {
+        pub output: O,
+        pub usage: Option<Usage>,
+    }
+}
+
+#[allow(dead_code)]
+pub mod finish_task {
+    pub mod request {
+        use super::super::{EmptyObj, Streaming};
+
+        #[derive(Debug, Default, serde::Serialize)]
+        struct Payload {
+            pub input: EmptyObj,
+        }
+
+        #[derive(Debug, serde::Serialize)]
+        struct Header {
+            pub action: String,
+            pub task_id: String,
+            pub streaming: Streaming,
+        }
+
+        impl Header {
+            pub fn new(task_id: &str) -> Self {
+                Self {
+                    action: "finish-task".to_string(),
+                    task_id: task_id.to_string(),
+                    streaming: Streaming::Duplex,
+                }
+            }
+        }
+
+        #[derive(Debug, serde::Serialize)]
+        pub struct Request {
+            header: Header,
+            payload: Payload,
+        }
+
+        impl Request {
+            #[must_use]
+            pub fn new(task_id: &str) -> Self {
+                Self {
+                    header: Header::new(task_id),
+                    payload: Payload::default(),
+                }
+            }
+        }
+    }
+
+    pub mod response {
+        use super::super::EmptyObj;
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize)]
+        struct Payload {
+            output: EmptyObj,
+            usage: Option<EmptyObj>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        enum Event {
+            #[serde(rename = "task-finished")]
+            TaskFinished,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Header {
+            task_id: String,
+            event: Event,
+            attributes: EmptyObj,
+        }
+
+        #[derive(Debug, Deserialize)]
+        pub struct Response {
+            header: Header,
+            payload: Payload,
+        }
+    }
+}
+
+#[allow(dead_code)]
+pub mod task_failed {
+    use super::EmptyObj;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    enum Event {
+        #[serde(rename = "task-failed")]
+        TaskFailed,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Header {
+        task_id: String,
+        event: Event,
+        pub error_code: String,
+        pub error_message: String,
+        attributes: EmptyObj,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Response {
+        pub header: Header,
+        payload: EmptyObj,
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+pub enum ServerEvent<O> {
+    TaskStarted(run_task::response::Response),
+    ResultGenerated(result_generated::Response<O>),
+    TaskFinished(finish_task::response::Response),
+    TaskFailed(task_failed::Response),
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ParaformerWord {
+    pub begin_time: u32,
+    pub end_time: u32,
+    pub text: String,
+    pub punctuation: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ParaformerSentence {
+    pub begin_time: u32,
+    pub end_time: Option<u32>,
+    pub text: String,
+    pub heartbeat: Option<bool>,
+    pub sentence_end: bool,
+    pub words: Vec<ParaformerWord>,
+}
+
+/// The `output` of a result: one sentence, as Paraformer and Fun-ASR send it.
+#[derive(Debug, serde::Deserialize)]
+pub struct SentenceOutput {
+    pub sentence: ParaformerSentence,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SentenceOutput;
+    use super::result_generated::Response;
+
+    #[test]
+    fn result_generated_deserialize() {
+        let data = r#"
+    {
+      "header": {
+        "task_id": "2bf83b9a-baeb-4fda-8d9a-xxxxxxxxxxxx",
+        "event": "result-generated",
+        "attributes": {}
+      },
+      "payload": {
+        "output": {
+          "sentence": {
+            "begin_time": 170,
+            "end_time": null,
+            "text": "好，我知道了",
+            "heartbeat": false,
+            "sentence_end": true,
+            "emo_tag": "neutral",
+            "emo_confidence": 0.914,
+            "words": [
+              {
+                "begin_time": 170,
+                "end_time": 295,
+                "text": "好",
+                "punctuation": "，"
+              },
+              {
+                "begin_time": 295,
+                "end_time": 503,
+                "text": "我",
+                "punctuation": ""
+              },
+              {
+                "begin_time": 503,
+                "end_time": 711,
+                "text": "知道",
+                "punctuation": ""
+              },
+              {
+                "begin_time": 711,
+                "end_time": 920,
+                "text": "了",
+                "punctuation": ""
+              }
+            ]
+          }
+        },
+        "usage": {
+          "duration": 3
+        }
+      }
+    }
+    "#;
+        let _: Response<SentenceOutput> = serde_json::from_str(data).unwrap();
+    }
+
+    #[test]
+    fn result_generated_deserialize2() {
+        let data = r#"
+            {"header":{"task_id":"239a50e2-65aa-4c66-bcf1-741c15e7021b","event":"result-generated","attributes":{}},"payload":{"output":{"sentence":{"sentence_id":1,"begin_time":1940,"end_time":null,"text":"","channel_id":0,"speaker_id":null,"sentence_end":false,"sentence_begin":true,"words":[]}}}}
+            "#;
+
+        let _: Response<SentenceOutput> = serde_json::from_str(data).unwrap();
+    }
+
+    #[test]
+    fn fun_asr_result_generated_deserialize() {
+        let data = r#"
+    {
+      "header": {
+        "task_id": "2bf83b9a-baeb-4fda-8d9a-xxxxxxxxxxxx",
+        "event": "result-generated",
+        "attributes": {}
+      },
+      "payload": {
+        "output": {
+          "sentence": {
+            "sentence_id": 1,
+            "begin_time": 170,
+            "end_time": 920,
+            "text": "我用 Dictype 打字。",
+            "channel_id": 0,
+            "speaker_id": null,
+            "sentence_end": true,
+            "words": [
+              {
+                "begin_time": 170,
+                "end_time": 920,
+                "text": "我用 Dictype 打字",
+                "punctuation": "",
+                "fixed": false,
+                "speaker_id": null
+              }
+            ]
+          }
+        },
+        "usage": {
+          "duration": 1
+        }
+      }
+    }
+    "#;
+        let response: Response<SentenceOutput> = serde_json::from_str(data).unwrap();
+        assert_eq!(response.payload.output.sentence.end_time, Some(920));
+    }
+}
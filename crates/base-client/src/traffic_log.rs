@@ -0,0 +1,486 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, mpsc};
+use std::task::{Context, Poll, Waker, ready};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures_util::{Sink, Stream};
+use pin_project_lite::pin_project;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::{Error as WsError, Message};
+
+/// One line of a traffic log.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Entry {
+    /// A new connection to `url`.
+    Connect {
+        url: String,
+    },
+    Sent(Frame),
+    Received(Frame),
+    /// The connection failed, e.g. it was reset.
+    Error(String),
+}
+
+/// A websocket frame. Pings and pongs are left out.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Frame {
+    Text(String),
+    /// Base64 of the payload.
+    Binary(String),
+    Close(Option<Closing>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Closing {
+    pub code: u16,
+    pub reason: String,
+}
+
+impl Frame {
+    fn from_message(message: &Message) -> Option<Self> {
+        match message {
+            Message::Text(text) => Some(Self::Text(text.to_string())),
+            Message::Binary(data) => Some(Self::Binary(STANDARD.encode(data))),
+            Message::Close(frame) => Some(Self::Close(frame.as_ref().map(|frame| Closing {
+                code: frame.code.into(),
+                reason: frame.reason.to_string(),
+            }))),
+            Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => None,
+        }
+    }
+
+    fn into_message(self) -> io::Result<Message> {
+        Ok(match self {
+            Self::Text(text) => Message::Text(text.into()),
+            Self::Binary(data) => Message::Binary(
+                STANDARD
+                    .decode(data)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                    .into(),
+            ),
+            Self::Close(closing) => Message::Close(closing.map(|closing| CloseFrame {
+                code: closing.code.into(),
+                reason: closing.reason.into(),
+            })),
+        })
+    }
+}
+
+/// Writes the websocket traffic of one session to a JSONL file, for debugging.
+///
+/// Secrets such as API keys are redacted wherever they appear, and handshake headers such as
+/// `Authorization` are not recorded. The file is only readable by its owner.
+///
+/// Lines are written by a thread of their own, so that the session never waits on the file.
+/// Dropping the log waits for the lines queued so far.
+#[derive(Debug)]
+pub struct TrafficLog {
+    path: PathBuf,
+    lines: Option<mpsc::Sender<String>>,
+    writer: Option<thread::JoinHandle<()>>,
+    secrets: Vec<String>,
+}
+
+impl TrafficLog {
+    const REDACTED: &str = "<redacted>";
+
+    /// Creates `{prefix}-{unix time in ms}.jsonl` in `dir`.
+    pub fn create(
+        dir: impl AsRef<Path>,
+        prefix: &str,
+        secrets: impl IntoIterator<Item = String>,
+    ) -> io::Result<Self> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.as_ref().join(format!("{prefix}-{millis}.jsonl"));
+        let mut file = File::options()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(&path)?;
+        let (lines, queued) = mpsc::channel::<String>();
+        let writer = thread::Builder::new()
+            .name("traffic-log".to_string())
+            .spawn(move || {
+                // Logging is best effort and never fails the session.
+                for line in queued {
+                    let _ = file.write_all(line.as_bytes());
+                }
+            })?;
+        Ok(Self {
+            path,
+            lines: Some(lines),
+            writer: Some(writer),
+            secrets: secrets.into_iter().filter(|s| !s.is_empty()).collect(),
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret, Self::REDACTED)
+        })
+    }
+
+    fn write(&self, entry: Entry) {
+        let entry = match entry {
+            Entry::Connect { url } => Entry::Connect {
+                url: self.redact(&url),
+            },
+            Entry::Sent(Frame::Text(text)) => Entry::Sent(Frame::Text(self.redact(&text))),
+            Entry::Received(Frame::Text(text)) => Entry::Received(Frame::Text(self.redact(&text))),
+            Entry::Error(error) => Entry::Error(self.redact(&error)),
+            entry => entry,
+        };
+        let Ok(mut line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        line.push('\n');
+        if let Some(lines) = &self.lines {
+            let _ = lines.send(line);
+        }
+    }
+}
+
+impl Drop for TrafficLog {
+    fn drop(&mut self) {
+        drop(self.lines.take());
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+pin_project! {
+    /// A websocket whose traffic is written to a `TrafficLog`, if there is one.
+    #[derive(Debug)]
+    pub struct Recorded<W> {
+        #[pin]
+        inner: W,
+        log: Option<Arc<TrafficLog>>,
+    }
+}
+
+impl<W> Recorded<W> {
+    /// Wraps a connection to `url`.
+    pub fn new(inner: W, url: &str, log: Option<Arc<TrafficLog>>) -> Self {
+        if let Some(log) = &log {
+            log.write(Entry::Connect {
+                url: url.to_string(),
+            });
+        }
+        Self { inner, log }
+    }
+}
+
+impl<W> Stream for Recorded<W>
+where
+    W: Stream<Item = Result<Message, WsError>>,
+{
+    type Item = W::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = ready!(this.inner.poll_next(cx));
+        if let Some(log) = this.log {
+            match &item {
+                Some(Ok(message)) => {
+                    if let Some(frame) = Frame::from_message(message) {
+                        log.write(Entry::Received(frame));
+                    }
+                }
+                // Replayed as I/O errors.
+                Some(Err(WsError::Io(error))) => log.write(Entry::Error(error.to_string())),
+                Some(Err(error)) => log.write(Entry::Error(error.to_string())),
+                None => {}
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl<W> Sink<Message> for Recorded<W>
+where
+    W: Sink<Message>,
+{
+    type Error = W::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        let this = self.project();
+        if let Some(log) = this.log
+            && let Some(frame) = Frame::from_message(&item)
+        {
+            log.write(Entry::Sent(frame));
+        }
+        this.inner.start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_close(cx)
+    }
+}
+
+/// The connections of a recorded session, to be played back in order.
+#[derive(Debug)]
+pub struct Replay {
+    connections: VecDeque<ReplayConnection>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(jsonl: &str) -> io::Result<Self> {
+        let mut connections = VecDeque::new();
+        for line in jsonl.lines().filter(|line| !line.trim().is_empty()) {
+            let entry: Entry = serde_json::from_str(line)?;
+            if matches!(entry, Entry::Connect { .. }) {
+                connections.push_back(ReplayConnection::default());
+                continue;
+            }
+            let Some(connection) = connections.back_mut() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "traffic before the first connection",
+                ));
+            };
+            match entry {
+                Entry::Connect { .. } => {}
+                Entry::Sent(_) => connection.recorded_sent += 1,
+                Entry::Received(frame) => {
+                    let message = Ok(frame.into_message()?);
+                    connection
+                        .received
+                        .push_back((connection.recorded_sent, message));
+                }
+                Entry::Error(error) => connection
+                    .received
+                    .push_back((connection.recorded_sent, Err(error))),
+            }
+        }
+        Ok(Self { connections })
+    }
+
+    /// The next recorded connection, or `None` once all of them were used.
+    pub fn next_connection(&mut self) -> Option<ReplayConnection> {
+        self.connections.pop_front()
+    }
+}
+
+/// Plays back what the server sent on one connection.
+///
+/// Each frame is delivered once the client has sent as many frames as it had before that frame
+/// was received, or has closed. What the client sends is not checked.
+#[derive(Debug, Default)]
+pub struct ReplayConnection {
+    /// Frames with the number of frames sent before each.
+    received: VecDeque<(usize, Result<Message, String>)>,
+    recorded_sent: usize,
+    sent: usize,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl Stream for ReplayConnection {
+    type Item = Result<Message, WsError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.received.front() {
+            None => Poll::Ready(None),
+            Some((sent_before, _)) if *sent_before <= self.sent || self.closed => {
+                let (_, message) = self.received.pop_front().expect("checked above");
+                Poll::Ready(Some(
+                    message.map_err(|error| WsError::Io(io::Error::other(error))),
+                ))
+            }
+            Some(_) => {
+                self.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Sink<Message> for ReplayConnection {
+    type Error = WsError;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        if Frame::from_message(&item).is_some() {
+            self.sent += 1;
+        }
+        if item.is_close() {
+            self.closed = true;
+        }
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.closed = true;
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use futures_util::{SinkExt, StreamExt};
+
+    use super::*;
+
+    const LOG: &str = r#"
+{"connect":{"url":"wss://example.com"}}
+{"sent":{"text":"start"}}
+{"received":{"text":"started"}}
+{"sent":{"binary":"AAA="}}
+{"sent":{"binary":"AAA="}}
+{"received":{"text":"result"}}
+{"received":{"close":{"code":1000,"reason":"bye"}}}
+{"connect":{"url":"wss://example.com"}}
+{"error":"Connection reset by peer (os error 104)"}
+"#;
+
+    #[tokio::test]
+    async fn replay_waits_for_client_frames() {
+        let mut replay = Replay::parse(LOG).unwrap();
+        let mut connection = replay.next_connection().unwrap();
+
+        let next = |connection: &mut ReplayConnection| {
+            let mut cx = Context::from_waker(Waker::noop());
+            connection.poll_next_unpin(&mut cx)
+        };
+        assert!(next(&mut connection).is_pending());
+        connection.send(Message::text("start")).await.unwrap();
+        assert_eq!(
+            connection.next().await.unwrap().unwrap(),
+            Message::text("started")
+        );
+        connection
+            .send(Message::binary(vec![0u8; 2]))
+            .await
+            .unwrap();
+        assert!(next(&mut connection).is_pending());
+        // Pings are not counted.
+        connection
+            .send(Message::Ping(Vec::new().into()))
+            .await
+            .unwrap();
+        assert!(next(&mut connection).is_pending());
+        connection
+            .send(Message::binary(vec![0u8; 2]))
+            .await
+            .unwrap();
+        assert_eq!(
+            connection.next().await.unwrap().unwrap(),
+            Message::text("result")
+        );
+        assert!(matches!(
+            connection.next().await,
+            Some(Ok(Message::Close(Some(frame)))) if frame.reason == "bye"
+        ));
+        assert!(connection.next().await.is_none());
+
+        let mut connection = replay.next_connection().unwrap();
+        assert!(connection.next().await.unwrap().is_err());
+        assert!(replay.next_connection().is_none());
+    }
+
+    #[tokio::test]
+    async fn recording_a_replay_gives_the_same_log() {
+        let dir = std::env::temp_dir().join(format!("traffic-log-test-{}", std::process::id()));
+        let log = Arc::new(TrafficLog::create(&dir, "test", ["secret-key".to_string()]).unwrap());
+        let path = log.path().to_path_buf();
+
+        let mut replay = Replay::parse(LOG).unwrap();
+        let mut first = Recorded::new(
+            replay.next_connection().unwrap(),
+            "wss://example.com",
+            Some(log.clone()),
+        );
+        first.send(Message::text("start")).await.unwrap();
+        first.next().await.unwrap().unwrap();
+        first.send(Message::binary(vec![0u8; 2])).await.unwrap();
+        first.send(Message::binary(vec![0u8; 2])).await.unwrap();
+        while first.next().await.is_some() {}
+        drop(first);
+        let mut second = Recorded::new(
+            replay.next_connection().unwrap(),
+            "wss://example.com",
+            Some(log.clone()),
+        );
+        while second.next().await.is_some() {}
+        drop(second);
+
+        // Secrets are redacted.
+        let mut replay =
+            Replay::parse("{\"connect\":{\"url\":\"\"}}\n{\"error\":\"401: Bearer secret-key\"}")
+                .unwrap();
+        let mut recorded = Recorded::new(
+            replay.next_connection().unwrap(),
+            "wss://example.com/?key=secret-key",
+            Some(log),
+        );
+        recorded
+            .send(Message::text("auth secret-key"))
+            .await
+            .unwrap();
+        while recorded.next().await.is_some() {}
+        // The last reference to the log, so dropping it waits for the writes.
+        drop(recorded);
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let expected = [
+            LOG.trim(),
+            r#"{"connect":{"url":"wss://example.com/?key=<redacted>"}}"#,
+            r#"{"sent":{"text":"auth <redacted>"}}"#,
+            r#"{"error":"401: Bearer <redacted>"}"#,
+        ];
+        assert_eq!(written.trim(), expected.join("\n"));
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
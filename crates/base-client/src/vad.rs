@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+
+const SAMPLE_RATE: u32 = 16_000;
+const FRAME_MILLIS: u32 = 30;
+const FRAME_SAMPLES: usize = (SAMPLE_RATE * FRAME_MILLIS / 1000) as usize;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VadConfig {
+    /// RMS level in `0.0..=1.0` above which a frame counts as speech.
+    pub threshold: f32,
+    /// Silence that ends an utterance.
+    pub silence_duration_ms: u32,
+    /// Audio kept before the first speech frame, so onsets are not clipped.
+    pub prefix_padding_ms: u32,
+    /// Utterances with less speech than this are dropped.
+    pub min_speech_duration_ms: u32,
+    /// Utterances are cut when they grow longer than this.
+    pub max_segment_duration_ms: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.01,
+            silence_duration_ms: 800,
+            prefix_padding_ms: 300,
+            min_speech_duration_ms: 250,
+            max_segment_duration_ms: 30_000,
+        }
+    }
+}
+
+/// An utterance cut out of the audio stream, as 16 kHz mono PCM.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Segment {
+    /// Offset of the first sample from the start of the stream.
+    pub begin_time: u32,
+    pub samples: Vec<i16>,
+}
+
+impl Segment {
+    #[must_use]
+    pub fn duration_ms(&self) -> u32 {
+        u32::try_from(self.samples.len() * 1000 / SAMPLE_RATE as usize).unwrap_or(u32::MAX)
+    }
+}
+
+/// Energy based voice activity detector that cuts 16 kHz mono S16LE PCM into utterances.
+#[derive(Debug)]
+pub struct VoiceActivityDetector {
+    config: VadConfig,
+    /// Bytes and samples not yet forming a whole frame.
+    pending_byte: Option<u8>,
+    pending_samples: Vec<i16>,
+    /// Recent silent frames, used as prefix padding.
+    history: Vec<i16>,
+    /// Number of samples consumed so far, including `pending_samples`.
+    position: usize,
+    current: Option<Segment>,
+    speech_ms: u32,
+    silence_ms: u32,
+}
+
+impl VoiceActivityDetector {
+    #[must_use]
+    pub const fn new(config: VadConfig) -> Self {
+        Self {
+            config,
+            pending_byte: None,
+            pending_samples: Vec::new(),
+            history: Vec::new(),
+            position: 0,
+            current: None,
+            speech_ms: 0,
+            silence_ms: 0,
+        }
+    }
+
+    /// Feeds S16LE PCM and returns the utterances that ended within it.
+    pub fn push(&mut self, pcm: &[u8]) -> Vec<Segment> {
+        let mut bytes = pcm;
+        if let Some(low) = self.pending_byte.take() {
+            if let Some((&high, rest)) = bytes.split_first() {
+                self.pending_samples.push(i16::from_le_bytes([low, high]));
+                bytes = rest;
+            } else {
+                self.pending_byte = Some(low);
+            }
+        }
+        let mut chunks = bytes.chunks_exact(2);
+        self.pending_samples
+            .extend(chunks.by_ref().map(|b| i16::from_le_bytes([b[0], b[1]])));
+        if let [low] = chunks.remainder() {
+            self.pending_byte = Some(*low);
+        }
+
+        let mut finished = Vec::new();
+        let mut offset = 0;
+        while self.pending_samples.len() - offset >= FRAME_SAMPLES {
+            let frame = self.pending_samples[offset..offset + FRAME_SAMPLES].to_vec();
+            offset += FRAME_SAMPLES;
+            if let Some(segment) = self.process_frame(&frame) {
+                finished.push(segment);
+            }
+        }
+        self.pending_samples.drain(..offset);
+
+        finished
+    }
+
+    /// The utterance still in progress, if speech has started.
+    #[must_use]
+    pub const fn current(&self) -> Option<&Segment> {
+        self.current.as_ref()
+    }
+
+    /// Ends the stream, returning the utterance in progress if it has enough speech.
+    pub fn finish(&mut self) -> Option<Segment> {
+        let pending = std::mem::take(&mut self.pending_samples);
+        if let Some(current) = self.current.as_mut() {
+            current.samples.extend_from_slice(&pending);
+        }
+        self.take_current()
+    }
+
+    fn process_frame(&mut self, frame: &[i16]) -> Option<Segment> {
+        let begin = self.position;
+        self.position += frame.len();
+        let is_speech = rms(frame) >= self.config.threshold;
+
+        match self.current.as_mut() {
+            None if is_speech => {
+                let mut samples = std::mem::take(&mut self.history);
+                let begin = begin - samples.len();
+                samples.extend_from_slice(frame);
+                self.current = Some(Segment {
+                    begin_time: samples_to_ms(begin),
+                    samples,
+                });
+                self.speech_ms = FRAME_MILLIS;
+                self.silence_ms = 0;
+                None
+            }
+            None => {
+                self.history.extend_from_slice(frame);
+                let keep = ms_to_samples(self.config.prefix_padding_ms);
+                if self.history.len() > keep {
+                    let excess = self.history.len() - keep;
+                    self.history.drain(..excess);
+                }
+                None
+            }
+            Some(current) => {
+                current.samples.extend_from_slice(frame);
+                if is_speech {
+                    self.speech_ms += FRAME_MILLIS;
+                    self.silence_ms = 0;
+                } else {
+                    self.silence_ms += FRAME_MILLIS;
+                }
+
+                let too_long = current.duration_ms() >= self.config.max_segment_duration_ms;
+                if self.silence_ms >= self.config.silence_duration_ms || too_long {
+                    self.take_current()
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn take_current(&mut self) -> Option<Segment> {
+        let segment = self.current.take()?;
+        let speech_ms = std::mem::take(&mut self.speech_ms);
+        self.silence_ms = 0;
+        (speech_ms >= self.config.min_speech_duration_ms).then_some(segment)
+    }
+}
+
+#[must_use]
+pub fn samples_to_ms(samples: usize) -> u32 {
+    u32::try_from(samples * 1000 / SAMPLE_RATE as usize).unwrap_or(u32::MAX)
+}
+
+#[must_use]
+pub const fn ms_to_samples(ms: u32) -> usize {
+    ms as usize * SAMPLE_RATE as usize / 1000
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn rms(frame: &[i16]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = frame
+        .iter()
+        .map(|&s| {
+            let s = f64::from(s) / f64::from(i16::MAX);
+            s * s
+        })
+        .sum();
+    #[allow(clippy::cast_possible_truncation)]
+    let rms = (sum / frame.len() as f64).sqrt() as f32;
+    rms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcm(ms: u32, amplitude: i16) -> Vec<u8> {
+        (0..ms_to_samples(ms))
+            .flat_map(|i| {
+                let sample = if i % 2 == 0 { amplitude } else { -amplitude };
+                sample.to_le_bytes()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn silence_produces_nothing() {
+        let mut vad = VoiceActivityDetector::new(VadConfig::default());
+        assert!(vad.push(&pcm(3000, 0)).is_empty());
+        assert!(vad.current().is_none());
+        assert!(vad.finish().is_none());
+    }
+
+    #[test]
+    fn speech_followed_by_silence_is_one_segment() {
+        let mut vad = VoiceActivityDetector::new(VadConfig::default());
+        assert!(vad.push(&pcm(990, 0)).is_empty());
+        assert!(vad.push(&pcm(990, 8000)).is_empty());
+        assert!(vad.current().is_some());
+
+        let segments = vad.push(&pcm(1200, 0));
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].begin_time, 990 - 300);
+        assert!(vad.current().is_none());
+    }
+
+    #[test]
+    fn odd_sized_chunks_are_reassembled() {
+        let mut vad = VoiceActivityDetector::new(VadConfig::default());
+        let audio = [pcm(600, 8000), pcm(1200, 0)].concat();
+        let segments: Vec<Segment> = audio.chunks(333).flat_map(|c| vad.push(c)).collect();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].begin_time, 0);
+    }
+
+    #[test]
+    fn short_blips_are_dropped() {
+        let mut vad = VoiceActivityDetector::new(VadConfig::default());
+        vad.push(&pcm(60, 8000));
+        assert!(vad.push(&pcm(1200, 0)).is_empty());
+    }
+
+    #[test]
+    fn long_speech_is_cut() {
+        let mut vad = VoiceActivityDetector::new(VadConfig {
+            max_segment_duration_ms: 1_000,
+            ..VadConfig::default()
+        });
+        let segments = vad.push(&pcm(2_400, 8000));
+        assert_eq!(segments.len(), 2);
+        assert!(vad.finish().is_some());
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::VecDeque;
+use std::io;
+
+use async_stream::stream;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use tokio::net::TcpStream;
+use tokio::select;
+use tokio::time::sleep;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::error::UrlError;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::http::header::AUTHORIZATION;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, client_async_tls, connect_async};
+use tokio_util::bytes::Bytes;
+use tracing::{error, info};
+
+use crate::audio_stream::AudioStream;
+use crate::grpc_server::TranscribeResponse;
+use crate::proxy::Proxy;
+use crate::reconnect::Backoff;
+
+pub type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("websocket error: {0}")]
+    WebSocket(#[from] WsError),
+
+    /// A header value could not be encoded using HTTP header rules.
+    #[error("invalid header value for `{0}`")]
+    InvalidHeaderValue(&'static str),
+
+    /// The connection was lost and could not be re-established.
+    #[error("connection error")]
+    Connection,
+
+    #[error("connection closed: {0}")]
+    Closed(String),
+
+    #[error("invalid proxy `{0}`")]
+    InvalidProxy(String),
+
+    /// The proxy could not be reached or refused the tunnel.
+    #[error("proxy error: {0}")]
+    Proxy(String),
+}
+
+/// Opens a websocket to `url`, authorized by `api_key` as a bearer token. It goes through `proxy`
+/// if given, else through the one configured in the environment, if any.
+pub async fn connect(
+    url: &str,
+    api_key: &str,
+    proxy: Option<&str>,
+) -> Result<WebSocket, SessionError> {
+    let mut request = url.into_client_request()?;
+    request.headers_mut().insert(
+        AUTHORIZATION,
+        HeaderValue::from_str(&format!("Bearer {api_key}"))
+            .map_err(|_| SessionError::InvalidHeaderValue("Authorization"))?,
+    );
+
+    let proxy = match proxy {
+        Some(proxy) => Some(Proxy::parse(proxy)?),
+        None => Proxy::from_env(request.uri())?,
+    };
+    let (ws_stream, _resp) = match proxy {
+        Some(proxy) => {
+            let uri = request.uri();
+            let host = uri.host().ok_or(WsError::Url(UrlError::NoHostName))?;
+            let default_port = if uri.scheme_str() == Some("wss") {
+                443
+            } else {
+                80
+            };
+            let stream = proxy
+                .tunnel(host, uri.port_u16().unwrap_or(default_port))
+                .await?;
+            client_async_tls(request, stream).await?
+        }
+        None => connect_async(request).await?,
+    };
+    Ok(ws_stream)
+}
+
+/// The backend side of a realtime session: what to send for the audio, and how to turn server
+/// events into results. Connections, pings, closing and reconnecting are left to [`drive`].
+pub trait Protocol {
+    type Event: serde::de::DeserializeOwned;
+    type Error: From<SessionError> + From<serde_json::Error>;
+
+    /// Starts a new connection, e.g. by requesting a task.
+    fn open(&mut self, out: &mut Output<Self::Error>);
+
+    /// Whether the next chunk of audio is wanted, e.g. once the server accepts it.
+    fn wants_audio(&self) -> bool;
+
+    /// Handles a chunk of audio, or `None` once the audio ended.
+    fn audio(&mut self, chunk: Option<io::Result<Bytes>>, out: &mut Output<Self::Error>);
+
+    fn event(&mut self, event: Self::Event, out: &mut Output<Self::Error>);
+
+    /// Called once a connection was finished to roll over to the next one.
+    fn rolled_over(&mut self);
+
+    /// Called once a connection was lost, before reconnecting. Only results may be emitted.
+    fn lost(&mut self, out: &mut Output<Self::Error>);
+}
+
+enum Action<E> {
+    Send(Message),
+    Emit(Result<TranscribeResponse, E>),
+    Close,
+    End,
+}
+
+/// What a [`Protocol`] does in response to audio and events, and what it knows about the
+/// current connection.
+#[allow(clippy::struct_excessive_bools)]
+pub struct Output<E> {
+    actions: VecDeque<Action<E>>,
+    started: bool,
+    finished: bool,
+    rollover: bool,
+    closed: bool,
+}
+
+impl<E> Output<E> {
+    const fn new() -> Self {
+        Self {
+            actions: VecDeque::new(),
+            started: false,
+            finished: false,
+            rollover: false,
+            closed: false,
+        }
+    }
+
+    /// Sends a request as JSON. If it cannot be serialized, the session ends with an error.
+    pub fn send(&mut self, request: &impl serde::Serialize)
+    where
+        E: From<serde_json::Error>,
+    {
+        match serde_json::to_string(request) {
+            Ok(text) => self
+                .actions
+                .push_back(Action::Send(Message::Text(text.into()))),
+            Err(e) => {
+                self.emit(Err(e.into()));
+                self.actions.push_back(Action::End);
+            }
+        }
+    }
+
+    pub fn send_binary(&mut self, data: Bytes) {
+        self.actions.push_back(Action::Send(Message::Binary(data)));
+    }
+
+    pub fn emit(&mut self, result: Result<TranscribeResponse, E>) {
+        self.actions.push_back(Action::Emit(result));
+    }
+
+    /// The server accepted the connection. From now on, a close by the server is taken as a lost
+    /// connection.
+    pub const fn started(&mut self) {
+        self.started = true;
+    }
+
+    /// The current connection is being finished to continue on a new one.
+    pub const fn rolling_over(&mut self) {
+        self.rollover = true;
+    }
+
+    /// The server finished the task or session; the connection is closed.
+    pub fn finished(&mut self) {
+        self.finished = true;
+        self.actions.push_back(Action::Close);
+    }
+
+    /// Closes the connection, and ends the session once the server closed it too.
+    pub fn close(&mut self) {
+        self.closed = true;
+        self.actions.push_back(Action::Close);
+    }
+
+    /// Ends the session with an error.
+    pub fn fail(&mut self, error: E) {
+        self.emit(Err(error));
+        self.actions.push_back(Action::Close);
+        self.actions.push_back(Action::End);
+    }
+}
+
+/// Streams the audio through `protocol`, over as many connections as needed. A connection that
+/// is lost is re-established by `connect` with backoff; one finished for a rollover is replaced
+/// right away.
+pub fn drive<P, C, F, W>(
+    mut protocol: P,
+    mut connect: C,
+    web_socket_stream: W,
+    mut audio_stream: AudioStream,
+) -> impl Stream<Item = Result<TranscribeResponse, P::Error>>
+where
+    P: Protocol,
+    C: FnMut() -> F,
+    F: Future<Output = Result<W, P::Error>>,
+    W: Stream<Item = Result<Message, WsError>> + Sink<Message>,
+    P::Error: std::fmt::Display,
+{
+    stream! {
+        let mut web_socket_stream = Some(web_socket_stream);
+        let mut backoff = Backoff::new();
+
+        'connection: loop {
+            let web_socket_stream = match web_socket_stream.take() {
+                Some(ws) => ws,
+                None => loop {
+                    let Some(delay) = backoff.next_delay() else {
+                        yield Err(SessionError::Connection.into());
+                        return;
+                    };
+                    sleep(delay).await;
+                    match connect().await {
+                        Ok(ws) => break ws,
+                        Err(e) => error!("failed to reconnect: {e}"),
+                    }
+                },
+            };
+            let (mut send, mut recv) = web_socket_stream.split();
+
+            let mut out = Output::new();
+            let mut lost = false;
+            protocol.open(&mut out);
+
+            loop {
+                while let Some(action) = out.actions.pop_front() {
+                    match action {
+                        Action::Send(message) => {
+                            if send.send(message).await.is_err() {
+                                lost = true;
+                                break;
+                            }
+                        }
+                        Action::Emit(result) => yield result,
+                        Action::Close => {
+                            let _ = send.close().await;
+                        }
+                        Action::End => return,
+                    }
+                }
+                if lost {
+                    break;
+                }
+                if out.started {
+                    backoff.reset();
+                }
+
+                select! {
+                    server_msg = recv.next() => match server_msg {
+                        Some(Ok(Message::Text(text))) => {
+                            match serde_json::from_str::<P::Event>(&text) {
+                                Ok(event) => protocol.event(event, &mut out),
+                                Err(e) => error!("ignoring unexpected server event ({e}): {text}"),
+                            }
+                        }
+                        Some(Ok(Message::Ping(data))) => {
+                            let _ = send.send(Message::Pong(data)).await;
+                        }
+                        Some(Ok(Message::Pong(_))) => {
+                            // ignore
+                        }
+                        Some(Ok(Message::Binary(_) | Message::Frame(_))) => {
+                            error!("unexpected binary message from server.");
+                        }
+                        Some(Ok(Message::Close(frame))) if out.started && !out.finished => {
+                            // E.g. closed for idleness; carry on in a new connection.
+                            info!("close by server during session: {:?}", frame);
+                            lost = true;
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            out.closed = true;
+                            info!("close by server: {:?}", frame);
+                            if let Some(close_frame) = frame {
+                                yield Err(SessionError::Closed(close_frame.reason.as_str().to_string()).into());
+                            }
+                        }
+                        None if out.finished && out.rollover => {
+                            protocol.rolled_over();
+                            continue 'connection;
+                        }
+                        None if out.finished || out.closed => {
+                            info!("server disconnected.");
+                            return;
+                        }
+                        None => {
+                            error!("server disconnected during session.");
+                            lost = true;
+                        }
+                        Some(Err(error)) => {
+                            error!("connection error: {:?}", error);
+                            lost = true;
+                        }
+                    },
+                    chunk = audio_stream.next(), if protocol.wants_audio() => {
+                        protocol.audio(chunk, &mut out);
+                    }
+                }
+                if lost {
+                    break;
+                }
+            }
+
+            info!("connection lost, reconnecting.");
+            out.actions.clear();
+            protocol.lost(&mut out);
+            while let Some(action) = out.actions.pop_front() {
+                if let Action::Emit(result) = action {
+                    yield result;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::traffic_log::Replay;
+
+    #[derive(Debug, thiserror::Error)]
+    enum TestError {
+        #[error(transparent)]
+        Session(#[from] SessionError),
+        #[error(transparent)]
+        Json(#[from] serde_json::Error),
+    }
+
+    /// Emits the `text` of each event until one is `done`.
+    struct Echo;
+
+    impl Protocol for Echo {
+        type Event = Value;
+        type Error = TestError;
+
+        fn open(&mut self, _out: &mut Output<Self::Error>) {}
+
+        fn wants_audio(&self) -> bool {
+            false
+        }
+
+        fn audio(&mut self, _chunk: Option<io::Result<Bytes>>, _out: &mut Output<Self::Error>) {}
+
+        fn event(&mut self, event: Self::Event, out: &mut Output<Self::Error>) {
+            out.started();
+            match event["text"].as_str() {
+                Some(text) => out.emit(Ok(TranscribeResponse {
+                    text: text.to_string(),
+                    begin_time: 0,
+                    sentence_end: true,
+                    translation: None,
+                })),
+                None => out.finished(),
+            }
+        }
+
+        fn rolled_over(&mut self) {}
+
+        fn lost(&mut self, _out: &mut Output<Self::Error>) {}
+    }
+
+    #[tokio::test]
+    async fn unexpected_frames_are_ignored() {
+        let mut replay = Replay::parse(
+            r#"
+{"connect":{"url":"wss://example.com"}}
+{"received":{"binary":"AAA="}}
+{"received":{"text":"not json"}}
+{"received":{"text":"{\"text\":\"hi\"}"}}
+{"received":{"text":"{\"done\":true}"}}
+"#,
+        )
+        .unwrap();
+        let first = replay.next_connection().unwrap();
+        let audio = AudioStream(Box::pin(futures_util::stream::empty()));
+        let results: Vec<_> = drive(
+            Echo,
+            || std::future::ready(Err(SessionError::Connection.into())),
+            first,
+            audio,
+        )
+        .map(|result| result.unwrap().text)
+        .collect()
+        .await;
+        assert_eq!(results, ["hi"]);
+    }
+}
@@ -6,7 +6,8 @@ use serde::{Deserialize, Serialize};
 use pulseaudio_recorder::PulseAudioConfig;
 
 use crate::config_store_error::ConfigStoreError;
-use crate::profile_config::ProfileConfig;
+use crate::profile_config::Profile;
+use crate::usage_config::UsageConfig;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -15,17 +16,36 @@ pub struct ConfigFile {
     pulseaudio: PulseAudioConfig,
 
     #[serde(rename = "Profiles", default)]
-    profiles: BTreeMap<String, ProfileConfig>,
+    profiles: BTreeMap<String, Profile>,
+
+    #[serde(rename = "Usage", default)]
+    usage: UsageConfig,
 }
 
 impl ConfigFile {
     pub fn parse(content: &str) -> Result<Self, ConfigStoreError> {
-        let config = toml::from_str(content)?;
+        let config: Self = toml::from_str(content)?;
+        for (profile_name, profile) in &config.profiles {
+            profile
+                .backend
+                .api_key_source()
+                .map_err(|source| ConfigStoreError::ApiKey {
+                    profile: profile_name.clone(),
+                    source,
+                })?;
+            profile
+                .backend
+                .validate()
+                .map_err(|message| ConfigStoreError::Invalid {
+                    profile: profile_name.clone(),
+                    message,
+                })?;
+        }
         Ok(config)
     }
 
     #[must_use]
-    pub const fn profiles(&self) -> &BTreeMap<String, ProfileConfig> {
+    pub const fn profiles(&self) -> &BTreeMap<String, Profile> {
         &self.profiles
     }
 
@@ -33,6 +53,11 @@ impl ConfigFile {
     pub const fn pulseaudio(&self) -> &PulseAudioConfig {
         &self.pulseaudio
     }
+
+    #[must_use]
+    pub const fn usage(&self) -> &UsageConfig {
+        &self.usage
+    }
 }
 
 pub fn get_config_path() -> Result<PathBuf, ConfigStoreError> {
@@ -43,9 +68,25 @@ pub fn get_config_path() -> Result<PathBuf, ConfigStoreError> {
     Ok(path)
 }
 
+/// Where dictyped keeps its state: `$XDG_DATA_HOME/dictype`, or `~/.local/share/dictype`.
+pub fn get_data_dir() -> Result<PathBuf, ConfigStoreError> {
+    let mut path =
+        if let Some(data_home) = std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+            PathBuf::from(data_home)
+        } else {
+            let home = std::env::var_os("HOME").ok_or(ConfigStoreError::MissingHome)?;
+            PathBuf::from(home).join(".local").join("share")
+        };
+    path.push("dictype");
+    Ok(path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::post_process_config::{PostProcessConfig, PunctuationWidth};
+    use crate::profile_config::ProfileConfig;
+    use base_client::audio_stream::AudioSource;
 
     #[test]
     fn test_get_config_path() {
@@ -78,6 +119,307 @@ mod tests {
         assert_eq!(config.profiles.len(), 1);
     }
 
+    #[test]
+    fn test_load_profiles_with_source() {
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "ParaformerV2"
+        Config = { dashscope_api_key = "fake" }
+
+        [Profiles.Captions]
+        Backend = "QwenV3"
+        Source = "Monitor"
+        Config = { dashscope_api_key = "fake" }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        assert_eq!(config.profiles["Profile1"].source, AudioSource::Microphone);
+        assert_eq!(config.profiles["Captions"].source, AudioSource::Monitor);
+    }
+
+    #[test]
+    fn test_load_profiles_with_preconnect() {
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "ParaformerV2"
+        Preconnect = true
+        Config = { dashscope_api_key = "fake" }
+
+        [Profiles.Profile2]
+        Backend = "QwenV3"
+        Config = { dashscope_api_key = "fake" }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        assert!(config.profiles["Profile1"].preconnect);
+        assert!(!config.profiles["Profile2"].preconnect);
+    }
+
+    #[test]
+    fn test_load_fallback_profile() {
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "Fallback"
+        Config = { profiles = ["Cloud", "Offline"], first_response_timeout_secs = 3 }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        let ProfileConfig::Fallback(fallback) = &config.profiles["Profile1"].backend else {
+            panic!("expected a fallback profile")
+        };
+        assert_eq!(fallback.profiles, ["Cloud", "Offline"]);
+        assert_eq!(fallback.first_response_timeout_secs(), 3);
+    }
+
+    #[test]
+    fn test_load_api_key_from_command() {
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "QwenV3"
+        Config = { dashscope_api_key_command = "pass show dashscope" }
+
+        [Profiles.Profile2]
+        Backend = "ParaformerV2"
+        Config = { dashscope_api_key = "sk-plaintext" }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        let ProfileConfig::QwenV3(qwen) = &config.profiles["Profile1"].backend else {
+            panic!("expected a QwenV3 profile")
+        };
+        assert_eq!(
+            qwen.api_key.dashscope_api_key_command.as_deref(),
+            Some("pass show dashscope")
+        );
+        assert!(!format!("{config:?}").contains("sk-plaintext"));
+    }
+
+    #[test]
+    fn test_reject_profile_without_single_api_key_source() {
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "Gummy"
+        Config = { dashscope_api_key = "fake", dashscope_api_key_env = "DASHSCOPE_API_KEY" }
+        "#;
+        let err = ConfigFile::parse(config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "profile Profile1: more than one API key source is configured"
+        );
+
+        let config = r#"
+        [Profiles.Profile2]
+        Backend = "FunAsrRealtime"
+        Config = { }
+        "#;
+        let err = ConfigFile::parse(config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "profile Profile2: no API key is configured"
+        );
+
+        let config = r#"
+        [Profiles.Profile3]
+        Backend = "ParaformerV2"
+        Config = { dashscope_api_key = "fake", dashscope_api_key_typo = "x" }
+        "#;
+        assert!(ConfigFile::parse(config).is_err());
+
+        let config = r#"
+        [Profiles.Profile4]
+        Backend = "OpenAiRealtime"
+        Config = { api_key = "sk-plaintext", api_key_command = "pass show openai" }
+        "#;
+        let err = ConfigFile::parse(config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "profile Profile4: more than one API key source is configured"
+        );
+    }
+
+    #[test]
+    fn test_reject_short_max_durations() {
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "Gummy"
+        Config = { dashscope_api_key = "fake", max_task_duration_secs = 0 }
+        "#;
+        let err = ConfigFile::parse(config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "profile Profile1: max_task_duration_secs must be at least 10, not 0"
+        );
+
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "ParaformerV2"
+        Config = { dashscope_api_key = "fake", max_task_duration_secs = 10 }
+        "#;
+        assert!(ConfigFile::parse(config).is_ok());
+
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "QwenV3"
+        Config = { dashscope_api_key = "fake", max_session_duration_secs = 5 }
+        "#;
+        let err = ConfigFile::parse(config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "profile Profile1: max_session_duration_secs must be at least 10, not 5"
+        );
+    }
+
+    #[test]
+    fn test_openai_transcriptions_key_is_optional() {
+        let config = r#"
+        [Profiles.Local]
+        Backend = "OpenAiTranscriptions"
+        Config = { base_url = "http://127.0.0.1:8000/v1" }
+
+        [Profiles.OpenAi]
+        Backend = "OpenAiTranscriptions"
+        Config = { api_key_env = "OPENAI_API_KEY" }
+        "#;
+        assert!(ConfigFile::parse(config).is_ok());
+
+        let config = r#"
+        [Profiles.OpenAi]
+        Backend = "OpenAiTranscriptions"
+        Config = { api_key = "sk-plaintext", api_key_env = "OPENAI_API_KEY" }
+        "#;
+        assert!(ConfigFile::parse(config).is_err());
+    }
+
+    #[test]
+    fn test_load_comparison_profile() {
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "Comparison"
+        Config = { primary = "Paraformer", others = ["Qwen"] }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        let ProfileConfig::Comparison(comparison) = &config.profiles["Profile1"].backend else {
+            panic!("expected a comparison profile")
+        };
+        assert_eq!(comparison.primary, "Paraformer");
+        assert_eq!(comparison.others, ["Qwen"]);
+    }
+
+    #[test]
+    fn test_load_usage_caps() {
+        let config = r#"
+        [Usage]
+        daily_cap_secs = 3600
+
+        [Profiles.Profile1]
+        Backend = "ParaformerV2"
+        Config = { dashscope_api_key = "fake" }
+
+        [Profiles.Offline]
+        Backend = "Whisper"
+        Config = { model_path = "/models/ggml-base.bin" }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        assert_eq!(config.usage().daily_cap_secs, Some(3600));
+        assert_eq!(config.usage().monthly_cap_secs, None);
+        assert!(config.profiles["Profile1"].backend.is_billed());
+        assert!(!config.profiles["Offline"].backend.is_billed());
+    }
+
+    #[test]
+    fn test_load_whisper_profile() {
+        let config = r#"
+        [Profiles.Offline]
+        Backend = "Whisper"
+        Config = { model_path = "/models/ggml-base.bin", vad = { threshold = 0.02, silence_duration_ms = 600, prefix_padding_ms = 300, min_speech_duration_ms = 250, max_segment_duration_ms = 20000 } }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        assert_eq!(config.profiles["Offline"].backend.backend_name(), "Whisper");
+    }
+
+    #[test]
+    fn test_load_gummy_profile() {
+        let config = r#"
+        [Profiles.Translate]
+        Backend = "Gummy"
+        Config = { dashscope_api_key = "fake", source_language = "zh", type_translation = true }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        let ProfileConfig::Gummy(gummy) = &config.profiles["Translate"].backend else {
+            panic!("expected a Gummy profile")
+        };
+        assert_eq!(gummy.target_language(), "en");
+        assert!(gummy.type_translation());
+        assert!(config.profiles["Translate"].backend.is_billed());
+    }
+
+    #[test]
+    fn test_load_fun_asr_realtime_profile() {
+        let config = r#"
+        [Profiles.Dialects]
+        Backend = "FunAsrRealtime"
+        Config = { dashscope_api_key = "fake", language_hints = ["zh", "en"] }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        let backend = &config.profiles["Dialects"].backend;
+        assert_eq!(backend.backend_name(), "FunAsrRealtime");
+        assert!(backend.is_billed());
+    }
+
+    #[test]
+    fn test_load_post_process_rules() {
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "ParaformerV2"
+        Config = { dashscope_api_key = "fake" }
+
+        [Profiles.Profile1.PostProcess]
+        dictionary = { "dye type" = "Dictype" }
+        replacements = [
+            { pattern = '(\d+) percent', replacement = "$1%" },
+        ]
+        punctuation = "HalfWidth"
+        cjk_latin_spacing = true
+
+        [Profiles.Profile2]
+        Backend = "QwenV3"
+        Config = { dashscope_api_key = "fake" }
+        "#;
+
+        let config = ConfigFile::parse(config).unwrap();
+        let post_process = &config.profiles["Profile1"].post_process;
+        assert_eq!(
+            post_process.dictionary.as_ref().unwrap()["dye type"],
+            "Dictype"
+        );
+        assert_eq!(
+            post_process.replacements.as_ref().unwrap()[0].pattern,
+            r"(\d+) percent"
+        );
+        assert_eq!(post_process.punctuation, Some(PunctuationWidth::HalfWidth));
+        assert_eq!(
+            config.profiles["Profile2"].post_process,
+            PostProcessConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_reject_unknown_profile_fields() {
+        let config = r#"
+        [Profiles.Profile1]
+        Backend = "ParaformerV2"
+        Unknown = 1
+        Config = { dashscope_api_key = "fake" }
+        "#;
+
+        assert!(ConfigFile::parse(config).is_err());
+    }
+
     #[test]
     fn test_reject_known_sections() {
         let config = r"
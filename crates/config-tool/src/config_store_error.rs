@@ -8,4 +8,11 @@ pub enum ConfigStoreError {
     Toml(#[from] toml::de::Error),
     #[error("toml write error: {0}")]
     TomlWrite(#[from] toml::ser::Error),
+    #[error("profile {profile}: {source}")]
+    ApiKey {
+        profile: String,
+        source: base_client::api_key::ApiKeyError,
+    },
+    #[error("profile {profile}: {message}")]
+    Invalid { profile: String, message: String },
 }
@@ -1,3 +1,5 @@
 pub mod config_store;
 mod config_store_error;
+pub mod post_process_config;
 pub mod profile_config;
+pub mod usage_config;
@@ -0,0 +1,38 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Rewrites the text of a profile's results before it is typed, partial results included. The
+/// steps run in the order of the fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PostProcessConfig {
+    /// Misrecognized phrases and what to type instead, e.g. `"dye type" = "Dictype"`. Phrases
+    /// match regardless of case, and only as whole words where they start or end with one.
+    pub dictionary: Option<BTreeMap<String, String>>,
+    /// Regex replacements, applied in order.
+    pub replacements: Option<Vec<Replacement>>,
+    /// Converts punctuation to one width.
+    pub punctuation: Option<PunctuationWidth>,
+    /// Puts a space between CJK characters and Latin letters or digits.
+    pub cjk_latin_spacing: Option<bool>,
+    /// Capitalizes the first letter of each sentence.
+    pub capitalize_sentences: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Replacement {
+    /// A regex in the syntax of the `regex` crate.
+    pub pattern: String,
+    /// What a match is replaced with. `$1` or `${name}` stand for the groups of the match.
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PunctuationWidth {
+    /// `，。？！` become `, . ? !`, e.g. for English dictated with a Chinese model.
+    HalfWidth,
+    /// `, . ? !` after CJK characters become `，。？！`.
+    FullWidth,
+}
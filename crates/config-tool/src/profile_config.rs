@@ -1,13 +1,35 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use base_client::api_key::{ApiKeyError, ApiKeySource};
+use base_client::audio_stream::AudioSource;
+use fun_asr_realtime_client::config::FunAsrRealtimeConfig;
+use funasr_client::config::FunAsrConfig;
+use gummy_client::config::GummyConfig;
+use openai_realtime_client::config::OpenAiRealtimeConfig;
+use openai_transcriptions_client::config::OpenAiTranscriptionsConfig;
 use paraformer_v2_client::config::ParaformerV2Config;
 use qwen_v3_client::config::QwenV3Config;
+use sherpa_onnx_client::config::SherpaOnnxConfig;
+use whisper_client::config::WhisperConfig;
+
+use crate::post_process_config::PostProcessConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "Backend", content = "Config", deny_unknown_fields)]
 pub enum ProfileConfig {
     ParaformerV2(ParaformerV2Config),
     QwenV3(QwenV3Config),
+    Gummy(GummyConfig),
+    FunAsrRealtime(FunAsrRealtimeConfig),
+    Whisper(WhisperConfig),
+    SherpaOnnx(SherpaOnnxConfig),
+    FunAsr(FunAsrConfig),
+    OpenAiRealtime(OpenAiRealtimeConfig),
+    OpenAiTranscriptions(OpenAiTranscriptionsConfig),
+    Fallback(FallbackConfig),
+    Comparison(ComparisonConfig),
 }
 
 impl ProfileConfig {
@@ -16,6 +38,173 @@ impl ProfileConfig {
         match self {
             Self::ParaformerV2(_) => "ParaformerV2",
             Self::QwenV3(_) => "QwenV3",
+            Self::Gummy(_) => "Gummy",
+            Self::FunAsrRealtime(_) => "FunAsrRealtime",
+            Self::Whisper(_) => "Whisper",
+            Self::SherpaOnnx(_) => "SherpaOnnx",
+            Self::FunAsr(_) => "FunAsr",
+            Self::OpenAiRealtime(_) => "OpenAiRealtime",
+            Self::OpenAiTranscriptions(_) => "OpenAiTranscriptions",
+            Self::Fallback(_) => "Fallback",
+            Self::Comparison(_) => "Comparison",
+        }
+    }
+
+    /// Where the backend's API key comes from, or why its settings name no single source. `None`
+    /// for backends without a key.
+    pub fn api_key_source(&self) -> Result<Option<ApiKeySource<'_>>, ApiKeyError> {
+        match self {
+            Self::ParaformerV2(config) => config.api_key.source().map(Some),
+            Self::QwenV3(config) => config.api_key.source().map(Some),
+            Self::Gummy(config) => config.api_key.source().map(Some),
+            Self::FunAsrRealtime(config) => config.api_key.source().map(Some),
+            Self::OpenAiRealtime(config) => config.api_key.source().map(Some),
+            // The key is optional, since many self-hosted servers need none.
+            Self::OpenAiTranscriptions(config) => match config.api_key.source() {
+                Err(ApiKeyError::Missing) => Ok(None),
+                source => source.map(Some),
+            },
+            Self::Whisper(_)
+            | Self::SherpaOnnx(_)
+            | Self::FunAsr(_)
+            | Self::Fallback(_)
+            | Self::Comparison(_) => Ok(None),
+        }
+    }
+
+    /// Checks settings that parse but that the backend cannot run with.
+    pub fn validate(&self) -> Result<(), String> {
+        let max_task_duration_secs = match self {
+            Self::ParaformerV2(config) => config.max_task_duration_secs,
+            Self::Gummy(config) => config.max_task_duration_secs,
+            Self::FunAsrRealtime(config) => config.max_task_duration_secs,
+            _ => None,
+        };
+        // The floor is the same for all of them.
+        let min = ParaformerV2Config::MIN_MAX_TASK_DURATION_SECS;
+        if let Some(secs) = max_task_duration_secs.filter(|secs| *secs < min) {
+            return Err(format!(
+                "max_task_duration_secs must be at least {min}, not {secs}"
+            ));
+        }
+        if let Self::QwenV3(config) = self {
+            let min = QwenV3Config::MIN_MAX_SESSION_DURATION_SECS;
+            if let Some(secs) = config.max_session_duration_secs.filter(|secs| *secs < min) {
+                return Err(format!(
+                    "max_session_duration_secs must be at least {min}, not {secs}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the backend bills for the audio it is sent. OpenAI-compatible backends pointed at
+    /// another server, e.g. a local one, are not counted.
+    #[must_use]
+    pub const fn is_billed(&self) -> bool {
+        match self {
+            Self::ParaformerV2(_) | Self::QwenV3(_) | Self::Gummy(_) | Self::FunAsrRealtime(_) => {
+                true
+            }
+            Self::OpenAiRealtime(config) => config.websocket_url.is_none(),
+            Self::OpenAiTranscriptions(config) => config.base_url.is_none(),
+            // Composite profiles are counted by the profiles they use.
+            Self::Whisper(_)
+            | Self::SherpaOnnx(_)
+            | Self::FunAsr(_)
+            | Self::Fallback(_)
+            | Self::Comparison(_) => false,
+        }
+    }
+}
+
+/// Tries other profiles in order, moving on to the next when one fails to start a session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FallbackConfig {
+    /// Names of the profiles to try. They cannot be fallback profiles themselves.
+    pub profiles: Vec<String>,
+    /// How long a profile may take to connect, and to send its first response after the first
+    /// speech, before the next one is tried.
+    pub first_response_timeout_secs: Option<u32>,
+}
+
+impl FallbackConfig {
+    pub const DEFAULT_FIRST_RESPONSE_TIMEOUT_SECS: u32 = 5;
+
+    #[must_use]
+    pub fn first_response_timeout_secs(&self) -> u32 {
+        self.first_response_timeout_secs
+            .unwrap_or(Self::DEFAULT_FIRST_RESPONSE_TIMEOUT_SECS)
+    }
+}
+
+/// Transcribes with several profiles at once. Only the primary's results are typed; the others'
+/// are logged with their timing, to compare backends on the same speech.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComparisonConfig {
+    /// Name of the profile whose results are typed.
+    pub primary: String,
+    /// Names of the profiles whose results are only logged. None of the profiles can be fallback
+    /// or comparison profiles themselves.
+    pub others: Vec<String>,
+}
+
+/// A profile as written in the config file: a backend plus where its audio comes from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawProfile")]
+pub struct Profile {
+    #[serde(flatten)]
+    pub backend: ProfileConfig,
+
+    /// `Monitor` transcribes system playback (e.g. for live captions) instead of a microphone.
+    #[serde(rename = "Source", default)]
+    pub source: AudioSource,
+
+    /// Opens the backend connection ahead of time: when the frontend hints that a session may
+    /// start, and right after a session ends.
+    #[serde(rename = "Preconnect", default)]
+    pub preconnect: bool,
+
+    /// Rewrites the results before they are typed, e.g. to fix misrecognized terms.
+    #[serde(rename = "PostProcess", default)]
+    pub post_process: PostProcessConfig,
+}
+
+/// `deny_unknown_fields` does not work together with `flatten`, so unknown keys are collected
+/// here and rejected in `TryFrom`.
+#[derive(Deserialize)]
+struct RawProfile {
+    #[serde(flatten)]
+    backend: ProfileConfig,
+
+    #[serde(rename = "Source", default)]
+    source: AudioSource,
+
+    #[serde(rename = "Preconnect", default)]
+    preconnect: bool,
+
+    #[serde(rename = "PostProcess", default)]
+    post_process: PostProcessConfig,
+
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+impl TryFrom<RawProfile> for Profile {
+    type Error = String;
+
+    fn try_from(value: RawProfile) -> Result<Self, Self::Error> {
+        if let Some(field) = value.unknown.keys().next() {
+            return Err(format!("unknown field `{field}` in profile"));
         }
+
+        Ok(Self {
+            backend: value.backend,
+            source: value.source,
+            preconnect: value.preconnect,
+            post_process: value.post_process,
+        })
     }
 }
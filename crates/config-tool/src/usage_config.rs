@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// Caps on the audio sent to billed backends, summed over all profiles. Once one is reached,
+/// running sessions of billed profiles stop and new ones are refused until the day or month ends.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UsageConfig {
+    /// Billed audio allowed per calendar day, in local time.
+    pub daily_cap_secs: Option<u64>,
+    /// Billed audio allowed per calendar month, in local time.
+    pub monthly_cap_secs: Option<u64>,
+}
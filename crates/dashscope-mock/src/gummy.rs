@@ -0,0 +1,52 @@
+//! Server events of the Gummy protocol. It is Paraformer's `run-task` protocol with other
+//! results, so the other events are those of [`crate::paraformer`].
+
+use serde_json::{Value, json};
+
+fn sentence(text: &str, begin_time: u32, end_time: Option<u32>) -> Value {
+    json!({
+        "sentence_id": 0,
+        "begin_time": begin_time,
+        "end_time": end_time,
+        "text": text,
+        "sentence_end": end_time.is_some(),
+        "words": [],
+    })
+}
+
+fn english(text: &str, begin_time: u32, end_time: Option<u32>) -> Value {
+    let mut translation = sentence(text, begin_time, end_time);
+    translation["lang"] = "en".into();
+    translation
+}
+
+fn result_generated(output: Value) -> Value {
+    let mut result = json!({"header": {"event": "result-generated", "attributes": {}}});
+    result["payload"]["output"] = output;
+    result
+}
+
+/// A sentence and its English translation, if any, that end at `end_time`, or partial ones
+/// without it.
+#[must_use]
+pub fn result(
+    transcript: &str,
+    translation: Option<&str>,
+    begin_time: u32,
+    end_time: Option<u32>,
+) -> Value {
+    let translations: Vec<Value> = translation
+        .map(|text| english(text, begin_time, end_time))
+        .into_iter()
+        .collect();
+    result_generated(json!({
+        "transcription": sentence(transcript, begin_time, end_time),
+        "translations": translations,
+    }))
+}
+
+/// The English translation of the sentence alone, e.g. when it lags behind the transcript.
+#[must_use]
+pub fn translation(text: &str, begin_time: u32, end_time: Option<u32>) -> Value {
+    result_generated(json!({"translations": [english(text, begin_time, end_time)]}))
+}
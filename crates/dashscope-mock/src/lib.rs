@@ -0,0 +1,205 @@
+//! A local websocket server that speaks the Paraformer, Gummy and Qwen realtime protocols from
+//! scripts, so that their clients can be tested without an API key.
+
+use std::time::Duration;
+
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+pub mod gummy;
+pub mod paraformer;
+pub mod qwen;
+
+/// What the server does next on a connection.
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Waits for a client event, named by its Paraformer `header.action` or its Qwen `type`.
+    Expect(&'static str),
+    /// Waits until this many bytes of audio were received on the connection.
+    ExpectAudio(usize),
+    /// Sends an event. Paraformer events without a `task_id` get the one of the last `run-task`.
+    Send(Value),
+    Delay(Duration),
+    /// Sends a close frame, with a code and reason if given.
+    Close(Option<(u16, &'static str)>),
+    /// Drops the connection without a close handshake.
+    Reset,
+}
+
+/// What the server received on one connection.
+#[derive(Debug, Default)]
+pub struct Connection {
+    /// Client events in order, audio appends included.
+    pub events: Vec<Value>,
+    /// Audio from binary frames or `input_audio_buffer.append` events.
+    pub audio: Vec<u8>,
+}
+
+impl Connection {
+    /// Names of the events received, with consecutive repeats collapsed.
+    #[must_use]
+    pub fn event_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.events.iter().filter_map(event_name).collect();
+        names.dedup();
+        names
+    }
+}
+
+fn event_name(event: &Value) -> Option<&str> {
+    event["header"]["action"]
+        .as_str()
+        .or_else(|| event["type"].as_str())
+}
+
+/// Plays one script per connection, to consecutive connections in order.
+#[derive(Debug)]
+pub struct MockServer {
+    url: String,
+    handle: JoinHandle<Vec<Connection>>,
+}
+
+impl MockServer {
+    pub async fn start(scripts: Vec<Vec<Step>>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind mock server");
+        let url = format!("ws://{}", listener.local_addr().expect("no local address"));
+        let handle = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            for script in scripts {
+                let (tcp, _) = listener.accept().await.expect("failed to accept");
+                let ws = tokio_tungstenite::accept_async(tcp)
+                    .await
+                    .expect("failed to accept websocket");
+                connections.push(Player::new(ws).play(script).await);
+            }
+            connections
+        });
+        Self { url, handle }
+    }
+
+    #[must_use]
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// Waits until every script was played and the client closed each connection.
+    pub async fn connections(self) -> Vec<Connection> {
+        self.handle.await.expect("mock server failed")
+    }
+}
+
+struct Player {
+    ws: WebSocketStream<TcpStream>,
+    connection: Connection,
+    task_id: Option<String>,
+}
+
+impl Player {
+    fn new(ws: WebSocketStream<TcpStream>) -> Self {
+        Self {
+            ws,
+            connection: Connection::default(),
+            task_id: None,
+        }
+    }
+
+    async fn play(mut self, script: Vec<Step>) -> Connection {
+        for step in script {
+            let open = match step {
+                Step::Expect(name) => self.expect(name).await,
+                Step::ExpectAudio(bytes) => self.expect_audio(bytes).await,
+                Step::Send(event) => self.send(event).await,
+                Step::Delay(delay) => {
+                    sleep(delay).await;
+                    true
+                }
+                Step::Close(frame) => {
+                    let frame = frame.map(|(code, reason)| CloseFrame {
+                        code: code.into(),
+                        reason: reason.into(),
+                    });
+                    self.ws.close(frame).await.is_ok()
+                }
+                Step::Reset => return self.connection,
+            };
+            if !open {
+                return self.connection;
+            }
+        }
+        // Whatever the client sends until it closes.
+        while self.receive().await {}
+        self.connection
+    }
+
+    async fn expect(&mut self, name: &str) -> bool {
+        let seen = self.connection.events.len();
+        while !self.connection.events[seen..]
+            .iter()
+            .any(|event| event_name(event) == Some(name))
+        {
+            if !self.receive().await {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn expect_audio(&mut self, bytes: usize) -> bool {
+        while self.connection.audio.len() < bytes {
+            if !self.receive().await {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn send(&mut self, mut event: Value) -> bool {
+        if let Some(header) = event.get_mut("header").and_then(Value::as_object_mut)
+            && header.get("task_id").is_none_or(Value::is_null)
+            && let Some(task_id) = &self.task_id
+        {
+            header.insert("task_id".to_string(), task_id.clone().into());
+        }
+        self.ws
+            .send(Message::Text(event.to_string().into()))
+            .await
+            .is_ok()
+    }
+
+    /// Records the next message. Returns false once the connection is closed.
+    async fn receive(&mut self) -> bool {
+        match self.ws.next().await {
+            Some(Ok(Message::Text(text))) => {
+                let event: Value = serde_json::from_str(&text).expect("client sent invalid JSON");
+                if event["header"]["action"] == "run-task" {
+                    self.task_id = event["header"]["task_id"].as_str().map(ToString::to_string);
+                }
+                if let Some(audio) = event["audio"].as_str()
+                    && event["type"] == "input_audio_buffer.append"
+                {
+                    let audio = base64::engine::general_purpose::STANDARD
+                        .decode(audio)
+                        .expect("client sent invalid base64");
+                    self.connection.audio.extend(audio);
+                }
+                self.connection.events.push(event);
+                true
+            }
+            Some(Ok(Message::Binary(audio))) => {
+                self.connection.audio.extend(audio);
+                true
+            }
+            // Reading on after a close frame completes the close handshake.
+            Some(Ok(_)) => true,
+            Some(Err(_)) | None => false,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Server events of the Paraformer `run-task` protocol. Their `task_id` is filled in by the
+//! server.
+
+use serde_json::{Value, json};
+
+fn header(event: &str) -> Value {
+    json!({"event": event, "attributes": {}})
+}
+
+#[must_use]
+pub fn task_started() -> Value {
+    json!({"header": header("task-started"), "payload": {}})
+}
+
+/// A sentence that ends at `end_time`, or a partial one without it.
+#[must_use]
+pub fn result(text: &str, begin_time: u32, end_time: Option<u32>) -> Value {
+    json!({
+        "header": header("result-generated"),
+        "payload": {"output": {"sentence": {
+            "begin_time": begin_time,
+            "end_time": end_time,
+            "text": text,
+            "sentence_end": end_time.is_some(),
+            "words": [],
+        }}},
+    })
+}
+
+#[must_use]
+pub fn task_finished() -> Value {
+    json!({"header": header("task-finished"), "payload": {"output": {}, "usage": null}})
+}
+
+#[must_use]
+pub fn task_failed(error_code: &str, error_message: &str) -> Value {
+    let mut header = header("task-failed");
+    header["error_code"] = error_code.into();
+    header["error_message"] = error_message.into();
+    json!({"header": header, "payload": {}})
+}
@@ -0,0 +1,76 @@
+//! Server events of the Qwen realtime protocol.
+
+use serde_json::{Value, json};
+
+#[must_use]
+pub fn session_created() -> Value {
+    json!({
+        "event_id": "event_0",
+        "type": "session.created",
+        "session": {
+            "id": "sess_0",
+            "object": "realtime.session",
+            "model": "qwen3-asr-flash-realtime",
+            "modalities": ["text"],
+            "input_audio_format": "pcm16",
+            "input_audio_transcription": {},
+            "turn_detection": {"type": "server_vad", "threshold": 0.2, "silence_duration_ms": 800},
+        },
+    })
+}
+
+#[must_use]
+pub fn speech_started(item_id: &str, audio_start_ms: u32) -> Value {
+    json!({
+        "event_id": "e",
+        "type": "input_audio_buffer.speech_started",
+        "audio_start_ms": audio_start_ms,
+        "item_id": item_id,
+    })
+}
+
+#[must_use]
+pub fn committed(item_id: &str) -> Value {
+    json!({"event_id": "e", "type": "input_audio_buffer.committed", "item_id": item_id})
+}
+
+/// A preview of the turn's transcript.
+#[must_use]
+pub fn text(item_id: &str, text: &str) -> Value {
+    json!({
+        "event_id": "e",
+        "type": "conversation.item.input_audio_transcription.text",
+        "item_id": item_id,
+        "content_index": 0,
+        "text": text,
+        "language": "en",
+        "emotion": "neutral",
+    })
+}
+
+#[must_use]
+pub fn completed(item_id: &str, transcript: &str) -> Value {
+    json!({
+        "event_id": "e",
+        "type": "conversation.item.input_audio_transcription.completed",
+        "item_id": item_id,
+        "content_index": 0,
+        "transcript": transcript,
+        "language": "en",
+        "emotion": "neutral",
+    })
+}
+
+#[must_use]
+pub fn session_finished() -> Value {
+    json!({"event_id": "e", "type": "session.finished"})
+}
+
+#[must_use]
+pub fn error(code: &str, message: &str) -> Value {
+    json!({
+        "event_id": "e",
+        "type": "error",
+        "error": {"type": "error", "code": code, "message": message, "param": "", "event_id": "e"},
+    })
+}
@@ -1,15 +1,34 @@
 use base_client::asr_client::AsrClient;
 use base_client::audio_stream::AudioStream;
 use base_client::transcribe_stream::TranscribeStream;
+use fun_asr_realtime_client::client::FunAsrRealtimeClient;
+use funasr_client::client::FunAsrClient;
+use gummy_client::client::GummyClient;
+use openai_realtime_client::client::OpenAiRealtimeClient;
+use openai_transcriptions_client::client::OpenAiTranscriptionsClient;
 use paraformer_v2_client::client::ParaformerV2Client;
 use qwen_v3_client::client::QwenV3Client;
+use sherpa_onnx_client::client::SherpaOnnxClient;
+use whisper_client::client::WhisperClient;
+
+/// Per-request settings, overriding those of the profile.
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    pub context: Option<String>,
+}
 
 #[async_trait::async_trait]
 pub trait BackendClient {
     async fn create_transcription_stream(
         &self,
         audio_stream: AudioStream,
+        options: &SessionOptions,
     ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error>;
+
+    /// Opens a connection for the next session in advance, where the backend supports it.
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -17,9 +36,14 @@ impl BackendClient for ParaformerV2Client {
     async fn create_transcription_stream(
         &self,
         audio_stream: AudioStream,
+        _options: &SessionOptions,
     ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
         self.create(audio_stream).await
     }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        AsrClient::prepare(self).await
+    }
 }
 
 #[async_trait::async_trait]
@@ -27,6 +51,111 @@ impl BackendClient for QwenV3Client {
     async fn create_transcription_stream(
         &self,
         audio_stream: AudioStream,
+        options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        match &options.context {
+            Some(context) => {
+                let mut config = self.config().clone();
+                config.context = Some(context.clone());
+                self.create_with_config(audio_stream, config).await
+            }
+            None => self.create(audio_stream).await,
+        }
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        AsrClient::prepare(self).await
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for GummyClient {
+    async fn create_transcription_stream(
+        &self,
+        audio_stream: AudioStream,
+        _options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        self.create(audio_stream).await
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        AsrClient::prepare(self).await
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for FunAsrRealtimeClient {
+    async fn create_transcription_stream(
+        &self,
+        audio_stream: AudioStream,
+        _options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        self.create(audio_stream).await
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        AsrClient::prepare(self).await
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for WhisperClient {
+    async fn create_transcription_stream(
+        &self,
+        audio_stream: AudioStream,
+        _options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        self.create(audio_stream).await
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for SherpaOnnxClient {
+    async fn create_transcription_stream(
+        &self,
+        audio_stream: AudioStream,
+        _options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        self.create(audio_stream).await
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for FunAsrClient {
+    async fn create_transcription_stream(
+        &self,
+        audio_stream: AudioStream,
+        _options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        self.create(audio_stream).await
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        AsrClient::prepare(self).await
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for OpenAiRealtimeClient {
+    async fn create_transcription_stream(
+        &self,
+        audio_stream: AudioStream,
+        _options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        self.create(audio_stream).await
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        AsrClient::prepare(self).await
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for OpenAiTranscriptionsClient {
+    async fn create_transcription_stream(
+        &self,
+        audio_stream: AudioStream,
+        _options: &SessionOptions,
     ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
         self.create(audio_stream).await
     }
@@ -1,23 +1,53 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::{Arc, Mutex};
 
 use base_client::asr_client::AsrClient;
-use config_tool::config_store::ConfigFile;
+use base_client::audio_stream::AudioSource;
+use config_tool::config_store::{ConfigFile, get_data_dir};
+use config_tool::post_process_config::PostProcessConfig;
 use config_tool::profile_config::ProfileConfig;
+use config_tool::usage_config::UsageConfig;
+use fun_asr_realtime_client::client::FunAsrRealtimeClient;
+use funasr_client::client::FunAsrClient;
+use gummy_client::client::GummyClient;
+use openai_realtime_client::client::OpenAiRealtimeClient;
+use openai_transcriptions_client::client::OpenAiTranscriptionsClient;
 use paraformer_v2_client::client::ParaformerV2Client;
 use qwen_v3_client::client::QwenV3Client;
+use sherpa_onnx_client::client::SherpaOnnxClient;
+use whisper_client::client::WhisperClient;
+
+use tracing::warn;
 
 use crate::client::BackendClient;
+use crate::comparison_client::ComparisonClient;
+use crate::error::DictypeError;
+use crate::fallback_client::FallbackClient;
+use crate::metered_client::MeteredClient;
+use crate::post_processor::PostProcessor;
+use crate::usage_ledger::UsageLedger;
 
+#[derive(Clone)]
 pub struct ClientStore {
     clients: Arc<Mutex<BTreeMap<String, Arc<dyn BackendClient + Send + Sync>>>>,
+    sources: BTreeMap<String, AudioSource>,
+    /// Profiles whose connection is opened ahead of sessions.
+    preconnect: BTreeSet<String>,
+    /// Profiles whose audio is recorded in the usage ledger.
+    billed: BTreeSet<String>,
+    usage_ledger: Arc<UsageLedger>,
+    post_processors: BTreeMap<String, Arc<PostProcessor>>,
 }
 
 impl ClientStore {
-    pub fn load(config_file: &ConfigFile) -> Self {
+    pub fn load(config_file: &ConfigFile) -> Result<Self, DictypeError> {
         let mut clients = BTreeMap::<String, Arc<dyn BackendClient + Send + Sync>>::new();
-        for (profile_name, config) in config_file.profiles() {
-            match &config {
+        let mut sources = BTreeMap::<String, AudioSource>::new();
+        let mut fallbacks = Vec::new();
+        let mut comparisons = Vec::new();
+        for (profile_name, profile) in config_file.profiles() {
+            sources.insert(profile_name.clone(), profile.source);
+            match &profile.backend {
                 ProfileConfig::ParaformerV2(paraformer_v2) => {
                     clients.insert(
                         profile_name.clone(),
@@ -30,15 +60,147 @@ impl ClientStore {
                         Arc::new(QwenV3Client::new(qwen_v3.clone())),
                     );
                 }
+                ProfileConfig::Gummy(gummy) => {
+                    clients.insert(
+                        profile_name.clone(),
+                        Arc::new(GummyClient::new(gummy.clone())),
+                    );
+                }
+                ProfileConfig::FunAsrRealtime(fun_asr_realtime) => {
+                    clients.insert(
+                        profile_name.clone(),
+                        Arc::new(FunAsrRealtimeClient::new(fun_asr_realtime.clone())),
+                    );
+                }
+                ProfileConfig::Whisper(whisper) => {
+                    clients.insert(
+                        profile_name.clone(),
+                        Arc::new(WhisperClient::new(whisper.clone())),
+                    );
+                }
+                ProfileConfig::SherpaOnnx(sherpa_onnx) => {
+                    clients.insert(
+                        profile_name.clone(),
+                        Arc::new(SherpaOnnxClient::new(sherpa_onnx.clone())),
+                    );
+                }
+                ProfileConfig::FunAsr(fun_asr) => {
+                    clients.insert(
+                        profile_name.clone(),
+                        Arc::new(FunAsrClient::new(fun_asr.clone())),
+                    );
+                }
+                ProfileConfig::OpenAiRealtime(openai_realtime) => {
+                    clients.insert(
+                        profile_name.clone(),
+                        Arc::new(OpenAiRealtimeClient::new(openai_realtime.clone())),
+                    );
+                }
+                ProfileConfig::OpenAiTranscriptions(openai_transcriptions) => {
+                    clients.insert(
+                        profile_name.clone(),
+                        Arc::new(OpenAiTranscriptionsClient::new(
+                            openai_transcriptions.clone(),
+                        )),
+                    );
+                }
+                ProfileConfig::Fallback(fallback) => {
+                    fallbacks.push((profile_name, fallback));
+                }
+                ProfileConfig::Comparison(comparison) => {
+                    comparisons.push((profile_name, comparison));
+                }
             }
         }
 
-        Self::from_clients(clients)
+        let mut store = Self::from_clients(BTreeMap::new());
+        store.sources = sources;
+        let usage_config = config_file.usage().clone();
+        store.set_usage_ledger(Arc::new(match get_data_dir() {
+            Ok(data_dir) => UsageLedger::load(data_dir.join("usage.json"), usage_config),
+            Err(e) => {
+                warn!("usage ledger is not saved: {e}");
+                UsageLedger::new(usage_config)
+            }
+        }));
+        for (profile_name, profile) in config_file.profiles() {
+            if profile.backend.is_billed() {
+                store.set_billed(profile_name);
+            }
+        }
+        for (profile_name, client) in clients {
+            store.set_client(&profile_name, client);
+        }
+        for (profile_name, fallback) in fallbacks {
+            let mut fallback = fallback.clone();
+            fallback.profiles.retain(|name| {
+                let nested = matches!(
+                    config_file
+                        .profiles()
+                        .get(name)
+                        .map(|profile| &profile.backend),
+                    Some(ProfileConfig::Fallback(_))
+                );
+                if nested {
+                    warn!("profile {profile_name} cannot fall back to fallback profile {name}");
+                }
+                !nested
+            });
+            store.set_client(
+                profile_name,
+                Arc::new(FallbackClient::from_config(&fallback, store.clone())),
+            );
+        }
+        for (profile_name, comparison) in comparisons {
+            let mut comparison = comparison.clone();
+            let composite = |name: &String| {
+                let composite = matches!(
+                    config_file
+                        .profiles()
+                        .get(name)
+                        .map(|profile| &profile.backend),
+                    Some(ProfileConfig::Fallback(_) | ProfileConfig::Comparison(_))
+                );
+                if composite {
+                    warn!("profile {profile_name} cannot compare with composite profile {name}");
+                }
+                composite
+            };
+            if composite(&comparison.primary) {
+                continue;
+            }
+            comparison.others.retain(|name| !composite(name));
+            store.set_client(
+                profile_name,
+                Arc::new(ComparisonClient::from_config(&comparison, store.clone())),
+            );
+        }
+        for (profile_name, profile) in config_file.profiles() {
+            if profile.preconnect {
+                store.set_preconnect(profile_name);
+            }
+            if profile.post_process != PostProcessConfig::default() {
+                let post_processor =
+                    PostProcessor::new(&profile.post_process).map_err(|source| {
+                        DictypeError::PostProcess {
+                            profile: profile_name.clone(),
+                            source,
+                        }
+                    })?;
+                store.set_post_processor(profile_name, post_processor);
+            }
+        }
+        Ok(store)
     }
 
     pub fn from_clients(clients: BTreeMap<String, Arc<dyn BackendClient + Send + Sync>>) -> Self {
         Self {
             clients: Arc::new(Mutex::new(clients)),
+            sources: BTreeMap::new(),
+            preconnect: BTreeSet::new(),
+            billed: BTreeSet::new(),
+            usage_ledger: Arc::new(UsageLedger::new(UsageConfig::default())),
+            post_processors: BTreeMap::new(),
         }
     }
 
@@ -50,4 +212,85 @@ impl ClientStore {
 
         locked.get(profile_name).cloned()
     }
+
+    /// Replaces the client of a profile; running sessions keep the old one. The audio of billed
+    /// profiles is metered.
+    pub fn set_client(&self, profile_name: &str, client: Arc<dyn BackendClient + Send + Sync>) {
+        let client: Arc<dyn BackendClient + Send + Sync> = if self.billed.contains(profile_name) {
+            Arc::new(MeteredClient::new(
+                profile_name.to_string(),
+                client,
+                self.usage_ledger.clone(),
+            ))
+        } else {
+            client
+        };
+        let mut locked = self.clients.lock().expect("locking asr clients");
+
+        locked.insert(profile_name.to_string(), client);
+    }
+
+    pub fn get_audio_source_for_profile(&self, profile_name: &str) -> AudioSource {
+        self.sources.get(profile_name).copied().unwrap_or_default()
+    }
+
+    pub fn set_preconnect(&mut self, profile_name: &str) {
+        self.preconnect.insert(profile_name.to_string());
+    }
+
+    pub fn is_preconnect_profile(&self, profile_name: &str) -> bool {
+        self.preconnect.contains(profile_name)
+    }
+
+    pub fn preconnect_profiles(&self) -> impl Iterator<Item = &str> {
+        self.preconnect.iter().map(String::as_str)
+    }
+
+    /// Marks a profile as billed; clients set afterwards are metered.
+    pub fn set_billed(&mut self, profile_name: &str) {
+        self.billed.insert(profile_name.to_string());
+    }
+
+    pub fn set_usage_ledger(&mut self, usage_ledger: Arc<UsageLedger>) {
+        self.usage_ledger = usage_ledger;
+    }
+
+    pub fn usage_ledger(&self) -> &UsageLedger {
+        &self.usage_ledger
+    }
+
+    pub fn set_post_processor(&mut self, profile_name: &str, post_processor: PostProcessor) {
+        self.post_processors
+            .insert(profile_name.to_string(), Arc::new(post_processor));
+    }
+
+    pub fn get_post_processor_for_profile(&self, profile_name: &str) -> Option<Arc<PostProcessor>> {
+        self.post_processors.get(profile_name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_post_processing_fails_the_load() {
+        let config = ConfigFile::parse(
+            r#"
+            [Profiles.Profile1]
+            Backend = "Whisper"
+            Config = { model_path = "/nonexistent/ggml-base.bin" }
+            PostProcess = { replacements = [{ pattern = "(", replacement = "" }] }
+            "#,
+        )
+        .unwrap();
+
+        let Err(err) = ClientStore::load(&config) else {
+            panic!("must fail")
+        };
+        assert!(
+            err.to_string()
+                .starts_with("invalid post-processing of profile Profile1")
+        );
+    }
 }
@@ -0,0 +1,284 @@
+use std::io;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_stream::stream;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_util::bytes::Bytes;
+use tracing::{info, warn};
+
+use base_client::audio_stream::AudioStream;
+use base_client::grpc_server::TranscribeResponse;
+use base_client::transcribe_stream::TranscribeStream;
+use config_tool::profile_config::ComparisonConfig;
+
+use crate::client::{BackendClient, SessionOptions};
+use crate::client_store::ClientStore;
+
+type AudioSender = mpsc::UnboundedSender<io::Result<Bytes>>;
+
+/// Transcribes the same audio with the clients of several profiles. The primary's results are
+/// passed on; everyone's are logged with the time since the session started.
+pub struct ComparisonClient {
+    primary: String,
+    others: Vec<String>,
+    client_store: ClientStore,
+}
+
+impl ComparisonClient {
+    pub const fn new(primary: String, others: Vec<String>, client_store: ClientStore) -> Self {
+        Self {
+            primary,
+            others,
+            client_store,
+        }
+    }
+
+    pub fn from_config(config: &ComparisonConfig, client_store: ClientStore) -> Self {
+        Self::new(config.primary.clone(), config.others.clone(), client_store)
+    }
+
+    fn others(&self) -> Vec<(String, Arc<dyn BackendClient + Send + Sync>)> {
+        self.others
+            .iter()
+            .filter_map(|profile_name| {
+                let client = self.client_store.get_asr_client_for_profile(profile_name);
+                if client.is_none() {
+                    warn!("comparison profile not found: {profile_name}");
+                }
+                Some((profile_name.clone(), client?))
+            })
+            .collect()
+    }
+}
+
+/// Logs the results of one profile, for comparing them afterwards.
+struct ResultLog {
+    profile_name: String,
+    session_start: Instant,
+    responded: bool,
+}
+
+impl ResultLog {
+    const fn new(profile_name: String, session_start: Instant) -> Self {
+        Self {
+            profile_name,
+            session_start,
+            responded: false,
+        }
+    }
+
+    fn log(&mut self, result: &Result<TranscribeResponse, anyhow::Error>) {
+        let elapsed_ms = self.session_start.elapsed().as_millis();
+        let profile_name = &self.profile_name;
+        match result {
+            Ok(response) => {
+                if !self.responded {
+                    self.responded = true;
+                    info!("comparison: {profile_name} first responded after {elapsed_ms} ms");
+                }
+                if response.sentence_end {
+                    info!(
+                        "comparison: {profile_name} at {elapsed_ms} ms, sentence from {} ms: {}",
+                        response.begin_time, response.text
+                    );
+                }
+            }
+            Err(e) => warn!("comparison: {profile_name} failed at {elapsed_ms} ms: {e:#}"),
+        }
+    }
+}
+
+fn audio_channel() -> (AudioSender, AudioStream) {
+    let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+    (
+        audio_tx,
+        AudioStream(Box::pin(UnboundedReceiverStream::new(audio_rx))),
+    )
+}
+
+/// Copies the captured audio to every backend, until it ends.
+async fn fan_out(mut audio_stream: AudioStream, senders: Vec<AudioSender>) {
+    while let Some(chunk) = audio_stream.next().await {
+        match chunk {
+            Ok(chunk) => {
+                for sender in &senders {
+                    let _ = sender.send(Ok(chunk.clone()));
+                }
+            }
+            Err(err) => {
+                for sender in &senders {
+                    let _ = sender.send(Err(io::Error::new(err.kind(), err.to_string())));
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for ComparisonClient {
+    async fn create_transcription_stream(
+        &self,
+        audio_stream: AudioStream,
+        options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        let primary = self
+            .client_store
+            .get_asr_client_for_profile(&self.primary)
+            .ok_or_else(|| anyhow!("primary profile not found: {}", self.primary))?;
+        let session_start = Instant::now();
+
+        let (primary_tx, primary_audio) = audio_channel();
+        let mut transcription = primary
+            .create_transcription_stream(primary_audio, options)
+            .await?;
+
+        let mut senders = vec![primary_tx];
+        for (profile_name, client) in self.others() {
+            let (audio_tx, backend_audio) = audio_channel();
+            senders.push(audio_tx);
+            let options = options.clone();
+            tokio::spawn(async move {
+                let mut log = ResultLog::new(profile_name, session_start);
+                match client
+                    .create_transcription_stream(backend_audio, &options)
+                    .await
+                {
+                    Ok(mut transcription) => {
+                        while let Some(result) = transcription.next().await {
+                            log.log(&result);
+                        }
+                    }
+                    Err(e) => log.log(&Err(e)),
+                }
+            });
+        }
+        tokio::spawn(fan_out(audio_stream, senders));
+
+        let mut log = ResultLog::new(self.primary.clone(), session_start);
+        Ok(TranscribeStream::new(Box::pin(stream! {
+            while let Some(result) = transcription.next().await {
+                log.log(&result);
+                yield result;
+            }
+        })))
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        for (profile_name, client) in self.others() {
+            if let Err(e) = client.prepare().await {
+                warn!("failed to prepare comparison profile {profile_name}: {e:#}");
+            }
+        }
+        match self.client_store.get_asr_client_for_profile(&self.primary) {
+            Some(client) => client.prepare().await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    /// Responds with the number of bytes received once the audio ends, and reports it.
+    struct CountingClient(mpsc::UnboundedSender<usize>);
+
+    #[async_trait::async_trait]
+    impl BackendClient for CountingClient {
+        async fn create_transcription_stream(
+            &self,
+            mut audio_stream: AudioStream,
+            _options: &SessionOptions,
+        ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+            let counts = self.0.clone();
+            Ok(TranscribeStream::new(Box::pin(stream! {
+                let mut bytes = 0;
+                while let Some(Ok(chunk)) = audio_stream.next().await {
+                    bytes += chunk.len();
+                }
+                let _ = counts.send(bytes);
+                yield Ok(TranscribeResponse {
+                    text: bytes.to_string(),
+                    begin_time: 0,
+                    sentence_end: true,
+                    translation: None,
+                });
+            })))
+        }
+    }
+
+    struct FailingClient;
+
+    #[async_trait::async_trait]
+    impl BackendClient for FailingClient {
+        async fn create_transcription_stream(
+            &self,
+            _audio_stream: AudioStream,
+            _options: &SessionOptions,
+        ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+            Err(anyhow!("unauthorized"))
+        }
+    }
+
+    fn comparison(
+        primary: &str,
+        others: &[&str],
+    ) -> (ComparisonClient, mpsc::UnboundedReceiver<usize>) {
+        let (counts_tx, counts_rx) = mpsc::unbounded_channel();
+        let mut clients = BTreeMap::<String, Arc<dyn BackendClient + Send + Sync>>::new();
+        clients.insert(
+            "first".to_string(),
+            Arc::new(CountingClient(counts_tx.clone())),
+        );
+        clients.insert("second".to_string(), Arc::new(CountingClient(counts_tx)));
+        clients.insert("failing".to_string(), Arc::new(FailingClient));
+        let client = ComparisonClient::new(
+            primary.to_string(),
+            others.iter().map(ToString::to_string).collect(),
+            ClientStore::from_clients(clients),
+        );
+        (client, counts_rx)
+    }
+
+    fn audio() -> AudioStream {
+        let chunks = (0..10).map(|_| Ok(Bytes::from(vec![0u8; 320])));
+        AudioStream(Box::pin(tokio_stream::iter(chunks)))
+    }
+
+    async fn transcribe(client: &ComparisonClient) -> Result<Vec<String>, String> {
+        let transcription = client
+            .create_transcription_stream(audio(), &SessionOptions::default())
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(transcription
+            .map(|event| event.unwrap().text)
+            .collect()
+            .await)
+    }
+
+    #[tokio::test]
+    async fn every_profile_gets_the_audio() {
+        let (client, mut counts) = comparison("first", &["second", "failing", "missing"]);
+        assert_eq!(transcribe(&client).await, Ok(vec!["3200".to_string()]));
+        assert_eq!(counts.recv().await, Some(3200));
+        assert_eq!(counts.recv().await, Some(3200));
+    }
+
+    #[tokio::test]
+    async fn fails_when_primary_fails() {
+        let (client, _counts) = comparison("failing", &["first"]);
+        assert_eq!(transcribe(&client).await, Err("unauthorized".to_string()));
+
+        let (client, _counts) = comparison("missing", &["first"]);
+        assert_eq!(
+            transcribe(&client).await,
+            Err("primary profile not found: missing".to_string())
+        );
+    }
+}
@@ -2,6 +2,12 @@
 pub enum DictypeError {
     #[error("Io error: {0}")]
     Io(#[from] std::io::Error),
+
+    #[error("invalid post-processing of profile {profile}: {source}")]
+    PostProcess {
+        profile: String,
+        source: regex::Error,
+    },
 }
 
 impl From<DictypeError> for tonic::Status {
@@ -0,0 +1,329 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_stream::stream;
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio::time::{Instant, sleep_until};
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_util::bytes::Bytes;
+use tracing::{info, warn};
+
+use base_client::audio_stream::AudioStream;
+use base_client::transcribe_stream::TranscribeStream;
+use base_client::vad::{VadConfig, VoiceActivityDetector};
+use config_tool::profile_config::FallbackConfig;
+
+use crate::client::{BackendClient, SessionOptions};
+use crate::client_store::ClientStore;
+
+/// Tries the clients of other profiles in order. Until one of them responds, the captured audio
+/// is kept, so that the next one transcribes the session from its start. A profile's time to
+/// respond is counted from the first speech, so silence before it does not cause a failover.
+pub struct FallbackClient {
+    profiles: Vec<String>,
+    first_response_timeout: Duration,
+    client_store: ClientStore,
+}
+
+impl FallbackClient {
+    pub const fn new(
+        profiles: Vec<String>,
+        first_response_timeout: Duration,
+        client_store: ClientStore,
+    ) -> Self {
+        Self {
+            profiles,
+            first_response_timeout,
+            client_store,
+        }
+    }
+
+    pub fn from_config(config: &FallbackConfig, client_store: ClientStore) -> Self {
+        Self::new(
+            config.profiles.clone(),
+            Duration::from_secs(config.first_response_timeout_secs().into()),
+            client_store,
+        )
+    }
+
+    fn clients(&self) -> Vec<(String, Arc<dyn BackendClient + Send + Sync>)> {
+        self.profiles
+            .iter()
+            .filter_map(|profile_name| {
+                let client = self.client_store.get_asr_client_for_profile(profile_name);
+                if client.is_none() {
+                    warn!("fallback profile not found: {profile_name}");
+                }
+                Some((profile_name.clone(), client?))
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for FallbackClient {
+    async fn create_transcription_stream(
+        &self,
+        mut audio_stream: AudioStream,
+        options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        let clients = self.clients();
+        if clients.is_empty() {
+            return Err(anyhow!("no profiles to fall back to"));
+        }
+        let options = options.clone();
+        let first_response_timeout = self.first_response_timeout;
+
+        Ok(TranscribeStream::new(Box::pin(stream! {
+            // Audio captured so far, until a profile responds.
+            let mut captured: Vec<Bytes> = Vec::new();
+            let mut audio_ended = false;
+            let mut vad = VoiceActivityDetector::new(VadConfig::default());
+            let mut speech_started = false;
+            let mut last_error = None;
+            let count = clients.len();
+
+            'profiles: for (index, (profile_name, client)) in clients.into_iter().enumerate() {
+                let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+                for chunk in &captured {
+                    let _ = audio_tx.send(Ok(chunk.clone()));
+                }
+                let mut audio_tx = (!audio_ended).then_some(audio_tx);
+                let backend_audio = AudioStream(Box::pin(UnboundedReceiverStream::new(audio_rx)));
+                // The last profile gets as long as it needs.
+                let timed = index + 1 < count;
+                let connect_deadline = timed.then(|| Instant::now() + first_response_timeout);
+
+                let started = match connect_deadline {
+                    Some(deadline) => tokio::time::timeout_at(deadline, client.create_transcription_stream(backend_audio, &options))
+                        .await
+                        .unwrap_or_else(|_| Err(anyhow!("timed out connecting"))),
+                    None => client.create_transcription_stream(backend_audio, &options).await,
+                };
+                let mut transcription = match started {
+                    Ok(transcription) => transcription,
+                    Err(e) => {
+                        warn!("profile {profile_name} failed to start: {e:#}");
+                        last_error = Some(e);
+                        continue;
+                    }
+                };
+
+                let mut responded = false;
+                // Set once the profile has been fed speech, or the end of the audio.
+                let mut deadline = (timed && (speech_started || audio_ended))
+                    .then(|| Instant::now() + first_response_timeout);
+                loop {
+                    select! {
+                        chunk = audio_stream.next(), if !audio_ended => match chunk {
+                            Some(Ok(chunk)) => {
+                                if !responded {
+                                    captured.push(chunk.clone());
+                                }
+                                if !speech_started {
+                                    let ended = vad.push(&chunk);
+                                    speech_started = !ended.is_empty() || vad.current().is_some();
+                                }
+                                if timed && speech_started && deadline.is_none() {
+                                    deadline = Some(Instant::now() + first_response_timeout);
+                                }
+                                if let Some(audio_tx) = &audio_tx {
+                                    let _ = audio_tx.send(Ok(chunk));
+                                }
+                            }
+                            Some(Err(err)) => {
+                                if let Some(audio_tx) = &audio_tx {
+                                    let _ = audio_tx.send(Err(err));
+                                }
+                            }
+                            None => {
+                                audio_ended = true;
+                                audio_tx = None;
+                                if timed && deadline.is_none() {
+                                    deadline = Some(Instant::now() + first_response_timeout);
+                                }
+                            }
+                        },
+                        response = transcription.next() => match response {
+                            Some(Ok(response)) => {
+                                if !responded {
+                                    info!("transcribing with profile: {profile_name}");
+                                    responded = true;
+                                    captured = Vec::new();
+                                }
+                                yield Ok(response);
+                            }
+                            Some(Err(e)) if responded => {
+                                yield Err(e);
+                                return;
+                            }
+                            Some(Err(e)) => {
+                                warn!("profile {profile_name} failed: {e:#}");
+                                last_error = Some(e);
+                                continue 'profiles;
+                            }
+                            None if responded || audio_ended => return,
+                            None => {
+                                warn!("profile {profile_name} ended early");
+                                last_error = Some(anyhow!("profile {profile_name} ended early"));
+                                continue 'profiles;
+                            }
+                        },
+                        () = sleep_until(deadline.unwrap_or_else(Instant::now)), if !responded && deadline.is_some() => {
+                            warn!("profile {profile_name} did not respond within {first_response_timeout:?}");
+                            last_error = Some(anyhow!("profile {profile_name} did not respond in time"));
+                            continue 'profiles;
+                        }
+                    }
+                }
+            }
+
+            yield Err(last_error.unwrap_or_else(|| anyhow!("no profile could transcribe")));
+        })))
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        match self.clients().first() {
+            Some((_, client)) => client.prepare().await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use base_client::grpc_server::TranscribeResponse;
+
+    struct FailingClient;
+
+    #[async_trait::async_trait]
+    impl BackendClient for FailingClient {
+        async fn create_transcription_stream(
+            &self,
+            _audio_stream: AudioStream,
+            _options: &SessionOptions,
+        ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+            Err(anyhow!("unauthorized"))
+        }
+    }
+
+    /// Takes all audio but never responds.
+    struct SilentClient;
+
+    #[async_trait::async_trait]
+    impl BackendClient for SilentClient {
+        async fn create_transcription_stream(
+            &self,
+            mut audio_stream: AudioStream,
+            _options: &SessionOptions,
+        ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+            Ok(TranscribeStream::new(Box::pin(stream! {
+                while audio_stream.next().await.is_some() {}
+                std::future::pending::<()>().await;
+                yield Err(anyhow!("unreachable"));
+            })))
+        }
+    }
+
+    /// Responds with the number of bytes received once the audio ends.
+    struct CountingClient;
+
+    #[async_trait::async_trait]
+    impl BackendClient for CountingClient {
+        async fn create_transcription_stream(
+            &self,
+            mut audio_stream: AudioStream,
+            _options: &SessionOptions,
+        ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+            Ok(TranscribeStream::new(Box::pin(stream! {
+                let mut bytes = 0;
+                while let Some(Ok(chunk)) = audio_stream.next().await {
+                    bytes += chunk.len();
+                }
+                yield Ok(TranscribeResponse {
+                    text: bytes.to_string(),
+                    begin_time: 0,
+                    sentence_end: true,
+                    translation: None,
+                });
+            })))
+        }
+    }
+
+    fn fallback(profiles: &[&str]) -> FallbackClient {
+        let mut clients = BTreeMap::<String, Arc<dyn BackendClient + Send + Sync>>::new();
+        clients.insert("failing".to_string(), Arc::new(FailingClient));
+        clients.insert("silent".to_string(), Arc::new(SilentClient));
+        clients.insert("counting".to_string(), Arc::new(CountingClient));
+        FallbackClient::new(
+            profiles.iter().map(ToString::to_string).collect(),
+            Duration::from_millis(50),
+            ClientStore::from_clients(clients),
+        )
+    }
+
+    fn audio() -> AudioStream {
+        let chunks = (0..10).map(|_| Ok(Bytes::from(vec![0u8; 320])));
+        AudioStream(Box::pin(tokio_stream::iter(chunks)))
+    }
+
+    /// Silence that lasts longer than the first response timeout.
+    fn slow_silence() -> AudioStream {
+        AudioStream(Box::pin(stream! {
+            for _ in 0..10 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                yield Ok(Bytes::from(vec![0u8; 320]));
+            }
+        }))
+    }
+
+    async fn transcribe(client: &FallbackClient) -> Vec<Result<String, String>> {
+        transcribe_audio(client, audio()).await
+    }
+
+    async fn transcribe_audio(
+        client: &FallbackClient,
+        audio: AudioStream,
+    ) -> Vec<Result<String, String>> {
+        client
+            .create_transcription_stream(audio, &SessionOptions::default())
+            .await
+            .unwrap()
+            .map(|event| event.map(|e| e.text).map_err(|e| e.to_string()))
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn falls_back_when_start_fails() {
+        let client = fallback(&["failing", "missing", "counting"]);
+        assert_eq!(transcribe(&client).await, [Ok("3200".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn falls_back_with_captured_audio_when_first_response_is_late() {
+        let client = fallback(&["silent", "counting"]);
+        assert_eq!(transcribe(&client).await, [Ok("3200".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn silence_does_not_count_towards_first_response() {
+        let client = fallback(&["counting", "failing"]);
+        assert_eq!(
+            transcribe_audio(&client, slow_silence()).await,
+            [Ok("3200".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_when_every_profile_fails() {
+        let client = fallback(&["silent", "failing"]);
+        assert_eq!(transcribe(&client).await, [Err("unauthorized".to_string())]);
+    }
+}
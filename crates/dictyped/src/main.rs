@@ -2,10 +2,16 @@
 
 mod client;
 mod client_store;
+mod comparison_client;
 mod error;
+mod fallback_client;
+mod metered_client;
+mod post_processor;
 mod service;
 mod service_state;
 mod session_stream;
+mod usage_ledger;
+mod vocabulary_manager;
 
 #[cfg(unix)]
 use std::os::fd::AsRawFd;
@@ -52,19 +58,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let config = {
         let path = get_config_path()?;
-        let content = fs::read_to_string(&path)?;
-
-        ConfigFile::parse(&content).unwrap_or_else(|err| {
-            warn!("failed to load config, using defaults: {err}");
-            ConfigFile::default()
-        })
+        match fs::read_to_string(&path) {
+            Ok(content) => ConfigFile::parse(&content)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                warn!("{} not found, using defaults", path.display());
+                ConfigFile::default()
+            }
+            Err(err) => return Err(err.into()),
+        }
     };
 
     let recorder = PulseAudioRecorder::new(config.pulseaudio().clone())?;
-    let service = DictypeService::<PulseAudioRecorder>::new(
-        client_store::ClientStore::load(&config),
-        recorder,
-    );
+    let client_store = client_store::ClientStore::load(&config)?;
+    tokio::spawn(vocabulary_manager::sync(
+        config.clone(),
+        client_store.clone(),
+    ));
+    let service = DictypeService::<PulseAudioRecorder>::new(client_store, recorder);
     let incoming = UnixListenerStream::new(listener);
 
     info!("listening on {}", socket_path.display());
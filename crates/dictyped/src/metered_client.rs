@@ -0,0 +1,199 @@
+use std::sync::{Arc, OnceLock};
+
+use anyhow::anyhow;
+use async_stream::stream;
+use tokio_stream::StreamExt;
+use tracing::warn;
+
+use base_client::audio_stream::AudioStream;
+use base_client::transcribe_stream::TranscribeStream;
+use base_client::vad::samples_to_ms;
+
+use crate::client::{BackendClient, SessionOptions};
+use crate::usage_ledger::UsageLedger;
+
+/// Records the audio sent to a billed backend in the usage ledger. Sessions are refused once a
+/// usage cap is reached, and running ones stop being sent audio, so that they end.
+pub struct MeteredClient {
+    profile_name: String,
+    client: Arc<dyn BackendClient + Send + Sync>,
+    ledger: Arc<UsageLedger>,
+}
+
+impl MeteredClient {
+    pub fn new(
+        profile_name: String,
+        client: Arc<dyn BackendClient + Send + Sync>,
+        ledger: Arc<UsageLedger>,
+    ) -> Self {
+        Self {
+            profile_name,
+            client,
+            ledger,
+        }
+    }
+}
+
+/// Saves the ledger off the async threads, since it writes a file.
+fn save_in_background(ledger: &Arc<UsageLedger>) {
+    let ledger = ledger.clone();
+    match tokio::runtime::Handle::try_current() {
+        Ok(runtime) => drop(runtime.spawn_blocking(move || ledger.save())),
+        Err(_) => ledger.save(),
+    }
+}
+
+/// Saves the ledger once a session's audio ends.
+struct SaveOnDrop(Arc<UsageLedger>);
+
+impl Drop for SaveOnDrop {
+    fn drop(&mut self) {
+        save_in_background(&self.0);
+    }
+}
+
+#[async_trait::async_trait]
+impl BackendClient for MeteredClient {
+    async fn create_transcription_stream(
+        &self,
+        mut audio_stream: AudioStream,
+        options: &SessionOptions,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        if let Some(refusal) = self.ledger.refusal() {
+            return Err(anyhow!(refusal));
+        }
+
+        let stopped_by_cap = Arc::new(OnceLock::<String>::new());
+        let profile_name = self.profile_name.clone();
+        let ledger = self.ledger.clone();
+        let stopped = stopped_by_cap.clone();
+        let metered_audio = AudioStream(Box::pin(stream! {
+            let _save = SaveOnDrop(ledger.clone());
+            let mut sent_bytes = 0;
+            while let Some(chunk) = audio_stream.next().await {
+                if let Ok(chunk) = &chunk {
+                    let sent_ms = samples_to_ms(sent_bytes / 2);
+                    sent_bytes += chunk.len();
+                    let ms = u64::from(samples_to_ms(sent_bytes / 2) - sent_ms);
+                    if ledger.record(&profile_name, ms) {
+                        save_in_background(&ledger);
+                    }
+                }
+                yield chunk;
+                if let Some(refusal) = ledger.refusal() {
+                    warn!("stopping session of {profile_name}: {refusal}");
+                    let _ = stopped.set(refusal);
+                    break;
+                }
+            }
+        }));
+
+        let mut transcription = self
+            .client
+            .create_transcription_stream(metered_audio, options)
+            .await?;
+        Ok(TranscribeStream::new(Box::pin(stream! {
+            while let Some(result) = transcription.next().await {
+                yield result;
+            }
+            if let Some(refusal) = stopped_by_cap.get() {
+                yield Err(anyhow!(refusal.clone()));
+            }
+        })))
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        self.client.prepare().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_util::bytes::Bytes;
+
+    use base_client::grpc_server::TranscribeResponse;
+    use config_tool::usage_config::UsageConfig;
+
+    use super::*;
+
+    /// Responds with the milliseconds of audio received once it ends.
+    struct DurationClient;
+
+    #[async_trait::async_trait]
+    impl BackendClient for DurationClient {
+        async fn create_transcription_stream(
+            &self,
+            mut audio_stream: AudioStream,
+            _options: &SessionOptions,
+        ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+            Ok(TranscribeStream::new(Box::pin(stream! {
+                let mut bytes = 0;
+                while let Some(Ok(chunk)) = audio_stream.next().await {
+                    bytes += chunk.len();
+                }
+                yield Ok(TranscribeResponse {
+                    text: samples_to_ms(bytes / 2).to_string(),
+                    begin_time: 0,
+                    sentence_end: true,
+                    translation: None,
+                });
+            })))
+        }
+    }
+
+    fn metered(daily_cap_secs: Option<u64>) -> MeteredClient {
+        let ledger = UsageLedger::new(UsageConfig {
+            daily_cap_secs,
+            monthly_cap_secs: None,
+        });
+        MeteredClient::new(
+            "Paraformer".to_string(),
+            Arc::new(DurationClient),
+            Arc::new(ledger),
+        )
+    }
+
+    /// `secs` of audio in 100ms chunks.
+    fn audio(secs: usize) -> AudioStream {
+        let chunks = (0..secs * 10).map(|_| Ok(Bytes::from(vec![0u8; 3200])));
+        AudioStream(Box::pin(tokio_stream::iter(chunks)))
+    }
+
+    async fn transcribe(client: &MeteredClient, secs: usize) -> Result<Vec<String>, String> {
+        let transcription = client
+            .create_transcription_stream(audio(secs), &SessionOptions::default())
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(transcription
+            .map(|event| event.map_or_else(|e| e.to_string(), |event| event.text))
+            .collect()
+            .await)
+    }
+
+    #[tokio::test]
+    async fn records_audio_sent() {
+        let client = metered(None);
+        assert_eq!(transcribe(&client, 3).await, Ok(vec!["3000".to_string()]));
+        assert_eq!(transcribe(&client, 2).await, Ok(vec!["2000".to_string()]));
+
+        let usage = client.ledger.usage();
+        assert_eq!(usage.today_ms, 5000);
+        assert_eq!(usage.profiles[0].profile_name, "Paraformer");
+    }
+
+    #[tokio::test]
+    async fn stops_at_cap_and_refuses_new_sessions() {
+        let client = metered(Some(2));
+        assert_eq!(
+            transcribe(&client, 5).await,
+            Ok(vec![
+                "2000".to_string(),
+                "usage daily cap of 2s reached".to_string()
+            ])
+        );
+        assert_eq!(
+            transcribe(&client, 1).await,
+            Err("usage daily cap of 2s reached".to_string())
+        );
+    }
+}
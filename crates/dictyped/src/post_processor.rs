@@ -0,0 +1,311 @@
+use regex::{NoExpand, Regex};
+
+use config_tool::post_process_config::{PostProcessConfig, PunctuationWidth};
+
+/// Applies the `PostProcess` rules of a profile to the text of its results. Each text is
+/// rewritten on its own, so that a partial result reads the same as the start of its final one.
+#[derive(Debug)]
+pub struct PostProcessor {
+    dictionary: Vec<(Regex, String)>,
+    replacements: Vec<(Regex, String)>,
+    punctuation: Option<PunctuationWidth>,
+    cjk_latin_spacing: bool,
+    capitalize_sentences: bool,
+}
+
+impl PostProcessor {
+    pub fn new(config: &PostProcessConfig) -> Result<Self, regex::Error> {
+        // Longer phrases first, so that they win over phrases they contain.
+        let mut phrases: Vec<_> = config.dictionary.iter().flatten().collect();
+        phrases.sort_by_key(|(phrase, _)| std::cmp::Reverse(phrase.chars().count()));
+        let dictionary = phrases
+            .into_iter()
+            .map(|(phrase, replacement)| Ok((phrase_regex(phrase)?, replacement.clone())))
+            .collect::<Result<_, regex::Error>>()?;
+        let replacements = config
+            .replacements
+            .iter()
+            .flatten()
+            .map(|replacement| {
+                Ok((
+                    Regex::new(&replacement.pattern)?,
+                    replacement.replacement.clone(),
+                ))
+            })
+            .collect::<Result<_, regex::Error>>()?;
+        Ok(Self {
+            dictionary,
+            replacements,
+            punctuation: config.punctuation,
+            cjk_latin_spacing: config.cjk_latin_spacing.unwrap_or(false),
+            capitalize_sentences: config.capitalize_sentences.unwrap_or(false),
+        })
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (regex, replacement) in &self.dictionary {
+            text = regex.replace_all(&text, NoExpand(replacement)).into_owned();
+        }
+        for (regex, replacement) in &self.replacements {
+            text = regex.replace_all(&text, replacement.as_str()).into_owned();
+        }
+        match self.punctuation {
+            Some(PunctuationWidth::HalfWidth) => text = half_width(&text),
+            Some(PunctuationWidth::FullWidth) => text = full_width(&text),
+            None => {}
+        }
+        if self.cjk_latin_spacing {
+            text = cjk_latin_spacing(&text);
+        }
+        if self.capitalize_sentences {
+            text = capitalize_sentences(&text);
+        }
+        text
+    }
+}
+
+/// Matches `phrase` regardless of case. Where it starts or ends with a Latin letter or digit, it
+/// has to be a whole word there; CJK text around it does not count as part of the word.
+fn phrase_regex(phrase: &str) -> Result<Regex, regex::Error> {
+    let boundary = |c: Option<char>| {
+        if c.is_some_and(|c| c.is_ascii_alphanumeric()) {
+            r"(?-u:\b)"
+        } else {
+            ""
+        }
+    };
+    Regex::new(&format!(
+        "(?i){}{}{}",
+        boundary(phrase.chars().next()),
+        regex::escape(phrase),
+        boundary(phrase.chars().next_back()),
+    ))
+}
+
+/// Whether `c` is a Han, kana or CJK radical character. CJK punctuation is not.
+const fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2E80}'..='\u{2FDF}'
+            | '\u{3040}'..='\u{30FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FFFF}'
+    )
+}
+
+/// Turns full-width punctuation, letters and digits into ASCII, with a space after punctuation
+/// that is followed by a word.
+fn half_width(text: &str) -> String {
+    let mut half = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let ascii = match c {
+            '。' => '.',
+            '、' => ',',
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(u32::from(c) - 0xFEE0).unwrap_or(c),
+            _ => {
+                half.push(c);
+                continue;
+            }
+        };
+        let after_digit = half.ends_with(|c: char| c.is_ascii_digit());
+        if ascii == '(' && half.ends_with(char::is_alphanumeric) {
+            half.push(' ');
+        }
+        half.push(ascii);
+        let next = chars.peek().copied();
+        let in_number = after_digit && next.is_some_and(|c| c.is_ascii_digit() || is_wide_digit(c));
+        if matches!(ascii, ',' | '.' | '?' | '!' | ':' | ';' | ')')
+            && next.is_some_and(char::is_alphanumeric)
+            && !in_number
+        {
+            half.push(' ');
+        }
+    }
+    half
+}
+
+const fn is_wide_digit(c: char) -> bool {
+    matches!(c, '０'..='９')
+}
+
+/// Turns ASCII punctuation after CJK characters into its full-width form, dropping the spaces
+/// after it.
+fn full_width(text: &str) -> String {
+    let mut full = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let wide = match c {
+            '.' => '。',
+            ',' => '，',
+            '?' | '!' | ':' | ';' => char::from_u32(u32::from(c) + 0xFEE0).unwrap_or(c),
+            _ => c,
+        };
+        if wide != c && full.ends_with(is_cjk) {
+            full.push(wide);
+            while chars.next_if(|c| *c == ' ').is_some() {}
+        } else {
+            full.push(c);
+        }
+    }
+    full
+}
+
+/// Puts a space between CJK characters and Latin letters or digits, where there is none.
+fn cjk_latin_spacing(text: &str) -> String {
+    let mut spaced = String::with_capacity(text.len());
+    let mut previous = None;
+    for c in text.chars() {
+        if let Some(previous) = previous
+            && ((is_cjk(previous) && c.is_ascii_alphanumeric())
+                || (previous.is_ascii_alphanumeric() && is_cjk(c)))
+        {
+            spaced.push(' ');
+        }
+        spaced.push(c);
+        previous = Some(c);
+    }
+    spaced
+}
+
+/// Capitalizes the first letter of the text and of each sentence in it.
+fn capitalize_sentences(text: &str) -> String {
+    let mut capitalized = String::with_capacity(text.len());
+    let mut sentence_start = true;
+    let mut after_stop = false;
+    for c in text.chars() {
+        if sentence_start && c.is_alphanumeric() {
+            capitalized.extend(c.to_uppercase());
+            sentence_start = false;
+            continue;
+        }
+        capitalized.push(c);
+        if matches!(c, '。' | '？' | '！') || (after_stop && c.is_whitespace()) {
+            sentence_start = true;
+        }
+        after_stop = matches!(c, '.' | '?' | '!');
+    }
+    capitalized
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use config_tool::post_process_config::Replacement;
+
+    use super::*;
+
+    fn processor(config: &PostProcessConfig) -> PostProcessor {
+        PostProcessor::new(config).unwrap()
+    }
+
+    #[test]
+    fn dictionary_fixes_whole_phrases_regardless_of_case() {
+        let processor = processor(&PostProcessConfig {
+            dictionary: Some(BTreeMap::from([
+                ("dye type".to_string(), "Dictype".to_string()),
+                ("dye".to_string(), "die".to_string()),
+                ("f citex".to_string(), "fcitx".to_string()),
+            ])),
+            ..PostProcessConfig::default()
+        });
+
+        assert_eq!(
+            processor.apply("Dye type runs on F citex, not dyes."),
+            "Dictype runs on fcitx, not dyes."
+        );
+        assert_eq!(processor.apply("我用dye type打字"), "我用Dictype打字");
+    }
+
+    #[test]
+    fn replacements_apply_in_order() {
+        let processor = processor(&PostProcessConfig {
+            replacements: Some(vec![
+                Replacement {
+                    pattern: r"\b(um|uh),? ".to_string(),
+                    replacement: String::new(),
+                },
+                Replacement {
+                    pattern: r"(\d+) percent".to_string(),
+                    replacement: "$1%".to_string(),
+                },
+            ]),
+            ..PostProcessConfig::default()
+        });
+
+        assert_eq!(processor.apply("um, about 5 percent"), "about 5%");
+    }
+
+    #[test]
+    fn invalid_pattern_is_an_error() {
+        let config = PostProcessConfig {
+            replacements: Some(vec![Replacement {
+                pattern: "(".to_string(),
+                replacement: String::new(),
+            }]),
+            ..PostProcessConfig::default()
+        };
+
+        assert!(PostProcessor::new(&config).is_err());
+    }
+
+    #[test]
+    fn punctuation_width() {
+        let half = processor(&PostProcessConfig {
+            punctuation: Some(PunctuationWidth::HalfWidth),
+            ..PostProcessConfig::default()
+        });
+        assert_eq!(
+            half.apply("Hello，world。Version ３．５（beta）！"),
+            "Hello, world. Version 3.5 (beta)!"
+        );
+
+        let full = processor(&PostProcessConfig {
+            punctuation: Some(PunctuationWidth::FullWidth),
+            ..PostProcessConfig::default()
+        });
+        assert_eq!(
+            full.apply("你好, 世界. Version 3.5, ok?"),
+            "你好，世界。Version 3.5, ok?"
+        );
+    }
+
+    #[test]
+    fn cjk_latin_spacing_and_capitalization() {
+        let processor = processor(&PostProcessConfig {
+            cjk_latin_spacing: Some(true),
+            capitalize_sentences: Some(true),
+            ..PostProcessConfig::default()
+        });
+
+        assert_eq!(processor.apply("我用dictype打字"), "我用 dictype 打字");
+        assert_eq!(
+            processor.apply("hello world. it works! 好的。ok"),
+            "Hello world. It works! 好的。Ok"
+        );
+    }
+
+    #[test]
+    fn partial_text_reads_like_the_start_of_the_final_one() {
+        let processor = processor(&PostProcessConfig {
+            dictionary: Some(BTreeMap::from([(
+                "dye type".to_string(),
+                "Dictype".to_string(),
+            )])),
+            punctuation: Some(PunctuationWidth::HalfWidth),
+            cjk_latin_spacing: Some(true),
+            capitalize_sentences: Some(true),
+            ..PostProcessConfig::default()
+        });
+
+        let partial = processor.apply("dye type很好用，");
+        let final_text = processor.apply("dye type很好用，really。");
+        assert_eq!(partial, "Dictype 很好用,");
+        assert_eq!(final_text, "Dictype 很好用, really.");
+    }
+}
@@ -9,9 +9,11 @@ use tracing::{Span, error, info, trace};
 
 use base_client::audio_stream::AudioCapture;
 use base_client::grpc_server::{
-    Dictype, StopRequest, StopResponse, TranscribeRequest, TranscribeResponse,
+    Dictype, PrepareRequest, PrepareResponse, StopRequest, StopResponse, TranscribeRequest,
+    TranscribeResponse, UsageRequest, UsageResponse,
 };
 
+use crate::client::{BackendClient, SessionOptions};
 use crate::client_store::ClientStore;
 use crate::service_state::ServiceState;
 use crate::session_stream::SessionStream;
@@ -59,6 +61,19 @@ where
                 Status::invalid_argument(format!("profile not found: {:?}", &req.profile_name))
             })?;
         info!("found asr client for profile: {}", &req.profile_name);
+        let audio_source = self
+            .client_store
+            .get_audio_source_for_profile(&req.profile_name);
+        let options = SessionOptions {
+            context: req.context.clone(),
+        };
+        let preconnect = self
+            .client_store
+            .is_preconnect_profile(&req.profile_name)
+            .then(|| req.profile_name.clone());
+        let post_processor = self
+            .client_store
+            .get_post_processor_for_profile(&req.profile_name);
 
         // Expose cancellation so Stop can signal this session.
         let recording_cancellation = CancellationToken::new();
@@ -76,7 +91,7 @@ where
         let recording_cancellation2 = recording_cancellation.clone();
         tokio::spawn(async move {
             trace!("starting recording");
-            let audio_stream = match recorder.create(recording_cancellation.clone()) {
+            let audio_stream = match recorder.create(audio_source, recording_cancellation.clone()) {
                 Ok(audio_stream) => audio_stream,
                 Err(e) => {
                     let _ = tx
@@ -89,7 +104,7 @@ where
             trace!("started recording");
 
             let mut client = match asr_client
-                .create_transcription_stream(audio_stream)
+                .create_transcription_stream(audio_stream, &options)
                 .await
                 .map_err(|e| Status::internal(format!("backend client connect failed: {e}")))
             {
@@ -102,7 +117,13 @@ where
             };
             loop {
                 match client.next().await {
-                    Some(Ok(evt)) => {
+                    Some(Ok(mut evt)) => {
+                        if let Some(post_processor) = &post_processor {
+                            evt.text = post_processor.apply(&evt.text);
+                            evt.translation = evt
+                                .translation
+                                .map(|translation| post_processor.apply(&translation));
+                        }
                         if tx.send(Ok(evt)).await.is_err() {
                             error!("Cannot send response to gRPC client, session stopped.");
                             break;
@@ -122,6 +143,11 @@ where
             }
 
             let _ = state.lock().expect("state poisoned").clear();
+
+            // Ready for the next session of this profile.
+            if let Some(profile_name) = preconnect {
+                prepare_client(&profile_name, asr_client.as_ref()).await;
+            }
         });
 
         let response_stream = ReceiverStream::new(rx);
@@ -134,6 +160,59 @@ where
         let response = StopResponse { stopped };
         Ok(Response::new(response))
     }
+
+    async fn prepare(
+        &self,
+        request: Request<PrepareRequest>,
+    ) -> Result<Response<PrepareResponse>, Status> {
+        let req = request.get_ref();
+        let profile_names: Vec<String> = if req.profile_name.is_empty() {
+            self.client_store
+                .preconnect_profiles()
+                .map(str::to_string)
+                .collect()
+        } else if self
+            .client_store
+            .get_asr_client_for_profile(&req.profile_name)
+            .is_none()
+        {
+            return Err(Status::invalid_argument(format!(
+                "profile not found: {:?}",
+                &req.profile_name
+            )));
+        } else if self.client_store.is_preconnect_profile(&req.profile_name) {
+            vec![req.profile_name.clone()]
+        } else {
+            Vec::new()
+        };
+
+        let mut preparing = 0;
+        for profile_name in profile_names {
+            let Some(asr_client) = self.client_store.get_asr_client_for_profile(&profile_name)
+            else {
+                continue;
+            };
+            preparing += 1;
+            tokio::spawn(async move {
+                prepare_client(&profile_name, asr_client.as_ref()).await;
+            });
+        }
+        Ok(Response::new(PrepareResponse { preparing }))
+    }
+
+    async fn usage(
+        &self,
+        _request: Request<UsageRequest>,
+    ) -> Result<Response<UsageResponse>, Status> {
+        Ok(Response::new(self.client_store.usage_ledger().usage()))
+    }
+}
+
+pub async fn prepare_client(profile_name: &str, asr_client: &(dyn BackendClient + Send + Sync)) {
+    match asr_client.prepare().await {
+        Ok(()) => trace!("prepared connection for profile: {profile_name}"),
+        Err(e) => error!("failed to prepare connection for profile {profile_name}: {e:?}"),
+    }
 }
 
 impl<R> DictypeService<R>
@@ -166,7 +245,7 @@ mod tests {
         use tokio_util::bytes::Bytes;
         use tokio_util::sync::CancellationToken;
 
-        use base_client::audio_stream::{AudioCapture, AudioStream};
+        use base_client::audio_stream::{AudioCapture, AudioSource, AudioStream};
 
         pub(super) struct NoiseRecorder {
             remaining: usize,
@@ -181,7 +260,11 @@ mod tests {
                 })
             }
 
-            fn create(&self, _cancellation_token: CancellationToken) -> io::Result<AudioStream> {
+            fn create(
+                &self,
+                _source: AudioSource,
+                _cancellation_token: CancellationToken,
+            ) -> io::Result<AudioStream> {
                 let mut remaining = self.remaining;
                 Ok(AudioStream(Box::pin(stream! {
                     let mut value = 0x1234_5678_u32;
@@ -213,7 +296,11 @@ mod tests {
                 })
             }
 
-            fn create(&self, _cancellation_token: CancellationToken) -> io::Result<AudioStream> {
+            fn create(
+                &self,
+                _source: AudioSource,
+                _cancellation_token: CancellationToken,
+            ) -> io::Result<AudioStream> {
                 let mut remaining = self.remaining;
                 let delay = self.delay;
                 Ok(AudioStream(Box::pin(stream! {
@@ -243,7 +330,11 @@ mod tests {
                 Ok(Self)
             }
 
-            fn create(&self, _cancellation_token: CancellationToken) -> io::Result<AudioStream> {
+            fn create(
+                &self,
+                _source: AudioSource,
+                _cancellation_token: CancellationToken,
+            ) -> io::Result<AudioStream> {
                 Err(io::Error::other("immediate bad capture boom!"))
             }
         }
@@ -259,7 +350,11 @@ mod tests {
                 Ok(Self { success_count })
             }
 
-            fn create(&self, _cancellation_token: CancellationToken) -> io::Result<AudioStream> {
+            fn create(
+                &self,
+                _source: AudioSource,
+                _cancellation_token: CancellationToken,
+            ) -> io::Result<AudioStream> {
                 let mut remaining = self.success_count;
                 Ok(AudioStream(Box::pin(stream! {
                     while remaining > 0 {
@@ -281,7 +376,7 @@ mod tests {
         use base_client::grpc_server::TranscribeResponse;
         use base_client::transcribe_stream::TranscribeStream;
 
-        use crate::client::BackendClient;
+        use crate::client::{BackendClient, SessionOptions};
 
         pub(super) struct ImmediateBadAsrClient;
 
@@ -290,6 +385,7 @@ mod tests {
             async fn create_transcription_stream(
                 &self,
                 _audio_stream: AudioStream,
+                _options: &SessionOptions,
             ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
                 Err(anyhow!("immediate bad asr client boom!"))
             }
@@ -304,6 +400,7 @@ mod tests {
             async fn create_transcription_stream(
                 &self,
                 mut audio_stream: AudioStream,
+                _options: &SessionOptions,
             ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
                 let remaining_success = self.remaining_success;
                 Ok(TranscribeStream::new(Box::pin(stream! {
@@ -320,6 +417,7 @@ mod tests {
                             yield Ok(TranscribeResponse {
                                 begin_time: 0,
                                 sentence_end: false,
+                                translation: None,
                                 text: "ok".to_string(),
                             });
                             success += 1;
@@ -331,14 +429,42 @@ mod tests {
             }
         }
 
+        /// Answers "yes" like `YesAsrClient`, and counts the connections prepared.
+        #[derive(Default)]
+        pub(super) struct PreparingAsrClient {
+            pub(crate) prepared: std::sync::atomic::AtomicUsize,
+        }
+
+        #[async_trait::async_trait]
+        impl BackendClient for PreparingAsrClient {
+            async fn create_transcription_stream(
+                &self,
+                audio_stream: AudioStream,
+                options: &SessionOptions,
+            ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+                YesAsrClient
+                    .create_transcription_stream(audio_stream, options)
+                    .await
+            }
+
+            async fn prepare(&self) -> Result<(), anyhow::Error> {
+                self.prepared
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
         pub(super) struct YesAsrClient;
 
         #[async_trait::async_trait]
+        /// Answers "yes", or the context of the request if there is one.
         impl BackendClient for YesAsrClient {
             async fn create_transcription_stream(
                 &self,
                 mut audio_stream: AudioStream,
+                options: &SessionOptions,
             ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+                let text = options.context.clone().unwrap_or_else(|| "yes".to_string());
                 Ok(TranscribeStream::new(Box::pin(stream! {
                     while let Some(chunk) = audio_stream.next().await {
                         let _chunk = match chunk {
@@ -351,12 +477,40 @@ mod tests {
                          yield Ok(TranscribeResponse {
                             begin_time: 0,
                             sentence_end: false,
-                            text: "yes".to_string(),
+                            translation: None,
+                            text: text.clone(),
                         })
                     };
                 })))
             }
         }
+
+        pub(super) struct TranslatingAsrClient;
+
+        #[async_trait::async_trait]
+        /// Answers "oui", translated as "yes".
+        impl BackendClient for TranslatingAsrClient {
+            async fn create_transcription_stream(
+                &self,
+                mut audio_stream: AudioStream,
+                _options: &SessionOptions,
+            ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+                Ok(TranscribeStream::new(Box::pin(stream! {
+                    while let Some(chunk) = audio_stream.next().await {
+                        if let Err(err) = chunk {
+                            yield Err(err.into());
+                            return;
+                        }
+                        yield Ok(TranscribeResponse {
+                            begin_time: 0,
+                            sentence_end: false,
+                            translation: Some("yes".to_string()),
+                            text: "oui".to_string(),
+                        });
+                    }
+                })))
+            }
+        }
     }
 
     mod mock_services {
@@ -364,12 +518,16 @@ mod tests {
         use std::sync::Arc;
 
         use base_client::audio_stream::AudioCapture;
+        use config_tool::post_process_config::PostProcessConfig;
+        use config_tool::usage_config::UsageConfig;
 
         use crate::client::BackendClient;
         use crate::client_store::ClientStore;
+        use crate::post_processor::PostProcessor;
         use crate::service::DictypeService;
         use crate::service::tests::mock_clients::*;
         use crate::service::tests::mock_recorders::*;
+        use crate::usage_ledger::UsageLedger;
 
         pub(super) fn bad_capture_service(
             success_count: usize,
@@ -425,6 +583,78 @@ mod tests {
             )
         }
 
+        pub(super) fn preconnect_service(
+            capture_count: usize,
+            preparing: Arc<PreparingAsrClient>,
+        ) -> DictypeService<NoiseRecorder> {
+            let mut clients = BTreeMap::new();
+            clients.insert(
+                "yes-asr".to_string(),
+                Arc::new(YesAsrClient {}) as Arc<dyn BackendClient + Send + Sync>,
+            );
+            clients.insert(
+                "warm".to_string(),
+                preparing as Arc<dyn BackendClient + Send + Sync>,
+            );
+            let mut client_store = ClientStore::from_clients(clients);
+            client_store.set_preconnect("warm");
+
+            DictypeService::new(
+                client_store,
+                NoiseRecorder::new(capture_count).expect("NoiseRecorder must initialize"),
+            )
+        }
+
+        /// `yes-asr` is billed, against a daily cap of `daily_cap_secs`.
+        pub(super) fn billed_service(
+            capture_count: usize,
+            daily_cap_secs: u64,
+        ) -> DictypeService<NoiseRecorder> {
+            let mut client_store = ClientStore::from_clients(BTreeMap::new());
+            client_store.set_usage_ledger(Arc::new(UsageLedger::new(UsageConfig {
+                daily_cap_secs: Some(daily_cap_secs),
+                monthly_cap_secs: None,
+            })));
+            client_store.set_billed("yes-asr");
+            client_store.set_client("yes-asr", Arc::new(YesAsrClient {}));
+
+            DictypeService::new(
+                client_store,
+                NoiseRecorder::new(capture_count).expect("NoiseRecorder must initialize"),
+            )
+        }
+
+        /// `yes-asr` types "yes" as "Yes, indeed", and `oui-asr` translates "oui" as "Yes, indeed".
+        pub(super) fn post_processed_service(
+            capture_count: usize,
+        ) -> DictypeService<NoiseRecorder> {
+            let mut clients = BTreeMap::new();
+            clients.insert(
+                "yes-asr".to_string(),
+                Arc::new(YesAsrClient {}) as Arc<dyn BackendClient + Send + Sync>,
+            );
+            clients.insert(
+                "oui-asr".to_string(),
+                Arc::new(TranslatingAsrClient {}) as Arc<dyn BackendClient + Send + Sync>,
+            );
+            let mut client_store = ClientStore::from_clients(clients);
+            let config = PostProcessConfig {
+                dictionary: Some(BTreeMap::from([(
+                    "yes".to_string(),
+                    "yes, indeed".to_string(),
+                )])),
+                capitalize_sentences: Some(true),
+                ..PostProcessConfig::default()
+            };
+            client_store.set_post_processor("yes-asr", PostProcessor::new(&config).unwrap());
+            client_store.set_post_processor("oui-asr", PostProcessor::new(&config).unwrap());
+
+            DictypeService::new(
+                client_store,
+                NoiseRecorder::new(capture_count).expect("NoiseRecorder must initialize"),
+            )
+        }
+
         pub(super) fn paced_asr_service(
             capture_count: usize,
         ) -> DictypeService<PacedNoiseRecorder> {
@@ -451,6 +681,7 @@ mod tests {
             let response = service
                 .transcribe(Request::new(TranscribeRequest {
                     profile_name: "yes-asr".to_string(),
+                    context: None,
                 }))
                 .await
                 .expect("transcribe should return a stream");
@@ -474,6 +705,7 @@ mod tests {
         let service = asr_service(1024, 0);
         let request = Request::new(TranscribeRequest {
             profile_name: "missing-profile".to_string(),
+            context: None,
         });
 
         let Err(err) = service.transcribe(request).await else {
@@ -492,6 +724,7 @@ mod tests {
         let first = service
             .transcribe(Request::new(TranscribeRequest {
                 profile_name: "missing-profile".to_string(),
+                context: None,
             }))
             .await;
         let Err(first) = first else {
@@ -502,6 +735,7 @@ mod tests {
         let second = service
             .transcribe(Request::new(TranscribeRequest {
                 profile_name: "missing-profile".to_string(),
+                context: None,
             }))
             .await;
         let Err(second) = second else {
@@ -521,6 +755,7 @@ mod tests {
             let response = service
                 .transcribe(Request::new(TranscribeRequest {
                     profile_name: "bad-asr".to_string(),
+                    context: None,
                 }))
                 .await
                 .expect("transcribe should return a stream");
@@ -554,6 +789,7 @@ mod tests {
             let response = service
                 .transcribe(Request::new(TranscribeRequest {
                     profile_name: "immediate-bad-asr".to_string(),
+                    context: None,
                 }))
                 .await
                 .expect("transcribe should return a stream");
@@ -577,6 +813,7 @@ mod tests {
             let response = service
                 .transcribe(Request::new(TranscribeRequest {
                     profile_name: "yes-asr".to_string(),
+                    context: None,
                 }))
                 .await
                 .expect("transcribe should fail");
@@ -609,6 +846,7 @@ mod tests {
             let response = service
                 .transcribe(Request::new(TranscribeRequest {
                     profile_name: "yes-asr".to_string(),
+                    context: None,
                 }))
                 .await
                 .expect("transcribe should fail");
@@ -634,6 +872,7 @@ mod tests {
         let mut first_stream = service
             .transcribe(Request::new(TranscribeRequest {
                 profile_name: "yes-asr".to_string(),
+                context: None,
             }))
             .await
             .expect("transcribe should succeed")
@@ -658,6 +897,7 @@ mod tests {
         let restarted = service
             .transcribe(Request::new(TranscribeRequest {
                 profile_name: "yes-asr".to_string(),
+                context: None,
             }))
             .await
             .expect("transcribe should succeed again after client disconnect")
@@ -677,6 +917,7 @@ mod tests {
             service
                 .transcribe(Request::new(TranscribeRequest {
                     profile_name: "yes-asr".to_string(),
+                    context: None,
                 }))
                 .await
         };
@@ -720,6 +961,7 @@ mod tests {
         let mut first_stream = service
             .transcribe(Request::new(TranscribeRequest {
                 profile_name: "yes-asr".to_string(),
+                context: None,
             }))
             .await
             .expect("transcribe should succeed")
@@ -742,6 +984,7 @@ mod tests {
         let second_err = service
             .transcribe(Request::new(TranscribeRequest {
                 profile_name: "yes-asr".to_string(),
+                context: None,
             }))
             .await
             .expect_err("service must stay busy until the current stream drains");
@@ -760,6 +1003,7 @@ mod tests {
         let restarted = service
             .transcribe(Request::new(TranscribeRequest {
                 profile_name: "yes-asr".to_string(),
+                context: None,
             }))
             .await
             .expect("transcribe should succeed after drain completes")
@@ -770,4 +1014,149 @@ mod tests {
             .expect("restarted stream should not fail");
         assert_eq!(restarted.text, "yes");
     }
+
+    #[tokio::test]
+    async fn context_is_passed_to_backend() {
+        let service = asr_service(4, 0);
+
+        let mut stream = service
+            .transcribe(Request::new(TranscribeRequest {
+                profile_name: "yes-asr".to_string(),
+                context: Some("Dictype".to_string()),
+            }))
+            .await
+            .expect("transcribe should succeed")
+            .into_inner();
+
+        while let Some(response) = stream.next().await {
+            let response = response.expect("stream should not fail");
+            assert_eq!(response.text, "Dictype");
+        }
+    }
+
+    async fn wait_for_prepared(client: &mock_clients::PreparingAsrClient, count: usize) {
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while client.prepared.load(std::sync::atomic::Ordering::SeqCst) < count {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("connection should be prepared");
+    }
+
+    #[tokio::test]
+    async fn prepare_warms_preconnect_profiles() {
+        let preparing = std::sync::Arc::new(mock_clients::PreparingAsrClient::default());
+        let service = preconnect_service(4, preparing.clone());
+
+        let prepare = async |profile_name: &str| {
+            service
+                .prepare(Request::new(PrepareRequest {
+                    profile_name: profile_name.to_string(),
+                }))
+                .await
+        };
+        assert_eq!(prepare("").await.unwrap().get_ref().preparing, 1);
+        wait_for_prepared(&preparing, 1).await;
+        assert_eq!(prepare("yes-asr").await.unwrap().get_ref().preparing, 0);
+        let Err(err) = prepare("missing-profile").await else {
+            panic!("must fail")
+        };
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        // A new connection is prepared once a session ends.
+        let response = service
+            .transcribe(Request::new(TranscribeRequest {
+                profile_name: "warm".to_string(),
+                context: None,
+            }))
+            .await
+            .expect("transcribe should return a stream");
+        let events: Vec<_> = response.into_inner().collect().await;
+        assert_eq!(events.len(), 4);
+        wait_for_prepared(&preparing, 2).await;
+    }
+
+    #[tokio::test]
+    async fn usage_is_reported_and_capped() {
+        // 8000 chunks of 2 samples each are 1s of audio.
+        let service = billed_service(8000, 1);
+
+        let response = service
+            .transcribe(Request::new(TranscribeRequest {
+                profile_name: "yes-asr".to_string(),
+                context: None,
+            }))
+            .await
+            .expect("transcribe should return a stream");
+        let events: Vec<_> = response.into_inner().collect().await;
+        let err = events
+            .last()
+            .unwrap()
+            .as_ref()
+            .expect_err("cap should end the session");
+        assert!(err.message().contains("usage daily cap of 1s reached"));
+
+        let usage = service
+            .usage(Request::new(UsageRequest {}))
+            .await
+            .expect("usage should succeed")
+            .into_inner();
+        assert_eq!(usage.today_ms, 1000);
+        assert_eq!(usage.daily_cap_ms, Some(1000));
+        assert_eq!(usage.profiles[0].profile_name, "yes-asr");
+
+        let response = service
+            .transcribe(Request::new(TranscribeRequest {
+                profile_name: "yes-asr".to_string(),
+                context: None,
+            }))
+            .await
+            .expect("transcribe should return a stream");
+        let events: Vec<_> = response.into_inner().collect().await;
+        assert_eq!(events.len(), 1);
+        assert!(events[0].is_err());
+    }
+
+    #[tokio::test]
+    async fn results_are_post_processed() {
+        let service = post_processed_service(16);
+
+        let response = service
+            .transcribe(Request::new(TranscribeRequest {
+                profile_name: "yes-asr".to_string(),
+                context: None,
+            }))
+            .await
+            .expect("transcribe should return a stream");
+        let texts: Vec<_> = response
+            .into_inner()
+            .map(|event| event.expect("session should succeed").text)
+            .collect()
+            .await;
+        assert_eq!(texts.len(), 16);
+        assert!(texts.iter().all(|text| text == "Yes, indeed"));
+    }
+
+    #[tokio::test]
+    async fn translations_are_post_processed() {
+        let service = post_processed_service(4);
+
+        let response = service
+            .transcribe(Request::new(TranscribeRequest {
+                profile_name: "oui-asr".to_string(),
+                context: None,
+            }))
+            .await
+            .expect("transcribe should return a stream");
+        let events: Vec<_> = response
+            .into_inner()
+            .map(|event| event.expect("session should succeed"))
+            .collect()
+            .await;
+        assert_eq!(events.len(), 4);
+        assert!(events.iter().all(
+            |event| event.text == "Oui" && event.translation.as_deref() == Some("Yes, indeed")
+        ));
+    }
 }
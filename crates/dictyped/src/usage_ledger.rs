@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use base_client::grpc_server::{ProfileUsage, UsageResponse};
+use config_tool::usage_config::UsageConfig;
+
+/// How much billed audio may be recorded before the ledger is written again.
+const SAVE_INTERVAL_MS: u64 = 30_000;
+
+/// Billed audio in milliseconds, by local date (`YYYY-MM-DD`) and profile. Only the days of the
+/// current month are kept.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Entries {
+    days: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+/// A local date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Date {
+    year: i32,
+    month: i32,
+    day: i32,
+}
+
+impl Date {
+    /// The date as `YYYY-MM-DD`, its key in `Entries`.
+    fn key(self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: Entries,
+    unsaved_ms: u64,
+    /// The day `today_ms` and `month_ms` are totals of, and its key.
+    today: Option<Date>,
+    today_key: String,
+    today_ms: u64,
+    month_ms: u64,
+}
+
+impl State {
+    /// Moves the totals on to `date`, dropping the days of other months.
+    fn roll(&mut self, date: Date) {
+        if self.today == Some(date) {
+            return;
+        }
+        self.today = Some(date);
+        self.today_key = date.key();
+        let month = &self.today_key[..8];
+        self.entries.days.retain(|day, _| day.starts_with(month));
+        self.month_ms = self.entries.days.values().flat_map(BTreeMap::values).sum();
+        self.today_ms = self
+            .entries
+            .days
+            .get(&self.today_key)
+            .map_or(0, |recorded| recorded.values().sum());
+    }
+}
+
+/// Keeps the audio sent to billed backends per profile and day, in a JSON file under the data
+/// dir, and checks it against the caps of `UsageConfig`.
+#[derive(Debug)]
+pub struct UsageLedger {
+    path: Option<PathBuf>,
+    config: UsageConfig,
+    state: Mutex<State>,
+    /// Held while the file is written, so that saves land in order.
+    saving: Mutex<()>,
+}
+
+impl UsageLedger {
+    /// A ledger that is not saved, for when there is nowhere to keep it.
+    pub fn new(config: UsageConfig) -> Self {
+        Self {
+            path: None,
+            config,
+            state: Mutex::new(State::default()),
+            saving: Mutex::new(()),
+        }
+    }
+
+    /// Loads the ledger at `path`, or starts an empty one there.
+    pub fn load(path: PathBuf, config: UsageConfig) -> Self {
+        let entries = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("ignoring unreadable usage ledger {}: {e}", path.display());
+                Entries::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Entries::default(),
+            Err(e) => {
+                warn!("failed to read usage ledger {}: {e}", path.display());
+                Entries::default()
+            }
+        };
+        let mut state = State {
+            entries,
+            ..State::default()
+        };
+        if let Some(date) = today() {
+            state.roll(date);
+        }
+        Self {
+            path: Some(path),
+            config,
+            state: Mutex::new(state),
+            saving: Mutex::new(()),
+        }
+    }
+
+    /// Adds `ms` of audio sent by `profile_name` today. Returns whether enough is unsaved that
+    /// the ledger should be saved.
+    pub fn record(&self, profile_name: &str, ms: u64) -> bool {
+        // Without the date, `refusal` stops the session.
+        today().is_some_and(|date| self.record_on(date, profile_name, ms))
+    }
+
+    fn record_on(&self, date: Date, profile_name: &str, ms: u64) -> bool {
+        let mut state = self.state.lock().expect("usage ledger poisoned");
+        state.roll(date);
+        let State {
+            entries, today_key, ..
+        } = &mut *state;
+        if let Some(recorded) = entries.days.get_mut(today_key.as_str()) {
+            if let Some(recorded) = recorded.get_mut(profile_name) {
+                *recorded = recorded.saturating_add(ms);
+            } else {
+                recorded.insert(profile_name.to_string(), ms);
+            }
+        } else {
+            let recorded = BTreeMap::from([(profile_name.to_string(), ms)]);
+            entries.days.insert(today_key.clone(), recorded);
+        }
+        state.today_ms = state.today_ms.saturating_add(ms);
+        state.month_ms = state.month_ms.saturating_add(ms);
+        state.unsaved_ms = state.unsaved_ms.saturating_add(ms);
+        state.unsaved_ms >= SAVE_INTERVAL_MS
+    }
+
+    /// Writes out what was recorded since the last save. It blocks on the file.
+    pub fn save(&self) {
+        let _saving = self.saving.lock().expect("usage ledger poisoned");
+        let mut state = self.state.lock().expect("usage ledger poisoned");
+        if state.unsaved_ms == 0 {
+            return;
+        }
+        state.unsaved_ms = 0;
+        let Some(path) = &self.path else {
+            return;
+        };
+        let content = serde_json::to_vec_pretty(&state.entries);
+        drop(state);
+        if let Err(e) = content
+            .map_err(io::Error::from)
+            .and_then(|content| write_atomically(path, &content))
+        {
+            error!("failed to save usage ledger {}: {e}", path.display());
+        }
+    }
+
+    /// Why no more audio may be sent, e.g. a cap that is reached.
+    pub fn refusal(&self) -> Option<String> {
+        let Some(date) = today() else {
+            return Some("usage caps cannot be checked without the local date".to_string());
+        };
+        self.cap_reached_on(date)
+            .map(|cap| format!("usage {cap} reached"))
+    }
+
+    /// Describes the cap that is reached on `date`, if any.
+    fn cap_reached_on(&self, date: Date) -> Option<String> {
+        let mut state = self.state.lock().expect("usage ledger poisoned");
+        state.roll(date);
+        let (today_ms, month_ms) = (state.today_ms, state.month_ms);
+        drop(state);
+
+        let cap_ms = |secs: u64| secs.saturating_mul(1000);
+        if let Some(cap) = self
+            .config
+            .daily_cap_secs
+            .filter(|cap| today_ms >= cap_ms(*cap))
+        {
+            return Some(format!("daily cap of {cap}s"));
+        }
+        if let Some(cap) = self
+            .config
+            .monthly_cap_secs
+            .filter(|cap| month_ms >= cap_ms(*cap))
+        {
+            return Some(format!("monthly cap of {cap}s"));
+        }
+        None
+    }
+
+    /// Totals of today and this month, per profile and over all of them.
+    pub fn usage(&self) -> UsageResponse {
+        today().map_or_else(UsageResponse::default, |date| self.usage_on(date))
+    }
+
+    fn usage_on(&self, date: Date) -> UsageResponse {
+        let mut state = self.state.lock().expect("usage ledger poisoned");
+        state.roll(date);
+        let mut profiles = BTreeMap::<String, ProfileUsage>::new();
+        for (day, recorded) in &state.entries.days {
+            for (profile_name, ms) in recorded {
+                let usage = profiles
+                    .entry(profile_name.clone())
+                    .or_insert_with(|| ProfileUsage {
+                        profile_name: profile_name.clone(),
+                        ..ProfileUsage::default()
+                    });
+                usage.month_ms += ms;
+                if *day == state.today_key {
+                    usage.today_ms += ms;
+                }
+            }
+        }
+        let (today_ms, month_ms) = (state.today_ms, state.month_ms);
+        drop(state);
+
+        let cap_ms = |secs: Option<u64>| secs.map(|secs| secs.saturating_mul(1000));
+        UsageResponse {
+            today_ms,
+            month_ms,
+            daily_cap_ms: cap_ms(self.config.daily_cap_secs),
+            monthly_cap_ms: cap_ms(self.config.monthly_cap_secs),
+            profiles: profiles.into_values().collect(),
+        }
+    }
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, content)?;
+    fs::rename(temp_path, path)
+}
+
+/// Today's date in local time, or `None` if it cannot be told.
+fn today() -> Option<Date> {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&raw const now, &raw mut tm) }.is_null() {
+        warn!("failed to get the local date");
+        return None;
+    }
+    Some(Date {
+        year: tm.tm_year + 1900,
+        month: tm.tm_mon + 1,
+        day: tm.tm_mday,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(key: &str) -> Date {
+        let parts: Vec<i32> = key.split('-').map(|part| part.parse().unwrap()).collect();
+        Date {
+            year: parts[0],
+            month: parts[1],
+            day: parts[2],
+        }
+    }
+
+    fn caps(daily_cap_secs: Option<u64>, monthly_cap_secs: Option<u64>) -> UsageConfig {
+        UsageConfig {
+            daily_cap_secs,
+            monthly_cap_secs,
+        }
+    }
+
+    #[test]
+    fn sums_days_of_the_month() {
+        let ledger = UsageLedger::new(caps(None, None));
+        ledger.record_on(date("2026-09-30"), "Paraformer", 5_000);
+        ledger.record_on(date("2026-10-01"), "Paraformer", 1_000);
+        ledger.record_on(date("2026-10-02"), "Paraformer", 2_000);
+        ledger.record_on(date("2026-10-02"), "Qwen", 500);
+
+        let usage = ledger.usage_on(date("2026-10-02"));
+        assert_eq!((usage.today_ms, usage.month_ms), (2_500, 3_500));
+        let profiles: Vec<_> = usage
+            .profiles
+            .iter()
+            .map(|usage| (usage.profile_name.as_str(), usage.today_ms, usage.month_ms))
+            .collect();
+        assert_eq!(profiles, [("Paraformer", 2_000, 3_000), ("Qwen", 500, 500)]);
+    }
+
+    #[test]
+    fn caps_are_per_day_and_month() {
+        let ledger = UsageLedger::new(caps(Some(60), Some(100)));
+        ledger.record_on(date("2026-10-01"), "Paraformer", 59_000);
+        assert_eq!(ledger.cap_reached_on(date("2026-10-01")), None);
+        ledger.record_on(date("2026-10-01"), "Qwen", 1_000);
+        assert_eq!(
+            ledger.cap_reached_on(date("2026-10-01")).as_deref(),
+            Some("daily cap of 60s")
+        );
+
+        ledger.record_on(date("2026-10-02"), "Paraformer", 41_000);
+        assert_eq!(
+            ledger.cap_reached_on(date("2026-10-02")).as_deref(),
+            Some("monthly cap of 100s")
+        );
+        assert_eq!(ledger.cap_reached_on(date("2026-11-01")), None);
+    }
+
+    #[test]
+    fn drops_days_of_other_months() {
+        let ledger = UsageLedger::new(caps(None, None));
+        ledger.record_on(date("2026-10-31"), "Paraformer", 5_000);
+        ledger.record_on(date("2026-11-01"), "Paraformer", 1_000);
+
+        let usage = ledger.usage_on(date("2026-11-01"));
+        assert_eq!((usage.today_ms, usage.month_ms), (1_000, 1_000));
+        let days: Vec<String> = ledger
+            .state
+            .lock()
+            .unwrap()
+            .entries
+            .days
+            .keys()
+            .cloned()
+            .collect();
+        assert_eq!(days, ["2026-11-01"]);
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let path = std::env::temp_dir()
+            .join(format!("dictyped-usage-{}", std::process::id()))
+            .join("usage.json");
+        let ledger = UsageLedger::load(path.clone(), caps(None, None));
+        ledger.record_on(date("2026-10-01"), "Paraformer", 1_000);
+        assert!(!path.exists(), "saved before the interval");
+        ledger.save();
+
+        let reloaded = UsageLedger::load(path.clone(), caps(None, None));
+        assert_eq!(reloaded.usage_on(date("2026-10-01")).today_ms, 1_000);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
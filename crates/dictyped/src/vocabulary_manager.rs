@@ -0,0 +1,187 @@
+//! Keeps a hotword vocabulary in sync with the `vocabulary` word list of each Paraformer profile.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use base_client::asr_client::AsrClient;
+use config_tool::config_store::{ConfigFile, get_data_dir};
+use config_tool::profile_config::ProfileConfig;
+use paraformer_v2_client::client::ParaformerV2Client;
+use paraformer_v2_client::error::ParaformerV2Error;
+use paraformer_v2_client::vocabulary::{VocabularyClient, VocabularyEntry};
+
+use crate::client_store::ClientStore;
+use crate::service::prepare_client;
+
+/// A random id of this installation, created on first use and kept at `path`. It keeps the
+/// vocabularies of users who share an account apart.
+fn installation_id(path: &Path) -> io::Result<String> {
+    match fs::read_to_string(path) {
+        Ok(id) if !id.trim().is_empty() => return Ok(id.trim().to_string()),
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let id = Uuid::new_v4().simple().to_string();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, &id)?;
+    Ok(id)
+}
+
+/// Vocabularies are found again by a prefix derived from the installation id and the profile
+/// name, so that no other state has to be kept between runs. Prefixes are limited to 9 lowercase
+/// letters or digits.
+fn vocabulary_prefix(installation_id: &str, profile_name: &str) -> String {
+    // FNV-1a, which unlike `DefaultHasher` is stable across builds.
+    let key = format!("{installation_id}/{profile_name}");
+    let hash = key.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    format!("dt{:07x}", hash >> 4)
+}
+
+fn same_words(a: &[VocabularyEntry], b: &[VocabularyEntry]) -> bool {
+    let key = |entries: &[VocabularyEntry]| {
+        let mut key: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.text.clone(), entry.weight))
+            .collect();
+        key.sort();
+        key
+    };
+    key(a) == key(b)
+}
+
+async fn sync_vocabulary(
+    client: &VocabularyClient,
+    prefix: &str,
+    words: &[String],
+) -> Result<String, ParaformerV2Error> {
+    let vocabulary: Vec<_> = words.iter().map(VocabularyEntry::new).collect();
+
+    let Some(vocabulary_id) = client.list(prefix).await?.into_iter().next() else {
+        let vocabulary_id = client.create(prefix, &vocabulary).await?;
+        info!("created vocabulary {vocabulary_id}");
+        return Ok(vocabulary_id);
+    };
+
+    let current = client.query(&vocabulary_id).await?;
+    if same_words(&current, &vocabulary) {
+        info!("vocabulary {vocabulary_id} is up to date");
+    } else {
+        client.update(&vocabulary_id, &vocabulary).await?;
+        info!("updated vocabulary {vocabulary_id}");
+    }
+    Ok(vocabulary_id)
+}
+
+/// Creates or updates the vocabularies of all profiles, then switches their clients over.
+/// Sessions started before a vocabulary is ready run without it.
+pub async fn sync(config: ConfigFile, client_store: ClientStore) {
+    let installation_id =
+        match get_data_dir().and_then(|dir| Ok(installation_id(&dir.join("installation-id"))?)) {
+            Ok(installation_id) => installation_id,
+            Err(e) => {
+                warn!("vocabularies are not synced without an installation id: {e}");
+                return;
+            }
+        };
+    for (profile_name, profile) in config.profiles() {
+        let ProfileConfig::ParaformerV2(paraformer_v2) = &profile.backend else {
+            continue;
+        };
+        if paraformer_v2.vocabulary_id.is_some() {
+            continue;
+        }
+        let Some(words) = paraformer_v2
+            .vocabulary
+            .as_ref()
+            .filter(|words| !words.is_empty())
+        else {
+            continue;
+        };
+
+        let prefix = vocabulary_prefix(&installation_id, profile_name);
+        let client = match paraformer_v2.api_key.resolve().await {
+            Ok(api_key) => VocabularyClient::new(api_key, None),
+            Err(err) => Err(err.into()),
+        };
+        let result = match client {
+            Ok(client) => sync_vocabulary(&client, &prefix, words).await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(vocabulary_id) => {
+                let mut paraformer_v2 = paraformer_v2.clone();
+                paraformer_v2.vocabulary_id = Some(vocabulary_id);
+                client_store.set_client(
+                    profile_name,
+                    Arc::new(ParaformerV2Client::new(paraformer_v2)),
+                );
+                // The connection opened ahead by the replaced client went with it.
+                if client_store.is_preconnect_profile(profile_name)
+                    && let Some(client) = client_store.get_asr_client_for_profile(profile_name)
+                {
+                    prepare_client(profile_name, client.as_ref()).await;
+                }
+            }
+            Err(err) => warn!("failed to sync vocabulary of profile {profile_name}: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_is_valid_and_stable() {
+        let prefix = vocabulary_prefix("a", "Profile1");
+        assert_eq!(prefix.len(), 9);
+        assert!(
+            prefix
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        );
+        assert_eq!(prefix, vocabulary_prefix("a", "Profile1"));
+        assert_ne!(prefix, vocabulary_prefix("a", "Profile2"));
+        assert_ne!(prefix, vocabulary_prefix("b", "Profile1"));
+    }
+
+    #[test]
+    fn installation_id_is_kept() {
+        let dir =
+            std::env::temp_dir().join(format!("dictyped-installation-{}", std::process::id()));
+        let path = dir.join("installation-id");
+        let id = installation_id(&path).unwrap();
+        assert!(!id.is_empty());
+        assert_eq!(installation_id(&path).unwrap(), id);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn same_words_ignores_order_and_language() {
+        let a = [
+            VocabularyEntry::new("Dictype"),
+            VocabularyEntry::new("fcitx"),
+        ];
+        let mut b = [
+            VocabularyEntry::new("fcitx"),
+            VocabularyEntry::new("Dictype"),
+        ];
+        assert!(same_words(&a, &b));
+
+        b[0].lang = Some("en".to_string());
+        assert!(same_words(&a, &b));
+
+        b[0].weight = 5;
+        assert!(!same_words(&a, &b));
+    }
+}
@@ -0,0 +1,241 @@
+use serde::Serialize;
+
+use base_client::api_key::DashscopeApiKey;
+use base_client::run_task::{RunTaskClient, RunTaskError, Sentence, SentenceOutput, Task};
+
+use crate::config::{FunAsrRealtimeConfig, Language};
+
+/// Read more: <https://help.aliyun.com/zh/model-studio/fun-asr-realtime-websocket-api>
+pub type FunAsrRealtimeClient = RunTaskClient<FunAsrRealtimeConfig>;
+
+#[derive(Debug, Serialize)]
+pub struct Parameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vocabulary_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language_hints: Option<Vec<Language>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    semantic_punctuation_enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_sentence_silence: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    multi_threshold_mode_enabled: Option<bool>,
+}
+
+/// Fun-ASR speaks Paraformer's protocol, with its own model and parameters.
+impl Task for FunAsrRealtimeConfig {
+    const MODEL: &'static str = "fun-asr-realtime";
+    const NAME: &'static str = "fun-asr-realtime";
+
+    type Parameters = Parameters;
+    type Output = SentenceOutput;
+    type Results = ();
+    type Error = RunTaskError;
+
+    fn api_key(&self) -> &DashscopeApiKey {
+        &self.api_key
+    }
+
+    fn websocket_url(&self) -> &str {
+        Self::websocket_url(self)
+    }
+
+    fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    fn traffic_log_dir(&self) -> Option<&str> {
+        self.traffic_log_dir.as_deref()
+    }
+
+    fn max_task_duration_secs(&self) -> u32 {
+        Self::max_task_duration_secs(self)
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters {
+            vocabulary_id: self.vocabulary_id.clone(),
+            language_hints: self
+                .language_hints
+                .clone()
+                .filter(|language_hints| !language_hints.is_empty()),
+            semantic_punctuation_enabled: self.semantic_punctuation_enabled,
+            max_sentence_silence: self.max_sentence_silence,
+            multi_threshold_mode_enabled: self.multi_threshold_mode_enabled,
+        }
+    }
+
+    fn results(&self, (): &mut (), output: SentenceOutput) -> Vec<Sentence> {
+        vec![output.into()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dashscope_mock::{MockServer, Step, paraformer};
+    use futures_util::StreamExt;
+    use tokio::sync::mpsc;
+    use tokio_util::bytes::Bytes;
+
+    use super::*;
+    use base_client::api_key::ApiKey;
+    use base_client::asr_client::AsrClient;
+    use base_client::audio_stream::AudioStream;
+    use base_client::grpc_server::TranscribeResponse;
+
+    /// 100ms of silence.
+    fn chunk() -> Bytes {
+        Bytes::from(vec![0u8; 3200])
+    }
+
+    /// A task that recognizes `text` at 100ms once it is finished. Fun-ASR speaks Paraformer's
+    /// protocol, so its events are mocked alike.
+    fn task(text: &str) -> Vec<Step> {
+        vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_started()),
+            Step::Expect("finish-task"),
+            Step::Send(paraformer::result(text, 100, Some(500))),
+            Step::Send(paraformer::task_finished()),
+        ]
+    }
+
+    fn config(server: &MockServer) -> FunAsrRealtimeConfig {
+        FunAsrRealtimeConfig {
+            api_key: ApiKey::new("sk-test").into(),
+            dashscope_websocket_url: Some(server.url()),
+            ..FunAsrRealtimeConfig::default()
+        }
+    }
+
+    fn audio(chunks: usize) -> AudioStream {
+        let audio = (0..chunks).map(|_| Ok(chunk()));
+        AudioStream(Box::pin(futures_util::stream::iter(audio)))
+    }
+
+    fn events(events: Vec<Result<TranscribeResponse, anyhow::Error>>) -> Vec<(u32, String, bool)> {
+        events
+            .into_iter()
+            .map(|event| {
+                let event = event.unwrap();
+                (event.begin_time, event.text, event.sentence_end)
+            })
+            .collect()
+    }
+
+    fn audio_received(connections: &[dashscope_mock::Connection]) -> Vec<usize> {
+        connections.iter().map(|c| c.audio.len()).collect()
+    }
+
+    #[tokio::test]
+    async fn runs_fun_asr_task() {
+        let server = MockServer::start(vec![task("我用 Dictype 打字。")]).await;
+        let client = FunAsrRealtimeClient::new(FunAsrRealtimeConfig {
+            language_hints: Some(vec![Language::Chinese, Language::English]),
+            ..config(&server)
+        });
+
+        let events = events(client.create(audio(5)).await.unwrap().collect().await);
+
+        assert_eq!(events, [(100, "我用 Dictype 打字。".to_string(), true)]);
+        let connections = server.connections().await;
+        let payload = &connections[0].events[0]["payload"];
+        assert_eq!(payload["model"], "fun-asr-realtime");
+        assert_eq!(
+            payload["parameters"]["language_hints"],
+            serde_json::json!(["zh", "en"])
+        );
+    }
+
+    #[tokio::test]
+    async fn rollover_on_max_duration() {
+        let server = MockServer::start(vec![task("Task 1."), task("Task 2.")]).await;
+        let client = FunAsrRealtimeClient::new(FunAsrRealtimeConfig {
+            max_task_duration_secs: Some(1),
+            ..config(&server)
+        });
+
+        let events = events(client.create(audio(15)).await.unwrap().collect().await);
+
+        assert_eq!(
+            events,
+            [
+                (100, "Task 1.".to_string(), true),
+                (1100, "Task 2.".to_string(), true),
+            ]
+        );
+        assert_eq!(audio_received(&server.connections().await), [32000, 16000]);
+    }
+
+    #[tokio::test]
+    async fn reconnect_replays_unfinished_audio() {
+        let cut = vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_started()),
+            Step::ExpectAudio(5 * 3200),
+            Step::Send(paraformer::result("First.", 100, Some(300))),
+            Step::Send(paraformer::result("hel", 400, None)),
+            Step::Reset,
+        ];
+        let server = MockServer::start(vec![cut, task("Task 2.")]).await;
+
+        let (audio, mut rx) = mpsc::unbounded_channel();
+        let audio_stream = AudioStream(Box::pin(futures_util::stream::poll_fn(move |cx| {
+            rx.poll_recv(cx)
+        })));
+        let mut client = FunAsrRealtimeClient::new(config(&server))
+            .create(audio_stream)
+            .await
+            .unwrap();
+        for _ in 0..5 {
+            audio.send(Ok(chunk())).unwrap();
+        }
+        let mut results = Vec::new();
+        for _ in 0..2 {
+            results.push(client.next().await.unwrap());
+        }
+        drop(audio);
+        results.extend(client.collect::<Vec<_>>().await);
+
+        // The unfinished sentence is withdrawn, and recognized again from the replayed audio.
+        assert_eq!(
+            events(results),
+            [
+                (100, "First.".to_string(), true),
+                (400, "hel".to_string(), false),
+                (400, String::new(), false),
+                (400, "Task 2.".to_string(), true),
+            ]
+        );
+        assert_eq!(audio_received(&server.connections().await), [16000, 6400]);
+    }
+
+    #[tokio::test]
+    async fn task_failed_ends_session() {
+        let script = vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_failed(
+                "InvalidParameter",
+                "unsupported language hint.",
+            )),
+        ];
+        let server = MockServer::start(vec![script]).await;
+
+        let client = FunAsrRealtimeClient::new(config(&server));
+        let events: Vec<_> = client.create(audio(5)).await.unwrap().collect().await;
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].as_ref().unwrap_err().to_string(),
+            "task failed (InvalidParameter): unsupported language hint."
+        );
+        assert!(
+            events[0]
+                .as_ref()
+                .unwrap_err()
+                .downcast_ref::<RunTaskError>()
+                .is_some()
+        );
+        server.connections().await;
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+use base_client::api_key::DashscopeApiKey;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[serde(rename = "zh")]
+    Chinese,
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "ja")]
+    Japanese,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FunAsrRealtimeConfig {
+    #[serde(flatten)]
+    pub api_key: DashscopeApiKey,
+    pub dashscope_websocket_url: Option<String>,
+    /// Languages spoken. Chinese covers Mandarin, the dialects and Chinese mixed with English.
+    pub language_hints: Option<Vec<Language>>,
+    pub semantic_punctuation_enabled: Option<bool>,
+    pub max_sentence_silence: Option<u32>,
+    pub multi_threshold_mode_enabled: Option<bool>,
+    /// A hotword vocabulary created beforehand, e.g. in the Model Studio console.
+    pub vocabulary_id: Option<String>,
+    /// A task is rolled over to a new one after this long, at the next sentence boundary. At
+    /// least 10 seconds.
+    pub max_task_duration_secs: Option<u32>,
+    /// Each session's websocket traffic is written to a JSONL file in this directory, for
+    /// debugging. The API key is redacted.
+    pub traffic_log_dir: Option<String>,
+    /// `http://` or `socks5://` proxy for the websocket, with optional `user:password@`. Without
+    /// it, `HTTPS_PROXY` or `ALL_PROXY` is used, unless `NO_PROXY` lists the host.
+    pub proxy: Option<String>,
+}
+
+impl FunAsrRealtimeConfig {
+    pub const DEFAULT_WEBSOCKET_URL: &str = "wss://dashscope.aliyuncs.com/api-ws/v1/inference";
+
+    pub const DEFAULT_MAX_TASK_DURATION_SECS: u32 = 30 * 60;
+
+    /// Shorter tasks would open a connection every few seconds.
+    pub const MIN_MAX_TASK_DURATION_SECS: u32 = 10;
+
+    #[must_use]
+    pub fn websocket_url(&self) -> &str {
+        self.dashscope_websocket_url
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_WEBSOCKET_URL)
+    }
+
+    #[must_use]
+    pub fn max_task_duration_secs(&self) -> u32 {
+        self.max_task_duration_secs
+            .unwrap_or(Self::DEFAULT_MAX_TASK_DURATION_SECS)
+    }
+}
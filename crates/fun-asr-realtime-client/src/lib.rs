@@ -0,0 +1,6 @@
+#![cfg_attr(test, allow(warnings))]
+
+//! Async client for Aliyun's Fun-ASR real-time speech recognition service.
+
+pub mod client;
+pub mod config;
@@ -0,0 +1,402 @@
+use async_stream::stream;
+use futures_util::Stream;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::select;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+use tracing::{error, info, trace};
+
+use base_client::asr_client::AsrClient;
+use base_client::audio_stream::AudioStream;
+use base_client::grpc_server::TranscribeResponse;
+use base_client::prepared_connection::PreparedConnection;
+use base_client::transcribe_stream::TranscribeStream;
+use base_client::vad::samples_to_ms;
+
+use crate::config::FunAsrConfig;
+use crate::error::FunAsrError;
+use crate::types;
+
+/// Read more: <https://github.com/modelscope/FunASR/blob/main/runtime/docs/websocket_protocol.md>
+#[derive(Debug)]
+pub struct FunAsrClient {
+    config: FunAsrConfig,
+    prepared: PreparedConnection<WebSocket>,
+}
+
+type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn connect(config: &FunAsrConfig) -> Result<WebSocket, FunAsrError> {
+    let request = config.websocket_url.as_str().into_client_request()?;
+    let (ws_stream, _resp) = connect_async(request).await?;
+    Ok(ws_stream)
+}
+
+enum Stage {
+    Streaming,
+    AwaitFinal,
+}
+
+/// The sentence being previewed by online results.
+struct Sentence {
+    begin_time: u32,
+    text: String,
+}
+
+/// 16 kHz mono S16LE.
+fn bytes_to_ms(bytes: usize) -> u32 {
+    samples_to_ms(bytes / 2)
+}
+
+fn transcribe<W>(
+    web_socket_stream: W,
+    mut audio_stream: AudioStream,
+    config: FunAsrConfig,
+) -> impl Stream<Item = Result<TranscribeResponse, FunAsrError>>
+where
+    W: Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
+        + StreamExt
+        + futures_util::Sink<Message>,
+    <W as futures_util::Sink<Message>>::Error: std::fmt::Debug,
+    FunAsrError: From<<W as futures_util::Sink<Message>>::Error>,
+{
+    let (mut send, mut recv) = web_socket_stream.split();
+
+    stream! {
+        let mut stage = Stage::Streaming;
+        // Audio sent so far, used to place sentences without timestamps.
+        let mut sent_bytes = 0;
+        let mut sentence: Option<Sentence> = None;
+
+        {
+            let start_req = match types::StartRequest::new(&config).and_then(|req| serde_json::to_string(&req)) {
+                Ok(t) => t,
+                Err(e) => { yield Err(e.into()); return; }
+            };
+            if let Err(_e) = send.send(Message::Text(start_req.into())).await { yield Err(FunAsrError::Connection); return; }
+        }
+
+        loop {
+            select! {
+                chunk = audio_stream.next(), if matches!(stage, Stage::Streaming) => {
+                    if let Some(chunk) = chunk {
+                        match chunk {
+                            Ok(chunk) => {
+                                sent_bytes += chunk.len();
+                                if let Err(_e) = send.send(Message::Binary(chunk)).await {
+                                    yield Err(FunAsrError::Connection);
+                                    break;
+                                }
+                            },
+                            Err(err) => {
+                                yield Err(FunAsrError::Audio(err));
+                            }
+                        }
+                    } else {
+                        let stop_req = match serde_json::to_string(&types::StopRequest::new()) {
+                            Ok(t) => t,
+                            Err(e) => {
+                                yield Err(e.into());
+                                break;
+                            }
+                        };
+                        if let Err(_e) = send.send(Message::Text(stop_req.into())).await {
+                            yield Err(FunAsrError::Connection);
+                            break;
+                        }
+                        stage = Stage::AwaitFinal;
+                    }
+                }
+                server_msg = recv.next() => {
+                    match server_msg {
+                        Some(Ok(Message::Text(text))) => {
+                            let result = match serde_json::from_str::<types::ServerResult>(&text) {
+                                Ok(r) => r,
+                                Err(e) => {
+                                    error!("failed to parse server result: {text}");
+                                    yield Err(e.into());
+                                    break;
+                                },
+                            };
+                            trace!("{result:?}");
+
+                            match result.mode {
+                                types::ResultMode::Online => {
+                                    if !result.text.is_empty() {
+                                        let current = sentence.get_or_insert_with(|| Sentence {
+                                            begin_time: bytes_to_ms(sent_bytes),
+                                            text: String::new(),
+                                        });
+                                        current.text.push_str(&result.text);
+                                        // The final result ends the sentence below.
+                                        if !result.is_final {
+                                            yield Ok(TranscribeResponse {
+                                                text: current.text.clone(),
+                                                begin_time: current.begin_time,
+                                                sentence_end: false,
+                                                translation: None,
+                                            });
+                                        }
+                                    }
+                                }
+                                types::ResultMode::Offline => {
+                                    // The offline model replaces the preview, keeping its place.
+                                    let begin_time = sentence.take().map(|s| s.begin_time);
+                                    if begin_time.is_some() || !result.text.is_empty() {
+                                        yield Ok(TranscribeResponse {
+                                            text: result.text.clone(),
+                                            begin_time: begin_time
+                                                .or_else(|| result.begin_time())
+                                                .unwrap_or_else(|| bytes_to_ms(sent_bytes)),
+                                            sentence_end: true,
+                                            translation: None,
+                                        });
+                                    }
+                                }
+                            }
+
+                            if result.is_final {
+                                if let Some(current) = sentence.take() {
+                                    yield Ok(TranscribeResponse {
+                                        text: current.text,
+                                        begin_time: current.begin_time,
+                                        sentence_end: true,
+                                        translation: None,
+                                    });
+                                }
+                                info!("final result received.");
+                                let _ = send.close().await;
+                            }
+                        }
+                        Some(Ok(Message::Ping(data))) => {
+                            let _ = send.send(Message::Pong(data)).await;
+                        }
+                        Some(Ok(Message::Pong(_) | Message::Binary(_) | Message::Frame(_))) => {
+                            // ignore
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            info!("close by server: {:?}", frame);
+                        }
+                        None => {
+                            info!("server disconnected.");
+                            if let Some(current) = sentence.take() {
+                                yield Ok(TranscribeResponse {
+                                    text: current.text,
+                                    begin_time: current.begin_time,
+                                    sentence_end: true,
+                                    translation: None,
+                                });
+                            }
+                            return;
+                        }
+                        Some(Err(error)) => {
+                            error!("connection error: {:?}", error);
+                            yield Err(FunAsrError::Connection);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsrClient for FunAsrClient {
+    type Config = FunAsrConfig;
+    type TranscriptionStream = TranscribeStream<anyhow::Error>;
+
+    fn new(config: impl Into<Self::Config>) -> Self {
+        Self {
+            config: config.into(),
+            prepared: PreparedConnection::new(),
+        }
+    }
+
+    async fn create(
+        &self,
+        audio_stream: AudioStream,
+    ) -> Result<Self::TranscriptionStream, anyhow::Error> {
+        let config = self.config.clone();
+        let ws_stream = match self.prepared.take() {
+            Some(ws_stream) => ws_stream,
+            None => connect(&config).await?,
+        };
+
+        let transcribe_stream = transcribe(ws_stream, audio_stream, config)
+            .map(|item| item.map_err(anyhow::Error::from));
+
+        Ok(TranscribeStream::new(Box::pin(transcribe_stream)))
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        if !self.prepared.is_fresh() {
+            self.prepared.put(connect(&self.config).await?);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use futures_util::{SinkExt, StreamExt};
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+    use tokio_util::bytes::Bytes;
+
+    use super::*;
+    use crate::config::Mode;
+    use base_client::asr_client::AsrClient;
+
+    /// Accepts one session, answers its first audio chunk with `after_audio` and its stop request
+    /// with `after_stop`, and returns its start request.
+    async fn serve(
+        listener: TcpListener,
+        after_audio: Vec<String>,
+        after_stop: Vec<String>,
+    ) -> Value {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+
+        let Some(Ok(Message::Text(start))) = ws.next().await else {
+            panic!("expected start request")
+        };
+        let start: Value = serde_json::from_str(&start).unwrap();
+
+        let mut after_audio = Some(after_audio);
+        while let Some(Ok(message)) = ws.next().await {
+            let replies = match message {
+                Message::Binary(_) => after_audio.take().unwrap_or_default(),
+                Message::Text(stop) => {
+                    let stop: Value = serde_json::from_str(&stop).unwrap();
+                    assert_eq!(stop, json!({"is_speaking": false}));
+                    after_stop.clone()
+                }
+                _ => Vec::new(),
+            };
+            for reply in replies {
+                ws.send(Message::Text(reply.into())).await.unwrap();
+            }
+        }
+        start
+    }
+
+    /// Starts a server with `serve` and a session sending it two chunks of audio.
+    async fn session(
+        config: FunAsrConfig,
+        after_audio: Vec<Value>,
+        after_stop: Vec<Value>,
+    ) -> (Vec<Result<(String, bool), String>>, Value) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let to_strings = |replies: Vec<Value>| replies.iter().map(Value::to_string).collect();
+        let server = tokio::spawn(serve(
+            listener,
+            to_strings(after_audio),
+            to_strings(after_stop),
+        ));
+        (session_with(config, url).await, server.await.unwrap())
+    }
+
+    async fn session_with(
+        config: FunAsrConfig,
+        url: String,
+    ) -> Vec<Result<(String, bool), String>> {
+        let backend = FunAsrClient::new(FunAsrConfig {
+            websocket_url: url,
+            ..config
+        });
+        let audio = vec![
+            Ok(Bytes::from(vec![0u8; 3200])),
+            Ok(Bytes::from(vec![0u8; 3200])),
+        ];
+        let audio_stream = AudioStream(Box::pin(futures_util::stream::iter(audio)));
+        let client = backend.create(audio_stream).await.unwrap();
+        client
+            .map(|event| {
+                event
+                    .map(|event| (event.text, event.sentence_end))
+                    .map_err(|e| e.to_string())
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn two_pass_session() {
+        let online = |text: &str| json!({"mode": "2pass-online", "text": text, "is_final": false});
+        let (events, start) = session(
+            FunAsrConfig {
+                hotwords: Some(BTreeMap::from([("dictype".to_string(), 20)])),
+                ..FunAsrConfig::default()
+            },
+            vec![
+                online("hello"),
+                online(" wor"),
+                json!({
+                    "mode": "2pass-offline",
+                    "text": "Hello world.",
+                    "is_final": false,
+                    "stamp_sents": [{"text_seg": "hello world", "punc": ".", "start": 120, "end": 900}],
+                }),
+                online("again"),
+            ],
+            vec![json!({"mode": "2pass-offline", "text": "Again.", "is_final": true})],
+        )
+        .await;
+        assert_eq!(
+            events,
+            [
+                Ok(("hello".to_string(), false)),
+                Ok(("hello wor".to_string(), false)),
+                Ok(("Hello world.".to_string(), true)),
+                Ok(("again".to_string(), false)),
+                Ok(("Again.".to_string(), true)),
+            ]
+        );
+
+        assert_eq!(start["mode"], "2pass");
+        assert_eq!(start["chunk_size"], json!([5, 10, 5]));
+        assert_eq!(start["is_speaking"], true);
+        assert_eq!(start["hotwords"], r#"{"dictype":20}"#);
+    }
+
+    #[tokio::test]
+    async fn online_session_ends_sentence_on_final_result() {
+        let (events, start) = session(
+            FunAsrConfig {
+                mode: Some(Mode::Online),
+                ..FunAsrConfig::default()
+            },
+            vec![json!({"mode": "online", "text": "hello", "is_final": false})],
+            vec![json!({"mode": "online", "text": " world", "is_final": true})],
+        )
+        .await;
+        assert_eq!(
+            events,
+            [
+                Ok(("hello".to_string(), false)),
+                Ok(("hello world".to_string(), true)),
+            ]
+        );
+        assert_eq!(start["mode"], "online");
+    }
+
+    #[tokio::test]
+    async fn unparsable_result_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(listener, vec!["not json".to_string()], Vec::new()));
+
+        let events = session_with(FunAsrConfig::default(), url).await;
+        assert!(
+            matches!(&events[..], [Err(error)] if error.starts_with("serialization error")),
+            "{events:?}"
+        );
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    /// Streaming partial results, revised by the offline model at the end of each sentence.
+    #[default]
+    #[serde(rename = "2pass")]
+    TwoPass,
+    /// Streaming partial results only.
+    #[serde(rename = "online")]
+    Online,
+    /// One result per sentence, no preview.
+    #[serde(rename = "offline")]
+    Offline,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FunAsrConfig {
+    /// e.g. `ws://192.168.1.2:10095`.
+    pub websocket_url: String,
+    pub mode: Option<Mode>,
+    /// Latency of streaming results, in units of 60 ms: look-back, chunk, look-ahead.
+    pub chunk_size: Option<[u32; 3]>,
+    /// Hotwords with their weights, e.g. `{ "Dictype" = 20 }`.
+    pub hotwords: Option<BTreeMap<String, u32>>,
+    pub itn: Option<bool>,
+}
+
+impl FunAsrConfig {
+    pub const DEFAULT_CHUNK_SIZE: [u32; 3] = [5, 10, 5];
+
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        self.mode.unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn chunk_size(&self) -> [u32; 3] {
+        self.chunk_size.unwrap_or(Self::DEFAULT_CHUNK_SIZE)
+    }
+}
@@ -0,0 +1,9 @@
+#![cfg_attr(test, allow(warnings))]
+
+//! Async client for the self-hosted [FunASR runtime](https://github.com/modelscope/FunASR/tree/main/runtime)
+//! websocket service.
+
+pub mod client;
+pub mod config;
+pub mod error;
+mod types;
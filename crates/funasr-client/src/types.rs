@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::{FunAsrConfig, Mode};
+
+const WAV_NAME: &str = "dictype";
+
+/// First message of a session, announcing the audio format and decoding options.
+#[derive(Debug, Serialize)]
+pub struct StartRequest {
+    mode: Mode,
+    chunk_size: [u32; 3],
+    chunk_interval: u32,
+    wav_name: &'static str,
+    wav_format: &'static str,
+    audio_fs: u32,
+    is_speaking: bool,
+    itn: bool,
+    /// A JSON object of hotword to weight, itself encoded as a string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hotwords: Option<String>,
+}
+
+impl StartRequest {
+    pub fn new(config: &FunAsrConfig) -> Result<Self, serde_json::Error> {
+        let hotwords = config
+            .hotwords
+            .as_ref()
+            .filter(|hotwords| !hotwords.is_empty())
+            .map(serde_json::to_string)
+            .transpose()?;
+
+        Ok(Self {
+            mode: config.mode(),
+            chunk_size: config.chunk_size(),
+            chunk_interval: 10,
+            wav_name: WAV_NAME,
+            wav_format: "pcm",
+            audio_fs: 16_000,
+            is_speaking: true,
+            itn: config.itn.unwrap_or(true),
+            hotwords,
+        })
+    }
+}
+
+/// Tells the server that no more audio follows.
+#[derive(Debug, Serialize)]
+pub struct StopRequest {
+    is_speaking: bool,
+}
+
+impl StopRequest {
+    pub const fn new() -> Self {
+        Self { is_speaking: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ResultMode {
+    /// Text recognized since the previous online result.
+    #[serde(rename = "2pass-online", alias = "online")]
+    Online,
+    /// The whole sentence, re-recognized by the offline model.
+    #[serde(rename = "2pass-offline", alias = "offline")]
+    Offline,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StampSentence {
+    /// Milliseconds from the start of the session.
+    pub start: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerResult {
+    pub mode: ResultMode,
+    #[serde(default)]
+    pub text: String,
+    /// Set on the last result of the session, after `is_speaking: false`.
+    #[serde(default)]
+    pub is_final: bool,
+    #[serde(default)]
+    pub stamp_sents: Vec<StampSentence>,
+}
+
+impl ServerResult {
+    /// Start of the sentence if the server reported timestamps.
+    pub fn begin_time(&self) -> Option<u32> {
+        self.stamp_sents
+            .first()
+            .and_then(|sentence| u32::try_from(sentence.start).ok())
+    }
+}
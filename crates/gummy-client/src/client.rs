@@ -0,0 +1,345 @@
+use base_client::api_key::DashscopeApiKey;
+use base_client::grpc_server::TranscribeResponse;
+use base_client::run_task::{RunTaskClient, RunTaskError, Sentence, Task};
+
+use crate::config::GummyConfig;
+use crate::types::{GummySentence, Parameters, PayloadOutput};
+
+/// Read more: <https://help.aliyun.com/zh/model-studio/real-time-speech-translation-websocket-api>
+pub type GummyClient = RunTaskClient<GummyConfig>;
+
+/// A sentence as far as its transcript and translation have arrived.
+#[derive(Debug)]
+struct Pending {
+    sentence_id: u32,
+    transcription: Option<GummySentence>,
+    translation: Option<GummySentence>,
+}
+
+impl Pending {
+    const fn new(sentence_id: u32) -> Self {
+        Self {
+            sentence_id,
+            transcription: None,
+            translation: None,
+        }
+    }
+
+    const fn is_final(&self) -> bool {
+        matches!(
+            (&self.transcription, &self.translation),
+            (Some(transcription), Some(translation))
+                if transcription.sentence_end && translation.sentence_end
+        )
+    }
+
+    /// The sentence to type, with its translation attached. `None` until the part to type arrives.
+    fn sentence(&self, type_translation: bool, sentence_end: bool) -> Option<Sentence> {
+        let typed = if type_translation {
+            self.translation.as_ref()?
+        } else {
+            self.transcription.as_ref()?
+        };
+        let end_time = self
+            .transcription
+            .as_ref()
+            .or(self.translation.as_ref())
+            .and_then(|sentence| sentence.end_time);
+        Some(Sentence {
+            response: TranscribeResponse {
+                text: typed.text.clone(),
+                begin_time: typed.begin_time,
+                sentence_end,
+                translation: self
+                    .translation
+                    .as_ref()
+                    .map(|translation| translation.text.clone()),
+            },
+            end_time: end_time.filter(|_| sentence_end),
+        })
+    }
+}
+
+/// The transcript and the translation of a sentence arrive apart, and either may end first. A
+/// sentence ends once both have, or when the next one starts or the task finishes.
+#[derive(Debug, Default)]
+pub struct Results {
+    pending: Option<Pending>,
+    /// `sentence_id` of the last sentence that ended. Results repeated after the end are
+    /// dropped, since the sentence is typed already.
+    ended: Option<u32>,
+}
+
+impl Results {
+    fn end(&mut self, type_translation: bool) -> Option<Sentence> {
+        let pending = self.pending.take()?;
+        self.ended = Some(pending.sentence_id);
+        pending.sentence(type_translation, true)
+    }
+}
+
+impl Task for GummyConfig {
+    const MODEL: &'static str = "gummy-realtime-v1";
+    const NAME: &'static str = "gummy";
+
+    type Parameters = Parameters;
+    type Output = PayloadOutput;
+    type Results = Results;
+    type Error = RunTaskError;
+
+    fn api_key(&self) -> &DashscopeApiKey {
+        &self.api_key
+    }
+
+    fn websocket_url(&self) -> &str {
+        Self::websocket_url(self)
+    }
+
+    fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    fn traffic_log_dir(&self) -> Option<&str> {
+        self.traffic_log_dir.as_deref()
+    }
+
+    fn max_task_duration_secs(&self) -> u32 {
+        Self::max_task_duration_secs(self)
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::new(self)
+    }
+
+    fn results(&self, results: &mut Results, output: PayloadOutput) -> Vec<Sentence> {
+        let translation = output
+            .translations
+            .into_iter()
+            .flatten()
+            .find(|translation| translation.lang == self.target_language())
+            .map(|translation| translation.sentence);
+        let mut sentences = Vec::new();
+        for (sentence, is_translation) in [(output.transcription, false), (translation, true)] {
+            let Some(sentence) = sentence else {
+                continue;
+            };
+            if results.ended == Some(sentence.sentence_id) {
+                continue;
+            }
+            if results
+                .pending
+                .as_ref()
+                .is_some_and(|pending| pending.sentence_id != sentence.sentence_id)
+            {
+                // The next sentence started before this one's other part ended.
+                sentences.extend(results.end(self.type_translation()));
+            }
+            let pending = results
+                .pending
+                .get_or_insert_with(|| Pending::new(sentence.sentence_id));
+            if is_translation {
+                pending.translation = Some(sentence);
+            } else {
+                pending.transcription = Some(sentence);
+            }
+        }
+        match &results.pending {
+            Some(pending) if pending.is_final() => {
+                sentences.extend(results.end(self.type_translation()));
+            }
+            Some(pending) => sentences.extend(pending.sentence(self.type_translation(), false)),
+            None => {}
+        }
+        sentences
+    }
+
+    fn finish(&self, results: &mut Results) -> Vec<Sentence> {
+        results.end(self.type_translation()).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dashscope_mock::{MockServer, Step, gummy, paraformer};
+    use futures_util::StreamExt;
+    use tokio_util::bytes::Bytes;
+
+    use super::*;
+    use base_client::api_key::ApiKey;
+    use base_client::asr_client::AsrClient;
+    use base_client::audio_stream::AudioStream;
+
+    fn config(server: &MockServer, type_translation: bool) -> GummyConfig {
+        GummyConfig {
+            api_key: ApiKey::new("sk-test").into(),
+            dashscope_websocket_url: Some(server.url()),
+            source_language: Some("zh".to_string()),
+            type_translation: Some(type_translation),
+            ..GummyConfig::default()
+        }
+    }
+
+    fn audio(chunks: usize) -> AudioStream {
+        let audio = (0..chunks).map(|_| Ok(Bytes::from(vec![0u8; 3200])));
+        AudioStream(Box::pin(futures_util::stream::iter(audio)))
+    }
+
+    /// A task that recognizes a sentence once it is finished, the translation lagging behind.
+    fn task() -> Vec<Step> {
+        vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_started()),
+            Step::Expect("finish-task"),
+            Step::Send(gummy::result("你", None, 100, None)),
+            Step::Send(gummy::result("你好", Some("Hel"), 100, None)),
+            Step::Send(gummy::result("你好。", Some("Hello."), 100, Some(500))),
+            Step::Send(gummy::result("你好。", Some("Hello."), 100, Some(500))),
+            Step::Send(paraformer::task_finished()),
+        ]
+    }
+
+    async fn events(
+        client: GummyClient,
+        chunks: usize,
+    ) -> Vec<(u32, String, Option<String>, bool)> {
+        client
+            .create(audio(chunks))
+            .await
+            .unwrap()
+            .map(|event| {
+                let event = event.unwrap();
+                (
+                    event.begin_time,
+                    event.text,
+                    event.translation,
+                    event.sentence_end,
+                )
+            })
+            .collect()
+            .await
+    }
+
+    async fn session(type_translation: bool) -> Vec<(String, Option<String>, bool)> {
+        let server = MockServer::start(vec![task()]).await;
+        let events = events(GummyClient::new(config(&server, type_translation)), 5)
+            .await
+            .into_iter()
+            .map(|(_, text, translation, sentence_end)| (text, translation, sentence_end))
+            .collect();
+
+        let connections = server.connections().await;
+        let parameters = &connections[0].events[0]["payload"]["parameters"];
+        assert_eq!(parameters["source_language"], "zh");
+        assert_eq!(parameters["translation_target_languages"][0], "en");
+        events
+    }
+
+    #[tokio::test]
+    async fn types_transcript_with_translation() {
+        assert_eq!(
+            session(false).await,
+            [
+                ("你".to_string(), None, false),
+                ("你好".to_string(), Some("Hel".to_string()), false),
+                ("你好。".to_string(), Some("Hello.".to_string()), true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn types_translation() {
+        assert_eq!(
+            session(true).await,
+            [
+                ("Hel".to_string(), Some("Hel".to_string()), false),
+                ("Hello.".to_string(), Some("Hello.".to_string()), true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn waits_for_translation_after_transcript_ends() {
+        let script = vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_started()),
+            Step::Expect("finish-task"),
+            Step::Send(gummy::result("你好。", None, 100, Some(500))),
+            Step::Send(gummy::translation("Hel", 100, None)),
+            Step::Send(gummy::translation("Hello.", 100, Some(500))),
+            Step::Send(paraformer::task_finished()),
+        ];
+        let server = MockServer::start(vec![script]).await;
+
+        assert_eq!(
+            events(GummyClient::new(config(&server, false)), 5).await,
+            [
+                (100, "你好。".to_string(), None, false),
+                (100, "你好。".to_string(), Some("Hel".to_string()), false),
+                (100, "你好。".to_string(), Some("Hello.".to_string()), true),
+            ]
+        );
+        server.connections().await;
+    }
+
+    #[tokio::test]
+    async fn rollover_on_max_duration() {
+        let task = |transcript, translation| {
+            vec![
+                Step::Expect("run-task"),
+                Step::Send(paraformer::task_started()),
+                Step::Expect("finish-task"),
+                Step::Send(gummy::result(transcript, Some(translation), 100, Some(500))),
+                Step::Send(paraformer::task_finished()),
+            ]
+        };
+        let server =
+            MockServer::start(vec![task("第一。", "First."), task("第二。", "Second.")]).await;
+        let client = GummyClient::new(GummyConfig {
+            max_task_duration_secs: Some(1),
+            ..config(&server, true)
+        });
+
+        assert_eq!(
+            events(client, 15).await,
+            [
+                (100, "First.".to_string(), Some("First.".to_string()), true),
+                (
+                    1100,
+                    "Second.".to_string(),
+                    Some("Second.".to_string()),
+                    true
+                ),
+            ]
+        );
+        let connections = server.connections().await;
+        let audio: Vec<_> = connections.iter().map(|c| c.audio.len()).collect();
+        assert_eq!(audio, [32000, 16000]);
+    }
+
+    #[tokio::test]
+    async fn task_failed_is_a_run_task_error() {
+        let script = vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_failed(
+                "InvalidParameter",
+                "unsupported target language.",
+            )),
+        ];
+        let server = MockServer::start(vec![script]).await;
+
+        let client = GummyClient::new(config(&server, false));
+        let events: Vec<_> = client.create(audio(5)).await.unwrap().collect().await;
+
+        assert_eq!(events.len(), 1);
+        let error = events[0].as_ref().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "task failed (InvalidParameter): unsupported target language."
+        );
+        assert!(matches!(
+            error.downcast_ref::<RunTaskError>(),
+            Some(RunTaskError::TaskFailed { .. })
+        ));
+        server.connections().await;
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+use base_client::api_key::DashscopeApiKey;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GummyConfig {
+    #[serde(flatten)]
+    pub api_key: DashscopeApiKey,
+    pub dashscope_websocket_url: Option<String>,
+    /// Language spoken, e.g. `zh`. Detected by the service if not set.
+    pub source_language: Option<String>,
+    /// Language the speech is translated into, e.g. `en`.
+    pub target_language: Option<String>,
+    /// Types the translation instead of the transcript. Either way, responses carry both.
+    pub type_translation: Option<bool>,
+    /// A hotword vocabulary created beforehand, e.g. in the Model Studio console.
+    pub vocabulary_id: Option<String>,
+    /// Silence in milliseconds that ends a sentence.
+    pub max_end_silence: Option<u32>,
+    /// A task is rolled over to a new one after this long, at the next sentence boundary. At
+    /// least 10 seconds.
+    pub max_task_duration_secs: Option<u32>,
+    /// Each session's websocket traffic is written to a JSONL file in this directory, for
+    /// debugging. The API key is redacted.
+    pub traffic_log_dir: Option<String>,
+    /// `http://` or `socks5://` proxy for the websocket, with optional `user:password@`. Without
+    /// it, `HTTPS_PROXY` or `ALL_PROXY` is used, unless `NO_PROXY` lists the host.
+    pub proxy: Option<String>,
+}
+
+impl GummyConfig {
+    pub const DEFAULT_WEBSOCKET_URL: &str = "wss://dashscope.aliyuncs.com/api-ws/v1/inference";
+
+    pub const DEFAULT_TARGET_LANGUAGE: &str = "en";
+
+    pub const DEFAULT_MAX_TASK_DURATION_SECS: u32 = 30 * 60;
+
+    /// Shorter tasks would open a connection every few seconds.
+    pub const MIN_MAX_TASK_DURATION_SECS: u32 = 10;
+
+    #[must_use]
+    pub fn websocket_url(&self) -> &str {
+        self.dashscope_websocket_url
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_WEBSOCKET_URL)
+    }
+
+    #[must_use]
+    pub fn max_task_duration_secs(&self) -> u32 {
+        self.max_task_duration_secs
+            .unwrap_or(Self::DEFAULT_MAX_TASK_DURATION_SECS)
+    }
+
+    #[must_use]
+    pub fn target_language(&self) -> &str {
+        self.target_language
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_TARGET_LANGUAGE)
+    }
+
+    #[must_use]
+    pub fn type_translation(&self) -> bool {
+        self.type_translation.unwrap_or(false)
+    }
+}
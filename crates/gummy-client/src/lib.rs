@@ -0,0 +1,7 @@
+#![cfg_attr(test, allow(warnings))]
+
+//! Async client for Aliyun's Gummy real-time speech recognition and translation service.
+
+pub mod client;
+pub mod config;
+mod types;
@@ -0,0 +1,102 @@
+//! The `parameters` and results of Gummy. The rest of its protocol is the shared `run-task` one.
+
+use crate::config::GummyConfig;
+
+#[derive(Debug, serde::Serialize)]
+pub struct Parameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_language: Option<String>,
+    transcription_enabled: bool,
+    translation_enabled: bool,
+    translation_target_languages: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vocabulary_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_end_silence: Option<u32>,
+}
+
+impl Parameters {
+    pub fn new(config: &GummyConfig) -> Self {
+        Self {
+            source_language: config.source_language.clone(),
+            transcription_enabled: true,
+            translation_enabled: true,
+            translation_target_languages: vec![config.target_language().to_string()],
+            vocabulary_id: config.vocabulary_id.clone(),
+            max_end_silence: config.max_end_silence,
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GummySentence {
+    pub sentence_id: u32,
+    pub begin_time: u32,
+    pub end_time: Option<u32>,
+    pub text: String,
+    pub sentence_end: bool,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GummyTranslation {
+    pub lang: String,
+    #[serde(flatten)]
+    pub sentence: GummySentence,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PayloadOutput {
+    pub transcription: Option<GummySentence>,
+    pub translations: Option<Vec<GummyTranslation>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PayloadOutput;
+
+    #[test]
+    fn result_generated_deserialize() {
+        let data = r#"
+        {
+          "header": {
+            "task_id": "2bf83b9a-baeb-4fda-8d9a-xxxxxxxxxxxx",
+            "event": "result-generated",
+            "attributes": {}
+          },
+          "payload": {
+            "output": {
+              "translations": [
+                {
+                  "sentence_id": 0,
+                  "begin_time": 240,
+                  "end_time": 4290,
+                  "text": "Hello, world.",
+                  "lang": "en",
+                  "words": [],
+                  "sentence_end": true
+                }
+              ],
+              "transcription": {
+                "sentence_id": 0,
+                "begin_time": 240,
+                "end_time": 4290,
+                "text": "你好，世界。",
+                "words": [],
+                "sentence_end": true
+              }
+            },
+            "usage": {
+              "duration": 6
+            }
+          }
+        }
+        "#;
+        let mut response: serde_json::Value = serde_json::from_str(data).unwrap();
+        let output: PayloadOutput =
+            serde_json::from_value(response["payload"]["output"].take()).unwrap();
+        assert_eq!(output.transcription.unwrap().text, "你好，世界。");
+        let translations = output.translations.unwrap();
+        assert_eq!(translations[0].lang, "en");
+        assert_eq!(translations[0].sentence.text, "Hello, world.");
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::BTreeMap;
+
+use async_stream::stream;
+use futures_util::{SinkExt, Stream, StreamExt};
+use tokio::net::TcpStream;
+use tokio::select;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+use tracing::{error, info, trace};
+use tungstenite::Message;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::HeaderValue;
+use tungstenite::http::header::AUTHORIZATION;
+
+use base_client::api_key::ApiKey;
+use base_client::asr_client::AsrClient;
+use base_client::audio_stream::AudioStream;
+use base_client::grpc_server::TranscribeResponse;
+use base_client::prepared_connection::PreparedConnection;
+use base_client::transcribe_stream::TranscribeStream;
+use base_client::vad::samples_to_ms;
+
+use crate::config::OpenAiRealtimeConfig;
+use crate::error::OpenAiRealtimeError;
+use crate::resample::Resampler;
+use crate::types;
+
+/// Read more: <https://platform.openai.com/docs/guides/realtime-transcription>
+#[derive(Debug)]
+pub struct OpenAiRealtimeClient {
+    config: OpenAiRealtimeConfig,
+    prepared: PreparedConnection<WebSocket>,
+}
+
+type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn connect(
+    config: &OpenAiRealtimeConfig,
+    api_key: &ApiKey,
+) -> Result<WebSocket, OpenAiRealtimeError> {
+    let mut request = config.websocket_url().into_client_request()?;
+    let headers = request.headers_mut();
+
+    headers.insert(
+        AUTHORIZATION,
+        HeaderValue::from_str(&format!("Bearer {}", api_key.expose()))
+            .map_err(|_| OpenAiRealtimeError::InvalidHeaderValue("Authorization"))?,
+    );
+    headers.insert("OpenAI-Beta", HeaderValue::from_static("realtime=v1"));
+
+    let (ws_stream, _resp) = connect_async(request).await?;
+    Ok(ws_stream)
+}
+
+#[derive(Debug, PartialEq)]
+enum Stage {
+    SessionCreating,
+    SessionCreated,
+    AwaitTaskFinished,
+}
+
+/// An utterance, from speech start until its transcription completes.
+struct Item {
+    start_time: u32,
+    text: String,
+    /// Server VAD detected the end of speech; the commit that follows is not ours.
+    stopped: bool,
+    /// Committed items are guaranteed to be transcribed.
+    committed: bool,
+}
+
+impl Item {
+    const fn new(start_time: u32) -> Self {
+        Self {
+            start_time,
+            text: String::new(),
+            stopped: false,
+            committed: false,
+        }
+    }
+}
+
+fn transcribe<W>(
+    web_socket_stream: W,
+    mut audio_stream: AudioStream,
+    config: OpenAiRealtimeConfig,
+) -> impl Stream<Item = Result<TranscribeResponse, OpenAiRealtimeError>>
+where
+    W: Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
+        + StreamExt
+        + futures_util::Sink<Message>,
+    <W as futures_util::Sink<Message>>::Error: std::fmt::Debug,
+    OpenAiRealtimeError: From<<W as futures_util::Sink<Message>>::Error>,
+{
+    let (mut send, mut recv) = web_socket_stream.split();
+
+    let mut stage = Stage::SessionCreating;
+    let mut event_count = 0;
+    let mut resampler = Resampler::default();
+    // 16 kHz samples sent so far, used to place items committed without speech detection.
+    let mut sent_samples = 0;
+    // Whether the server answered the final commit, after the audio stream ended.
+    let mut final_commit_done = false;
+
+    let mut items: BTreeMap<String, Item> = BTreeMap::new();
+
+    stream! {
+        loop {
+            if stage == Stage::AwaitTaskFinished && final_commit_done && !items.values().any(|item| item.committed) {
+                info!("all items transcribed.");
+                let _ = send.close().await;
+                return;
+            }
+
+            select! {
+                server_msg = recv.next() => {
+                    match server_msg {
+                        Some(Ok(Message::Text(text))) => {
+                            let server_event = match serde_json::from_str::<types::ServerEvent>(&text) {
+                                Ok(e) => e,
+                                Err(_e) => {
+                                    error!("failed to parse ServerEvent: {text}");
+                                    break;
+                                },
+                            };
+
+                            match server_event {
+                                types::ServerEvent::Error { error } if error.is_commit_empty() => {
+                                    trace!("nothing left to commit");
+                                    final_commit_done = true;
+                                }
+                                types::ServerEvent::Error { error } => {
+                                    error!("err: {error:?}");
+                                    yield Err(OpenAiRealtimeError::Server {
+                                        code: error.code.unwrap_or_default(),
+                                        message: error.message,
+                                    });
+                                    let _ = send.close().await;
+                                    break;
+                                }
+                                types::ServerEvent::TranscriptionSessionCreated => {
+                                    trace!("session created");
+                                    let session_update = types::transcription_session::update::request::Request::new(event_count, &config);
+                                    event_count += 1;
+                                    let body = match serde_json::to_string(&session_update) {
+                                        Ok(t) => t,
+                                        Err(e) => {
+                                            yield Err(e.into());
+                                            break;
+                                        }
+                                    };
+                                    if let Err(_e) = send.send(Message::Text(body.into())).await {
+                                        yield Err(OpenAiRealtimeError::Connection);
+                                        break;
+                                    }
+                                    stage = Stage::SessionCreated;
+                                }
+                                types::ServerEvent::TranscriptionSessionUpdated => {
+                                    info!("session updated");
+                                }
+                                types::ServerEvent::InputAudioBufferSpeechStarted { item_id, audio_start_ms } => {
+                                    trace!("speech started: {item_id} at {audio_start_ms}ms");
+                                    items.insert(item_id, Item::new(audio_start_ms));
+                                }
+                                types::ServerEvent::InputAudioBufferSpeechStopped { item_id } => {
+                                    trace!("speech stopped: {item_id}");
+                                    if let Some(item) = items.get_mut(&item_id) {
+                                        item.stopped = true;
+                                    }
+                                }
+                                types::ServerEvent::InputAudioBufferCommitted { item_id } => {
+                                    trace!("committed: {item_id}");
+                                    let item = items
+                                        .entry(item_id)
+                                        .or_insert_with(|| Item::new(samples_to_ms(sent_samples)));
+                                    item.committed = true;
+                                    // Commits by server VAD may still arrive after ours was sent.
+                                    if stage == Stage::AwaitTaskFinished && !item.stopped {
+                                        final_commit_done = true;
+                                    }
+                                }
+                                types::ServerEvent::ConversationItemInputAudioTranscriptionDelta { item_id, delta } => {
+                                    let Some(item) = items.get_mut(&item_id) else {
+                                        error!("delta for unknown item {item_id}");
+                                        continue;
+                                    };
+                                    item.text.push_str(&delta);
+                                    if item.text.is_empty() {
+                                        continue;
+                                    }
+                                    yield Ok(TranscribeResponse {
+                                        begin_time: item.start_time,
+                                        sentence_end: false,
+                                        translation: None,
+                                        text: item.text.clone(),
+                                    });
+                                }
+                                types::ServerEvent::ConversationItemInputAudioTranscriptionCompleted { item_id, transcript } => {
+                                    trace!("completed: {item_id}");
+                                    let Some(item) = items.remove(&item_id) else {
+                                        error!("completion for unknown item {item_id}");
+                                        continue;
+                                    };
+                                    yield Ok(TranscribeResponse {
+                                        begin_time: item.start_time,
+                                        sentence_end: true,
+                                        translation: None,
+                                        text: transcript.trim().to_string(),
+                                    });
+                                }
+                                types::ServerEvent::ConversationItemInputAudioTranscriptionFailed { item_id, error } => {
+                                    error!("transcription of {item_id} failed: {error:?}");
+                                    // Clear whatever was previewed for it.
+                                    if let Some(item) = items.remove(&item_id) && !item.text.is_empty() {
+                                        yield Ok(TranscribeResponse {
+                                            begin_time: item.start_time,
+                                            sentence_end: true,
+                                            translation: None,
+                                            text: String::new(),
+                                        });
+                                    }
+                                }
+                                types::ServerEvent::Other => {}
+                            }
+                        }
+                        Some(Ok(Message::Close(frame))) => {
+                            match frame {
+                                Some(close_frame) => {
+                                    info!("close by server: {:?}", &close_frame);
+                                    yield Err(OpenAiRealtimeError::Closed(close_frame.reason.as_str().to_string()));
+                                },
+                                None => {
+                                    info!("close by server: {:?}", frame);
+                                }
+                            }
+                        },
+                        None => {
+                            info!("server disconnected.");
+                            return;
+                        }
+                        Some(Err(error)) => {
+                            error!("connection error: {:?}", error);
+                            yield Err(OpenAiRealtimeError::Connection);
+                            break;
+                        },
+                        Some(Ok(Message::Ping(data))) => {
+                            let _ = send.send(Message::Pong(data)).await;
+                        },
+                        Some(Ok(Message::Pong(_) | Message::Binary(_) | Message::Frame(_))) => {
+                            // ignore
+                        }
+                    }
+                },
+                chunk = audio_stream.next(), if stage == Stage::SessionCreated => {
+                    let req = match chunk {
+                        Some(Ok(chunk)) => {
+                            sent_samples += chunk.len() / 2;
+                            let audio = resampler.push(&chunk);
+                            types::InputAudioBufferRequest::append(event_count, &audio.into())
+                        },
+                        Some(Err(err)) => {
+                            error!("error: {:?}", err);
+                            yield Err(OpenAiRealtimeError::Audio(err));
+                            continue;
+                        }
+                        None => {
+                            // There is no end of session: commit what is left and wait for it.
+                            stage = Stage::AwaitTaskFinished;
+                            types::InputAudioBufferRequest::commit(event_count)
+                        }
+                    };
+                    event_count += 1;
+                    let body = match serde_json::to_string(&req) {
+                        Ok(t) => t,
+                        Err(e) => {
+                            yield Err(e.into());
+                            break;
+                        }
+                    };
+                    if let Err(_e) = send.send(Message::Text(body.into())).await {
+                        yield Err(OpenAiRealtimeError::Connection);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsrClient for OpenAiRealtimeClient {
+    type Config = OpenAiRealtimeConfig;
+    type TranscriptionStream = TranscribeStream<anyhow::Error>;
+
+    fn new(config: impl Into<Self::Config>) -> Self {
+        Self {
+            config: config.into(),
+            prepared: PreparedConnection::new(),
+        }
+    }
+
+    async fn create(
+        &self,
+        audio_stream: AudioStream,
+    ) -> Result<Self::TranscriptionStream, anyhow::Error> {
+        let config = self.config.clone();
+        let ws_stream = if let Some(ws_stream) = self.prepared.take() {
+            ws_stream
+        } else {
+            let api_key = config
+                .api_key
+                .resolve()
+                .await
+                .map_err(OpenAiRealtimeError::from)?;
+            connect(&config, &api_key).await?
+        };
+
+        let transcribe_stream = transcribe(ws_stream, audio_stream, config)
+            .map(|item| item.map_err(anyhow::Error::from));
+
+        Ok(TranscribeStream::new(Box::pin(transcribe_stream)))
+    }
+
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        if !self.prepared.is_fresh() {
+            let api_key = self
+                .config
+                .api_key
+                .resolve()
+                .await
+                .map_err(OpenAiRealtimeError::from)?;
+            self.prepared.put(connect(&self.config, &api_key).await?);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{SinkExt, StreamExt};
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+    use tokio_util::bytes::Bytes;
+
+    use super::*;
+    use base_client::asr_client::AsrClient;
+
+    async fn send_json<S>(ws: &mut S, value: Value)
+    where
+        S: futures_util::Sink<Message> + Unpin,
+        S::Error: std::fmt::Debug,
+    {
+        ws.send(Message::Text(value.to_string().into()))
+            .await
+            .unwrap();
+    }
+
+    /// Accepts one session: the first utterance is detected by server VAD, the second is still
+    /// in progress when the client commits at the end.
+    async fn serve(listener: TcpListener) -> Vec<Value> {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+        send_json(
+            &mut ws,
+            json!({"type": "transcription_session.created", "session": {}}),
+        )
+        .await;
+
+        let mut received = Vec::new();
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            let event: Value = serde_json::from_str(&text).unwrap();
+            let event_type = event["type"].as_str().unwrap().to_string();
+            received.push(event);
+            match event_type.as_str() {
+                "transcription_session.update" => {
+                    send_json(
+                        &mut ws,
+                        json!({"type": "transcription_session.updated", "session": {}}),
+                    )
+                    .await;
+                    send_json(&mut ws, json!({"type": "input_audio_buffer.speech_started", "item_id": "item_1", "audio_start_ms": 40})).await;
+                    send_json(&mut ws, json!({"type": "input_audio_buffer.speech_stopped", "item_id": "item_1", "audio_end_ms": 900})).await;
+                    send_json(&mut ws, json!({"type": "input_audio_buffer.committed", "item_id": "item_1", "previous_item_id": null})).await;
+                    send_json(&mut ws, json!({"type": "conversation.item.input_audio_transcription.delta", "item_id": "item_1", "content_index": 0, "delta": "Hello"})).await;
+                    send_json(&mut ws, json!({"type": "conversation.item.input_audio_transcription.delta", "item_id": "item_1", "content_index": 0, "delta": " world."})).await;
+                    send_json(&mut ws, json!({"type": "conversation.item.input_audio_transcription.completed", "item_id": "item_1", "content_index": 0, "transcript": "Hello world."})).await;
+                    send_json(&mut ws, json!({"type": "input_audio_buffer.speech_started", "item_id": "item_2", "audio_start_ms": 1500})).await;
+                }
+                "input_audio_buffer.commit" => {
+                    send_json(&mut ws, json!({"type": "input_audio_buffer.committed", "item_id": "item_2", "previous_item_id": "item_1"})).await;
+                    send_json(&mut ws, json!({"type": "conversation.item.input_audio_transcription.completed", "item_id": "item_2", "content_index": 0, "transcript": "Bye."})).await;
+                }
+                _ => {}
+            }
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn transcription_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(listener));
+
+        let backend = OpenAiRealtimeClient::new(OpenAiRealtimeConfig {
+            api_key: ApiKey::new("sk-test").into(),
+            websocket_url: Some(url),
+            ..OpenAiRealtimeConfig::default()
+        });
+        let audio = vec![Ok(Bytes::from(vec![0u8; 3200]))];
+        let audio_stream = AudioStream(Box::pin(futures_util::stream::iter(audio)));
+        let client = backend.create(audio_stream).await.unwrap();
+
+        let events: Vec<(u32, String, bool)> = client
+            .map(|event| {
+                let event = event.unwrap();
+                (event.begin_time, event.text, event.sentence_end)
+            })
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            [
+                (40, "Hello".to_string(), false),
+                (40, "Hello world.".to_string(), false),
+                (40, "Hello world.".to_string(), true),
+                (1500, "Bye.".to_string(), true),
+            ]
+        );
+
+        let received = server.await.unwrap();
+        let types: Vec<&str> = received
+            .iter()
+            .map(|e| e["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "transcription_session.update",
+                "input_audio_buffer.append",
+                "input_audio_buffer.commit"
+            ]
+        );
+        // 100 ms of 16 kHz audio, upsampled to 24 kHz.
+        let audio = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            received[1]["audio"].as_str().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(audio.len(), 4794);
+    }
+
+    #[test]
+    fn debug_redacts_api_key() {
+        let config = OpenAiRealtimeConfig {
+            api_key: ApiKey::new("sk-secret").into(),
+            ..OpenAiRealtimeConfig::default()
+        };
+        assert!(!format!("{config:?}").contains("sk-secret"));
+    }
+}
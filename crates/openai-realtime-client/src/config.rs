@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use base_client::api_key::BearerApiKey;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TurnDetection {
+    pub threshold: f32,
+    pub prefix_padding_ms: u32,
+    pub silence_duration_ms: u32,
+}
+
+impl Default for TurnDetection {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            prefix_padding_ms: 300,
+            silence_duration_ms: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenAiRealtimeConfig {
+    #[serde(flatten)]
+    pub api_key: BearerApiKey,
+    /// Any server speaking the same protocol, e.g. a self-hosted one.
+    pub websocket_url: Option<String>,
+    pub model: Option<String>,
+    /// ISO-639-1 code, e.g. `en`.
+    pub language: Option<String>,
+    /// Free text that guides the transcription, e.g. expected vocabulary.
+    pub prompt: Option<String>,
+    pub turn_detection: Option<TurnDetection>,
+}
+
+impl OpenAiRealtimeConfig {
+    pub const DEFAULT_WEBSOCKET_URL: &str = "wss://api.openai.com/v1/realtime?intent=transcription";
+    pub const DEFAULT_MODEL: &str = "gpt-4o-transcribe";
+
+    #[must_use]
+    pub fn websocket_url(&self) -> &str {
+        self.websocket_url
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_WEBSOCKET_URL)
+    }
+
+    #[must_use]
+    pub fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or(Self::DEFAULT_MODEL)
+    }
+}
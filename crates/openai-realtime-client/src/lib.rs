@@ -0,0 +1,9 @@
+#![cfg_attr(test, allow(warnings))]
+
+//! Async client for OpenAI-style realtime transcription sessions.
+
+pub mod client;
+pub mod config;
+pub mod error;
+mod resample;
+mod types;
@@ -0,0 +1,70 @@
+/// Converts 16 kHz S16LE PCM to the 24 kHz the realtime API expects, by linear interpolation.
+///
+/// Every two input samples produce three output samples; the last input sample of each pair is
+/// held back until the next one arrives.
+#[derive(Debug, Default)]
+pub struct Resampler {
+    pending_byte: Option<u8>,
+    pending: Vec<i16>,
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn lerp(a: i16, b: i16, weight_a: i32) -> i16 {
+    ((i32::from(a) * weight_a + i32::from(b) * (3 - weight_a)) / 3) as i16
+}
+
+impl Resampler {
+    pub fn push(&mut self, pcm: &[u8]) -> Vec<u8> {
+        let mut bytes = self
+            .pending_byte
+            .take()
+            .into_iter()
+            .chain(pcm.iter().copied());
+        while let Some(low) = bytes.next() {
+            let Some(high) = bytes.next() else {
+                self.pending_byte = Some(low);
+                break;
+            };
+            self.pending.push(i16::from_le_bytes([low, high]));
+        }
+
+        let mut out = Vec::with_capacity(self.pending.len() * 3);
+        let mut consumed = 0;
+        while let [s0, s1, s2, ..] = self.pending[consumed..] {
+            for sample in [s0, lerp(s0, s1, 1), lerp(s1, s2, 2)] {
+                out.extend_from_slice(&sample.to_le_bytes());
+            }
+            consumed += 2;
+        }
+        self.pending.drain(..consumed);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcm(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn upsamples_by_three_halves() {
+        let mut resampler = Resampler::default();
+        let out = resampler.push(&pcm(&[0, 300, 600, 900, 1200]));
+        assert_eq!(out, pcm(&[0, 200, 400, 600, 800, 1000]));
+    }
+
+    #[test]
+    fn keeps_state_across_odd_chunks() {
+        let input = pcm(&(0..100).map(|i| i * 3).collect::<Vec<_>>());
+        let mut whole = Resampler::default();
+        let expected = whole.push(&input);
+
+        let mut chunked = Resampler::default();
+        let out: Vec<u8> = input.chunks(7).flat_map(|c| chunked.push(c)).collect();
+        assert_eq!(out, expected);
+        assert_eq!(out.len(), 98 / 2 * 3 * 2);
+    }
+}
@@ -0,0 +1,196 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio_util::bytes::Bytes;
+
+#[allow(dead_code)]
+pub mod transcription_session {
+    pub mod update {
+        pub mod request {
+            use serde::Serialize;
+
+            use crate::config::OpenAiRealtimeConfig;
+
+            #[derive(Debug, Serialize)]
+            enum Type {
+                #[serde(rename = "transcription_session.update")]
+                TranscriptionSessionUpdate,
+            }
+
+            #[derive(Debug, Serialize)]
+            enum InputAudioFormat {
+                #[serde(rename = "pcm16")]
+                Pcm16,
+            }
+
+            #[derive(Debug, Serialize)]
+            struct InputAudioTranscription {
+                model: String,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                language: Option<String>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                prompt: Option<String>,
+            }
+
+            #[derive(Debug, Serialize)]
+            enum TurnDetectionType {
+                #[serde(rename = "server_vad")]
+                ServerVad,
+            }
+
+            #[derive(Debug, Serialize)]
+            struct TurnDetection {
+                r#type: TurnDetectionType,
+                threshold: f32,
+                prefix_padding_ms: u32,
+                silence_duration_ms: u32,
+            }
+
+            #[derive(Debug, Serialize)]
+            struct Session {
+                input_audio_format: InputAudioFormat,
+                input_audio_transcription: InputAudioTranscription,
+                turn_detection: TurnDetection,
+            }
+
+            #[derive(Debug, Serialize)]
+            pub struct Request {
+                event_id: String,
+                r#type: Type,
+                session: Session,
+            }
+
+            impl Request {
+                pub fn new(event_id: u32, config: &OpenAiRealtimeConfig) -> Self {
+                    let turn_detection = config.turn_detection.clone().unwrap_or_default();
+                    Self {
+                        event_id: format!("transcription_session_update_{event_id}"),
+                        r#type: Type::TranscriptionSessionUpdate,
+                        session: Session {
+                            input_audio_format: InputAudioFormat::Pcm16,
+                            input_audio_transcription: InputAudioTranscription {
+                                model: config.model().to_string(),
+                                language: config.language.clone(),
+                                prompt: config.prompt.clone(),
+                            },
+                            turn_detection: TurnDetection {
+                                r#type: TurnDetectionType::ServerVad,
+                                threshold: turn_detection.threshold,
+                                prefix_padding_ms: turn_detection.prefix_padding_ms,
+                                silence_duration_ms: turn_detection.silence_duration_ms,
+                            },
+                        },
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+enum InputAudioBufferType {
+    #[serde(rename = "input_audio_buffer.append")]
+    Append,
+    #[serde(rename = "input_audio_buffer.commit")]
+    Commit,
+}
+
+/// `input_audio_buffer.append` and `input_audio_buffer.commit`.
+#[derive(Debug, Serialize)]
+pub struct InputAudioBufferRequest {
+    event_id: String,
+    r#type: InputAudioBufferType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audio: Option<String>,
+}
+
+impl InputAudioBufferRequest {
+    pub fn append(event_id: u32, audio_bytes: &Bytes) -> Self {
+        Self {
+            event_id: format!("event_{event_id}"),
+            r#type: InputAudioBufferType::Append,
+            audio: Some(base64::engine::general_purpose::STANDARD.encode(audio_bytes)),
+        }
+    }
+
+    pub fn commit(event_id: u32) -> Self {
+        Self {
+            event_id: format!("event_{event_id}"),
+            r#type: InputAudioBufferType::Commit,
+            audio: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ErrorDetail {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl ErrorDetail {
+    /// Committing an empty buffer is expected when the session ends in silence.
+    pub fn is_commit_empty(&self) -> bool {
+        self.code.as_deref() == Some("input_audio_buffer_commit_empty")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum ServerEvent {
+    #[serde(rename = "transcription_session.created")]
+    TranscriptionSessionCreated,
+    #[serde(rename = "transcription_session.updated")]
+    TranscriptionSessionUpdated,
+    #[serde(rename = "input_audio_buffer.speech_started")]
+    InputAudioBufferSpeechStarted {
+        item_id: String,
+        audio_start_ms: u32,
+    },
+    #[serde(rename = "input_audio_buffer.speech_stopped")]
+    InputAudioBufferSpeechStopped { item_id: String },
+    #[serde(rename = "input_audio_buffer.committed")]
+    InputAudioBufferCommitted { item_id: String },
+    #[serde(rename = "conversation.item.input_audio_transcription.delta")]
+    ConversationItemInputAudioTranscriptionDelta { item_id: String, delta: String },
+    #[serde(rename = "conversation.item.input_audio_transcription.completed")]
+    ConversationItemInputAudioTranscriptionCompleted { item_id: String, transcript: String },
+    #[serde(rename = "conversation.item.input_audio_transcription.failed")]
+    ConversationItemInputAudioTranscriptionFailed { item_id: String, error: ErrorDetail },
+    #[serde(rename = "error")]
+    Error { error: ErrorDetail },
+    /// e.g. `conversation.item.created`, `rate_limits.updated`.
+    #[serde(other)]
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OpenAiRealtimeConfig;
+
+    #[test]
+    fn serialize_session_update() {
+        let config = OpenAiRealtimeConfig {
+            language: Some("en".to_string()),
+            ..OpenAiRealtimeConfig::default()
+        };
+        let request = transcription_session::update::request::Request::new(0, &config);
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(value["type"], "transcription_session.update");
+        assert_eq!(value["session"]["input_audio_format"], "pcm16");
+        assert_eq!(
+            value["session"]["input_audio_transcription"],
+            serde_json::json!({"model": "gpt-4o-transcribe", "language": "en"})
+        );
+        assert_eq!(value["session"]["turn_detection"]["type"], "server_vad");
+    }
+
+    #[test]
+    fn deserialize_unknown_event() {
+        let event: ServerEvent =
+            serde_json::from_str(r#"{"type": "rate_limits.updated", "rate_limits": []}"#).unwrap();
+        assert!(matches!(event, ServerEvent::Other));
+    }
+}
@@ -0,0 +1,293 @@
+use async_stream::stream;
+use futures_util::{Stream, StreamExt};
+use reqwest::multipart::{Form, Part};
+use tracing::{info, trace};
+
+use base_client::api_key::{ApiKey, ApiKeyError};
+use base_client::asr_client::AsrClient;
+use base_client::audio_stream::AudioStream;
+use base_client::grpc_server::TranscribeResponse;
+use base_client::transcribe_stream::TranscribeStream;
+use base_client::vad::{Segment, VoiceActivityDetector};
+
+use crate::config::OpenAiTranscriptionsConfig;
+use crate::error::OpenAiTranscriptionsError;
+use crate::wav;
+
+/// Shown at the position of an utterance while it is being uploaded.
+const PLACEHOLDER: &str = "…";
+
+/// Audio is cut into utterances by VAD, and each one is uploaded as a WAV file once it ends.
+#[derive(Debug)]
+pub struct OpenAiTranscriptionsClient {
+    config: OpenAiTranscriptionsConfig,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+async fn upload(
+    http: &reqwest::Client,
+    config: &OpenAiTranscriptionsConfig,
+    api_key: Option<&ApiKey>,
+    segment: &Segment,
+) -> Result<String, OpenAiTranscriptionsError> {
+    let file = Part::bytes(wav::encode(&segment.samples))
+        .file_name("audio.wav")
+        .mime_str("audio/wav")?;
+    let mut form = Form::new()
+        .part("file", file)
+        .text("model", config.model().to_string())
+        .text("response_format", "json");
+    if let Some(language) = &config.language {
+        form = form.text("language", language.clone());
+    }
+    if let Some(prompt) = &config.prompt {
+        form = form.text("prompt", prompt.clone());
+    }
+
+    let mut request = http.post(config.transcriptions_url()).multipart(form);
+    if let Some(api_key) = api_key {
+        request = request.bearer_auth(api_key.expose());
+    }
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(OpenAiTranscriptionsError::Status {
+            status: status.as_u16(),
+            body: response.text().await.unwrap_or_default(),
+        });
+    }
+    let transcription = response.json::<TranscriptionResponse>().await?;
+    Ok(transcription.text.trim().to_string())
+}
+
+fn transcribe(
+    http: reqwest::Client,
+    mut audio_stream: AudioStream,
+    config: OpenAiTranscriptionsConfig,
+    api_key: Option<ApiKey>,
+) -> impl Stream<Item = Result<TranscribeResponse, OpenAiTranscriptionsError>> {
+    let mut vad = VoiceActivityDetector::new(config.vad.clone().unwrap_or_default());
+
+    stream! {
+        let mut ended = false;
+        while !ended {
+            let segments = match audio_stream.next().await {
+                Some(Ok(chunk)) => vad.push(&chunk),
+                Some(Err(err)) => {
+                    yield Err(OpenAiTranscriptionsError::Audio(err));
+                    continue;
+                }
+                None => {
+                    ended = true;
+                    vad.finish().into_iter().collect()
+                }
+            };
+
+            for segment in segments {
+                trace!("segment at {}ms, {}ms long", segment.begin_time, segment.duration_ms());
+                yield Ok(TranscribeResponse {
+                    text: PLACEHOLDER.to_string(),
+                    begin_time: segment.begin_time,
+                    sentence_end: false,
+                    translation: None,
+                });
+                match upload(&http, &config, api_key.as_ref(), &segment).await {
+                    Ok(text) => yield Ok(TranscribeResponse {
+                        text,
+                        begin_time: segment.begin_time,
+                        sentence_end: true,
+                        translation: None,
+                    }),
+                    Err(err) => {
+                        // Do not leave the placeholder behind.
+                        yield Ok(TranscribeResponse {
+                            text: String::new(),
+                            begin_time: segment.begin_time,
+                            sentence_end: true,
+                            translation: None,
+                        });
+                        yield Err(err);
+                        return;
+                    }
+                }
+            }
+        }
+        info!("audio stream ended.");
+    }
+}
+
+#[async_trait::async_trait]
+impl AsrClient for OpenAiTranscriptionsClient {
+    type Config = OpenAiTranscriptionsConfig;
+    type TranscriptionStream = TranscribeStream<anyhow::Error>;
+
+    fn new(config: impl Into<Self::Config>) -> Self {
+        Self {
+            config: config.into(),
+        }
+    }
+
+    async fn create(
+        &self,
+        audio_stream: AudioStream,
+    ) -> Result<Self::TranscriptionStream, anyhow::Error> {
+        let http = reqwest::Client::builder()
+            .build()
+            .map_err(OpenAiTranscriptionsError::from)?;
+
+        let api_key = match self.config.api_key.resolve().await {
+            Ok(api_key) => Some(api_key),
+            // Many self-hosted servers need none.
+            Err(ApiKeyError::Missing) => None,
+            Err(err) => return Err(OpenAiTranscriptionsError::from(err).into()),
+        };
+
+        let transcribe_stream = transcribe(http, audio_stream, self.config.clone(), api_key)
+            .map(|item| item.map_err(anyhow::Error::from));
+
+        Ok(TranscribeStream::new(Box::pin(transcribe_stream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio_util::bytes::Bytes;
+
+    use super::*;
+    use base_client::asr_client::AsrClient;
+    use base_client::vad::ms_to_samples;
+
+    fn pcm(ms: u32, amplitude: i16) -> Vec<u8> {
+        (0..ms_to_samples(ms))
+            .flat_map(|i| {
+                let sample = if i % 2 == 0 { amplitude } else { -amplitude };
+                sample.to_le_bytes()
+            })
+            .collect()
+    }
+
+    /// A minimal HTTP/1.1 server answering each request with the next of `responses`, and
+    /// returning the request bodies it received.
+    async fn serve(listener: TcpListener, responses: Vec<(u16, &'static str)>) -> Vec<String> {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let mut reader = BufReader::new(tcp);
+        let mut bodies = Vec::new();
+        for (status, body) in responses {
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).await.unwrap();
+            bodies.push(String::from_utf8_lossy(&request_body).into_owned());
+
+            let response = format!(
+                "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            reader
+                .get_mut()
+                .write_all(response.as_bytes())
+                .await
+                .unwrap();
+        }
+        bodies
+    }
+
+    fn audio_stream() -> AudioStream {
+        let audio = [pcm(990, 0), pcm(990, 8000), pcm(1200, 0), pcm(600, 8000)].concat();
+        let chunks: Vec<_> = audio
+            .chunks(3200)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        AudioStream(Box::pin(futures_util::stream::iter(chunks)))
+    }
+
+    #[tokio::test]
+    async fn one_final_per_utterance() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(
+            listener,
+            vec![
+                (200, r#"{"text": " First. "}"#),
+                (200, r#"{"text": "Second."}"#),
+            ],
+        ));
+
+        let backend = OpenAiTranscriptionsClient::new(OpenAiTranscriptionsConfig {
+            base_url: Some(base_url),
+            language: Some("en".to_string()),
+            ..OpenAiTranscriptionsConfig::default()
+        });
+        let client = backend.create(audio_stream()).await.unwrap();
+        let events: Vec<(u32, String, bool)> = client
+            .map(|event| {
+                let event = event.unwrap();
+                (event.begin_time, event.text, event.sentence_end)
+            })
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            [
+                (690, PLACEHOLDER.to_string(), false),
+                (690, "First.".to_string(), true),
+                (2880, PLACEHOLDER.to_string(), false),
+                (2880, "Second.".to_string(), true),
+            ]
+        );
+
+        let bodies = server.await.unwrap();
+        assert!(bodies[0].contains("RIFF"));
+        assert!(bodies[0].contains("whisper-1"));
+        assert!(bodies[0].contains("name=\"language\"\r\n\r\nen"));
+    }
+
+    #[tokio::test]
+    async fn failed_upload_clears_placeholder() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, vec![(500, r#"{"error": "boom"}"#)]));
+
+        let backend = OpenAiTranscriptionsClient::new(OpenAiTranscriptionsConfig {
+            base_url: Some(base_url),
+            ..OpenAiTranscriptionsConfig::default()
+        });
+        let events: Vec<_> = backend
+            .create(audio_stream())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(events.len(), 3);
+        let cleared = events[1].as_ref().unwrap();
+        assert!(cleared.text.is_empty() && cleared.sentence_end);
+        assert!(events[2].as_ref().unwrap_err().to_string().contains("500"));
+    }
+
+    #[test]
+    fn debug_redacts_api_key() {
+        let config = OpenAiTranscriptionsConfig {
+            api_key: ApiKey::new("sk-secret").into(),
+            ..OpenAiTranscriptionsConfig::default()
+        };
+        assert!(!format!("{config:?}").contains("sk-secret"));
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use base_client::api_key::BearerApiKey;
+use base_client::vad::VadConfig;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenAiTranscriptionsConfig {
+    /// Sent as a bearer token; many self-hosted servers need none.
+    #[serde(flatten)]
+    pub api_key: BearerApiKey,
+    /// e.g. `http://127.0.0.1:8000/v1`.
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    /// ISO-639-1 code, e.g. `en`.
+    pub language: Option<String>,
+    /// Free text that guides the transcription, e.g. expected vocabulary.
+    pub prompt: Option<String>,
+    pub vad: Option<VadConfig>,
+}
+
+impl OpenAiTranscriptionsConfig {
+    pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+    pub const DEFAULT_MODEL: &str = "whisper-1";
+
+    #[must_use]
+    pub fn transcriptions_url(&self) -> String {
+        let base_url = self.base_url.as_deref().unwrap_or(Self::DEFAULT_BASE_URL);
+        format!("{}/audio/transcriptions", base_url.trim_end_matches('/'))
+    }
+
+    #[must_use]
+    pub fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or(Self::DEFAULT_MODEL)
+    }
+}
@@ -0,0 +1,22 @@
+use std::io;
+
+use base_client::api_key::ApiKeyError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OpenAiTranscriptionsError {
+    /// Errors raised while sending the request or reading the response.
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The server answered with an error status.
+    #[error("server returned {status}: {body}")]
+    Status { status: u16, body: String },
+
+    /// The API key could not be read from its configured source.
+    #[error("api key error: {0}")]
+    ApiKey(#[from] ApiKeyError),
+
+    // Audio error
+    #[error("audio error: {0}")]
+    Audio(#[from] io::Error),
+}
@@ -0,0 +1,9 @@
+#![cfg_attr(test, allow(warnings))]
+
+//! Client for OpenAI-compatible `/audio/transcriptions` HTTP endpoints, made incremental by
+//! cutting the audio into utterances.
+
+pub mod client;
+pub mod config;
+pub mod error;
+mod wav;
@@ -1,194 +1,470 @@
-use async_stream::stream;
-use futures_util::Stream;
-use futures_util::{SinkExt, StreamExt};
-use tokio::select;
-use tokio_tungstenite::connect_async;
-use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::tungstenite::client::IntoClientRequest;
-use tokio_tungstenite::tungstenite::http::HeaderValue;
-use tokio_tungstenite::tungstenite::http::header::AUTHORIZATION;
-use tracing::{error, info, trace};
-
-use base_client::asr_client::AsrClient;
-use base_client::audio_stream::AudioStream;
-use base_client::grpc_server::TranscribeResponse;
-use base_client::transcribe_stream::TranscribeStream;
+use base_client::api_key::DashscopeApiKey;
+use base_client::run_task::{RunTaskClient, Sentence, SentenceOutput, Task};
 
 use crate::config::ParaformerV2Config;
 use crate::error::ParaformerV2Error;
-use crate::types;
+use crate::types::paraformer::Parameters;
 
 /// Read more: <https://help.aliyun.com/zh/model-studio/websocket-for-paraformer-real-time-service>
-#[derive(Debug)]
-pub struct ParaformerV2Client {
-    config: ParaformerV2Config,
-}
+pub type ParaformerV2Client = RunTaskClient<ParaformerV2Config>;
+
+impl Task for ParaformerV2Config {
+    const MODEL: &'static str = "paraformer-realtime-v2";
+    const NAME: &'static str = "paraformer-v2";
+
+    type Parameters = Parameters;
+    type Output = SentenceOutput;
+    type Results = ();
+    type Error = ParaformerV2Error;
+
+    fn api_key(&self) -> &DashscopeApiKey {
+        &self.api_key
+    }
+
+    fn websocket_url(&self) -> &str {
+        Self::websocket_url(self)
+    }
+
+    fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    fn traffic_log_dir(&self) -> Option<&str> {
+        self.traffic_log_dir.as_deref()
+    }
 
-#[allow(clippy::enum_variant_names)]
-enum Stage {
-    AwaitTaskStarted,
-    AwaitResultGenerated,
-    AwaitTaskFinished,
+    fn max_task_duration_secs(&self) -> u32 {
+        Self::max_task_duration_secs(self)
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::new(self)
+    }
+
+    fn results(&self, (): &mut (), output: SentenceOutput) -> Vec<Sentence> {
+        vec![output.into()]
+    }
 }
 
-fn transcribe<W>(
-    web_socket_stream: W,
-    mut audio_stream: AudioStream,
-    config: ParaformerV2Config,
-) -> impl Stream<Item = Result<TranscribeResponse, ParaformerV2Error>>
-where
-    W: Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
-        + StreamExt
-        + futures_util::Sink<Message>,
-    <W as futures_util::Sink<Message>>::Error: std::fmt::Debug,
-    ParaformerV2Error: From<<W as futures_util::Sink<Message>>::Error>,
-{
-    let (mut send, mut recv) = web_socket_stream.split();
-
-    stream! {
-        let mut stage: Stage = Stage::AwaitTaskStarted;
-        let task_id = {
-            let run_task_req = types::run_task::request::Request::new(&config);
-            let text = match serde_json::to_string(&run_task_req) { Ok(t) => t, Err(e) => { yield Err(e.into()); return; } };
-            if let Err(_e) = send.send(Message::Text(text.into())).await { yield Err(ParaformerV2Error::Connection); return; }
-            run_task_req.header.task_id.clone()
-        };
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use base_client::traffic_log::{Entry, Frame, Replay};
+    use dashscope_mock::{MockServer, Step, paraformer};
+    use futures_util::{Stream, StreamExt};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use tokio::sync::mpsc;
+    use tokio_util::bytes::Bytes;
+
+    use super::*;
+    use base_client::api_key::ApiKey;
+    use base_client::asr_client::AsrClient;
+    use base_client::audio_stream::AudioStream;
+    use base_client::grpc_server::TranscribeResponse;
+    use base_client::run_task::{RunTaskError, transcribe};
+
+    /// 100ms of silence.
+    fn chunk() -> Bytes {
+        Bytes::from(vec![0u8; 3200])
+    }
+
+    /// A task that recognizes `text` at 100ms once it is finished.
+    fn task(text: &str) -> Vec<Step> {
+        vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_started()),
+            Step::Expect("finish-task"),
+            Step::Send(paraformer::result(text, 100, Some(500))),
+            Step::Send(paraformer::task_finished()),
+        ]
+    }
+
+    fn client(server: &MockServer) -> ParaformerV2Client {
+        ParaformerV2Client::new(ParaformerV2Config {
+            api_key: ApiKey::new("sk-test").into(),
+            dashscope_websocket_url: Some(server.url()),
+            ..ParaformerV2Config::default()
+        })
+    }
+
+    fn audio(chunks: usize) -> AudioStream {
+        let audio = (0..chunks).map(|_| Ok(chunk()));
+        AudioStream(Box::pin(futures_util::stream::iter(audio)))
+    }
+
+    fn events(events: Vec<Result<TranscribeResponse, anyhow::Error>>) -> Vec<(u32, String, bool)> {
+        events
+            .into_iter()
+            .map(|event| {
+                let event = event.unwrap();
+                (event.begin_time, event.text, event.sentence_end)
+            })
+            .collect()
+    }
+
+    fn audio_received(connections: &[dashscope_mock::Connection]) -> Vec<usize> {
+        connections.iter().map(|c| c.audio.len()).collect()
+    }
+
+    #[tokio::test]
+    async fn rollover_on_max_duration() {
+        let server =
+            MockServer::start(vec![task("Task 1."), task("Task 2."), task("Task 3.")]).await;
+
+        let backend = ParaformerV2Client::new(ParaformerV2Config {
+            api_key: ApiKey::new("sk-test").into(),
+            dashscope_websocket_url: Some(server.url()),
+            max_task_duration_secs: Some(1),
+            ..ParaformerV2Config::default()
+        });
+        let client = backend.create(audio(25)).await.unwrap();
 
-        loop {
-            select! {
-                chunk = audio_stream.next(), if matches!(stage, Stage::AwaitResultGenerated) => {
-                    if let Some(chunk) = chunk {
-                        match chunk {
-                            Ok(chunk) => {
-                                if let Err(_e) = send.send(Message::Binary(chunk)).await {
-                                    yield Err(ParaformerV2Error::Connection);
-                                    break;
-                                }
-                            },
-                            Err(err) => {
-                                yield Err(ParaformerV2Error::Audio(err));
-                            }
-                        }
-                    } else {
-                        let finish_req = types::finish_task::request::Request::new(&task_id);
-                        let txt = match serde_json::to_string(&finish_req) {
-                            Ok(t) => t,
-                            Err(e) => {
-                                yield Err(e.into());
-                                break;
-                            }
-                        };
-                        if let Err(_e) = send.send(Message::Text(txt.into())).await {
-                            yield Err(ParaformerV2Error::Connection);
-                            break;
-                        }
-                        stage = Stage::AwaitTaskFinished;
-                    }
-                }
-                server_msg = recv.next() => {
-                    match server_msg {
-                        Some(Ok(Message::Text(text))) => {
-                            let server_event = serde_json::from_str::<types::ServerEvent>(&text);
-
-                            let server_event = match server_event {
-                                Ok(e) => e,
-                                Err(_e) => {
-                                    error!("failed to parse server event: {text}");
-                                    break;
-                                },
-                            };
-
-                            match server_event {
-                                types::ServerEvent::TaskFailed(response) => {
-                                    error!("TaskFailed {response:?}");
-                                    yield Err(ParaformerV2Error::Connection);
-                                    let _ = send.close().await;
-                                    break;
-                                } ,
-                                types::ServerEvent::TaskStarted(response) => {
-                                    trace!("TaskStarted {response:?}");
-                                    stage = Stage::AwaitResultGenerated;
-                                },
-                                types::ServerEvent::ResultGenerated(response) => {
-                                    yield Ok(response.into());
-                                },
-                                types::ServerEvent::TaskFinished(response) => {
-                                    info!("TaskFinished {response:?}");
-                                    let _ = send.close().await;
-                                },
-                            }
-                        }
-                        Some(Ok(Message::Ping(data))) => {
-                            let _ = send.send(Message::Pong(data)).await;
-                        }
-                        Some(Ok(Message::Pong(_))) => {
-                            // ignore
-                        }
-                        Some(Ok(Message::Close(frame))) => {
-                            match frame {
-                                Some(close_frame) => {
-                                    info!("close by server: {:?}", &close_frame);
-                                    yield Err(ParaformerV2Error::Closed(close_frame.reason.as_str().to_string()));
-                                },
-                                None => {
-                                    info!("close by server: {:?}", frame);
-                                }
-                            }
-                        }
-                        None => {
-                            info!("server disconnected.");
-                            return;
-                        }
-                        Some(Ok(Message::Binary(_))) => {
-                            unreachable!("Unexpected binary");
-                        }
-                        Some(Ok(Message::Frame(_))) => {
-                            unreachable!("Unexpected frame");
-                        }
-                        Some(Err(error)) => {
-                            error!("connection error: {:?}", error);
-                            yield Err(ParaformerV2Error::Connection);
-                            break;
-                        }
-                    }
-                }
-            }
+        assert_eq!(
+            events(client.collect().await),
+            [
+                (100, "Task 1.".to_string(), true),
+                (1100, "Task 2.".to_string(), true),
+                (2100, "Task 3.".to_string(), true),
+            ]
+        );
+        assert_eq!(
+            audio_received(&server.connections().await),
+            [32000, 32000, 16000]
+        );
+    }
+
+    /// A task cut after 500ms of audio, after a sentence and the start of another.
+    fn cut_task(cut: Step) -> Vec<Step> {
+        vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_started()),
+            Step::ExpectAudio(5 * 3200),
+            Step::Send(paraformer::result("First.", 100, Some(300))),
+            Step::Send(paraformer::result("hel", 400, None)),
+            cut,
+        ]
+    }
+
+    fn audio_channel() -> (
+        mpsc::UnboundedSender<Result<Bytes, std::io::Error>>,
+        AudioStream,
+    ) {
+        let (audio, mut rx) = mpsc::unbounded_channel();
+        let audio_stream = AudioStream(Box::pin(futures_util::stream::poll_fn(move |cx| {
+            rx.poll_recv(cx)
+        })));
+        (audio, audio_stream)
+    }
+
+    /// Streams 500ms of audio, takes the first three events, then streams another 500ms.
+    async fn interrupted_session<E: std::fmt::Debug>(
+        audio: mpsc::UnboundedSender<Result<Bytes, std::io::Error>>,
+        mut client: impl Stream<Item = Result<TranscribeResponse, E>> + Unpin,
+    ) -> Vec<(u32, String, bool)> {
+        let mut events = Vec::new();
+        let mut push = |event: Result<TranscribeResponse, E>| {
+            let event = event.unwrap();
+            events.push((event.begin_time, event.text, event.sentence_end));
+        };
+        for _ in 0..5 {
+            audio.send(Ok(chunk())).unwrap();
+        }
+        for _ in 0..3 {
+            push(client.next().await.unwrap());
+        }
+        for _ in 0..5 {
+            audio.send(Ok(chunk())).unwrap();
         }
+        drop(audio);
+        while let Some(event) = client.next().await {
+            push(event);
+        }
+        events
+    }
+
+    async fn reconnect(cut: Step) {
+        let server = MockServer::start(vec![cut_task(cut), task("Task 2.")]).await;
+
+        let (audio, audio_stream) = audio_channel();
+        let client = client(&server).create(audio_stream).await.unwrap();
+
+        // The unfinished sentence is withdrawn, to be recognized again.
+        assert_eq!(
+            interrupted_session(audio, client).await,
+            [
+                (100, "First.".to_string(), true),
+                (400, "hel".to_string(), false),
+                (400, String::new(), false),
+                (400, "Task 2.".to_string(), true),
+            ]
+        );
+        // The audio after the first sentence is replayed.
+        assert_eq!(
+            audio_received(&server.connections().await),
+            [16000, 6400 + 16000]
+        );
+    }
+
+    #[tokio::test]
+    async fn reconnect_after_server_close() {
+        reconnect(Step::Close(None)).await;
+    }
+
+    #[tokio::test]
+    async fn reconnect_after_connection_reset() {
+        reconnect(Step::Reset).await;
     }
-}
 
-#[async_trait::async_trait]
-impl AsrClient for ParaformerV2Client {
-    type Config = ParaformerV2Config;
-    type TranscriptionStream = TranscribeStream<anyhow::Error>;
+    #[tokio::test]
+    async fn early_task_finish_starts_new_task() {
+        let early = vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_started()),
+            Step::ExpectAudio(5 * 3200),
+            Step::Send(paraformer::task_finished()),
+        ];
+        let next = vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_started()),
+            Step::Send(paraformer::result("Task 2.", 100, Some(300))),
+            Step::Expect("finish-task"),
+            Step::Send(paraformer::task_finished()),
+        ];
+        let server = MockServer::start(vec![early, next]).await;
 
-    fn new(config: impl Into<Self::Config>) -> Self {
-        Self {
-            config: config.into(),
+        let (audio, audio_stream) = audio_channel();
+        let mut client = client(&server).create(audio_stream).await.unwrap();
+        for _ in 0..5 {
+            audio.send(Ok(chunk())).unwrap();
+        }
+        let result = client.next().await.unwrap().unwrap();
+        for _ in 0..5 {
+            audio.send(Ok(chunk())).unwrap();
         }
+        drop(audio);
+        assert!(client.collect::<Vec<_>>().await.is_empty());
+
+        // The new task starts after the audio of the finished one.
+        assert_eq!((result.begin_time, result.text.as_str()), (600, "Task 2."));
+        assert_eq!(
+            audio_received(&server.connections().await),
+            [5 * 3200, 5 * 3200]
+        );
+    }
+
+    #[tokio::test]
+    async fn audio_waits_for_slow_task_start() {
+        let mut script = task("Done.");
+        script.insert(1, Step::Delay(Duration::from_millis(200)));
+        let server = MockServer::start(vec![script]).await;
+
+        let client = client(&server).create(audio(5)).await.unwrap();
+
+        assert_eq!(
+            events(client.collect().await),
+            [(100, "Done.".to_string(), true)]
+        );
+        let connections = server.connections().await;
+        assert_eq!(audio_received(&connections), [16000]);
+        assert_eq!(connections[0].event_names(), ["run-task", "finish-task"]);
+    }
+
+    #[tokio::test]
+    async fn task_failed_ends_session() {
+        let script = vec![
+            Step::Expect("run-task"),
+            Step::Send(paraformer::task_started()),
+            Step::ExpectAudio(3200),
+            Step::Send(paraformer::task_failed(
+                "InvalidParameter",
+                "request timeout after 23 seconds.",
+            )),
+        ];
+        let server = MockServer::start(vec![script]).await;
+
+        let client = client(&server).create(audio(5)).await.unwrap();
+
+        let events: Vec<_> = client.collect().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].as_ref().unwrap_err().to_string(),
+            "task failed (InvalidParameter): request timeout after 23 seconds."
+        );
+        assert!(matches!(
+            events[0]
+                .as_ref()
+                .unwrap_err()
+                .downcast_ref::<ParaformerV2Error>(),
+            Some(ParaformerV2Error::TaskFailed { .. })
+        ));
+        server.connections().await;
+    }
+
+    #[tokio::test]
+    async fn close_before_task_start_is_reported() {
+        let script = vec![
+            Step::Expect("run-task"),
+            Step::Close(Some((1008, "Access denied."))),
+        ];
+        let server = MockServer::start(vec![script]).await;
+
+        let client = client(&server).create(audio(5)).await.unwrap();
+
+        let events: Vec<_> = client.collect().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].as_ref().unwrap_err().to_string(),
+            "connection closed: Access denied."
+        );
+        server.connections().await;
+    }
+
+    #[tokio::test]
+    async fn recorded_session_replays() {
+        let dir = std::env::temp_dir().join(format!("paraformer-traffic-{}", std::process::id()));
+        let server = MockServer::start(vec![cut_task(Step::Reset), task("Task 2.")]).await;
+        let backend = ParaformerV2Client::new(ParaformerV2Config {
+            api_key: ApiKey::new("sk-test").into(),
+            dashscope_websocket_url: Some(server.url()),
+            traffic_log_dir: Some(dir.to_string_lossy().into_owned()),
+            ..ParaformerV2Config::default()
+        });
+        let (audio, audio_stream) = audio_channel();
+        let client = backend.create(audio_stream).await.unwrap();
+        let recorded = interrupted_session(audio, client).await;
+        server.connections().await;
+
+        let log = std::fs::read_dir(&dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let mut replay = Replay::load(&log).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let first = replay.next_connection().unwrap();
+        let (audio, audio_stream) = audio_channel();
+        let client = transcribe(
+            move || std::future::ready(replay.next_connection().ok_or(RunTaskError::Connection)),
+            first,
+            audio_stream,
+            ParaformerV2Config::default(),
+        );
+
+        assert_eq!(interrupted_session(audio, Box::pin(client)).await, recorded);
+    }
+
+    /// Mostly plausible times, and some that overflow when offset.
+    fn time_ms() -> impl Strategy<Value = u32> {
+        prop_oneof![0..60_000u32, u32::MAX - 60_000..=u32::MAX]
     }
 
-    async fn create(
-        &self,
-        audio_stream: AudioStream,
-    ) -> Result<Self::TranscriptionStream, anyhow::Error> {
-        let config = self.config.clone();
-        let mut request = config.websocket_url().into_client_request()?;
-        let headers = request.headers_mut();
+    /// A server frame of an arbitrary session: mostly events, in any order and with any values.
+    fn arbitrary_entry() -> impl Strategy<Value = Entry> {
+        let event = prop_oneof![
+            Just(paraformer::task_started()),
+            ("[a-z ]{0,8}", time_ms(), proptest::option::of(time_ms())).prop_map(
+                |(text, begin_time, end_time)| paraformer::result(&text, begin_time, end_time)
+            ),
+            Just(paraformer::task_finished()),
+            Just(paraformer::task_failed("InvalidParameter", "bad request")),
+        ]
+        .prop_map(received);
+        prop_oneof![
+            8 => event,
+            1 => any::<String>().prop_map(|text| Entry::Received(Frame::Text(text))),
+            1 => Just(Entry::Received(Frame::Binary("AAA=".to_string()))),
+            1 => Just(Entry::Received(Frame::Close(None))),
+            1 => Just(Entry::Error("connection reset".to_string())),
+        ]
+    }
 
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", config.dashscope_api_key))
-                .map_err(|_| ParaformerV2Error::InvalidHeaderValue("Authorization"))?,
+    /// Replays `connections` to a session over `chunks` of audio. Returns its results, or `None`
+    /// if it did not end.
+    fn replayed_session(
+        connections: Vec<Vec<Entry>>,
+        chunks: Vec<Vec<u8>>,
+        config: ParaformerV2Config,
+    ) -> Option<Vec<Result<TranscribeResponse, RunTaskError>>> {
+        let jsonl: String = connections
+            .into_iter()
+            .flat_map(|entries| {
+                let url = ParaformerV2Config::DEFAULT_WEBSOCKET_URL.to_string();
+                std::iter::once(Entry::Connect { url }).chain(entries)
+            })
+            .map(|entry| serde_json::to_string(&entry).unwrap() + "\n")
+            .collect();
+        let mut replay = Replay::parse(&jsonl).unwrap();
+        let first = replay.next_connection().unwrap();
+        let audio = chunks.into_iter().map(|chunk| Ok(Bytes::from(chunk)));
+        let session = transcribe(
+            move || std::future::ready(replay.next_connection().ok_or(RunTaskError::Connection)),
+            first,
+            AudioStream(Box::pin(futures_util::stream::iter(audio))),
+            config,
         );
 
-        let (ws_stream, _resp) = connect_async(request)
-            .await
-            .map_err(ParaformerV2Error::from)?;
+        // Reconnects back off in paused time, which skips ahead whenever the session waits.
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            tokio::time::timeout(Duration::from_hours(24), session.collect())
+                .await
+                .ok()
+        })
+    }
 
-        let transcribe_stream = transcribe(ws_stream, audio_stream, config)
-            .map(|item| item.map_err(anyhow::Error::from));
+    /// A server event of the task `task`.
+    fn received(mut event: serde_json::Value) -> Entry {
+        event["header"]["task_id"] = "task".into();
+        Entry::Received(Frame::Text(event.to_string()))
+    }
 
-        Ok(TranscribeStream::new(Box::pin(transcribe_stream)))
+    #[test]
+    fn late_times_saturate() {
+        // Rolls over after a second of audio, so that the second task starts 1000ms in.
+        let sent = || Entry::Sent(Frame::Text(String::new()));
+        let first_task = [sent(), received(paraformer::task_started())]
+            .into_iter()
+            .chain(std::iter::repeat_with(sent).take(11))
+            .chain([received(paraformer::task_finished())])
+            .collect();
+        let results = replayed_session(
+            vec![
+                first_task,
+                vec![
+                    received(paraformer::task_started()),
+                    received(paraformer::result("late", u32::MAX, Some(u32::MAX))),
+                    received(paraformer::task_finished()),
+                ],
+            ],
+            vec![vec![0; 3200]; 10],
+            ParaformerV2Config {
+                max_task_duration_secs: Some(1),
+                ..ParaformerV2Config::default()
+            },
+        );
+        let results = results.unwrap();
+        assert_eq!(results[0].as_ref().unwrap().begin_time, u32::MAX);
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_server_frames_end_the_session(
+            connections in vec(vec(arbitrary_entry(), 0..12), 1..4),
+            chunks in vec(vec(any::<u8>(), 0..4000), 0..8),
+            max_task_duration_secs in proptest::option::of(1..3u32),
+        ) {
+            let config = ParaformerV2Config {
+                max_task_duration_secs,
+                ..ParaformerV2Config::default()
+            };
+            prop_assert!(replayed_session(connections, chunks, config).is_some());
+        }
     }
 }
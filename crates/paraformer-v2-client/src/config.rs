@@ -1,28 +1,55 @@
 use serde::{Deserialize, Serialize};
 
+use base_client::api_key::DashscopeApiKey;
+
 use crate::types;
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ParaformerV2Config {
-    pub dashscope_api_key: String,
+    #[serde(flatten)]
+    pub api_key: DashscopeApiKey,
     pub dashscope_websocket_url: Option<String>,
     pub disfluency_removal_enabled: Option<bool>,
-    pub language_hints: Option<Vec<types::run_task::request::Language>>,
+    pub language_hints: Option<Vec<types::paraformer::Language>>,
     pub semantic_punctuation_enabled: Option<bool>,
     pub max_sentence_silence: Option<u32>,
     pub multi_threshold_mode_enabled: Option<bool>,
     pub punctuation_prediction_enabled: Option<bool>,
     pub inverse_text_normalization_enabled: Option<bool>,
+    /// A hotword vocabulary created beforehand, e.g. in the Model Studio console.
+    pub vocabulary_id: Option<String>,
+    /// Hotwords kept in sync with a vocabulary by dictyped, when `vocabulary_id` is not set.
+    pub vocabulary: Option<Vec<String>>,
+    /// A task is rolled over to a new one after this long, at the next sentence boundary. At
+    /// least 10 seconds.
+    pub max_task_duration_secs: Option<u32>,
+    /// Each session's websocket traffic is written to a JSONL file in this directory, for
+    /// debugging. The API key is redacted.
+    pub traffic_log_dir: Option<String>,
+    /// `http://` or `socks5://` proxy for the websocket, with optional `user:password@`. Without
+    /// it, `HTTPS_PROXY` or `ALL_PROXY` is used, unless `NO_PROXY` lists the host.
+    pub proxy: Option<String>,
 }
 
 impl ParaformerV2Config {
     pub const DEFAULT_WEBSOCKET_URL: &str = "wss://dashscope.aliyuncs.com/api-ws/v1/inference";
 
+    pub const DEFAULT_MAX_TASK_DURATION_SECS: u32 = 30 * 60;
+
+    /// Shorter tasks would open a connection every few seconds.
+    pub const MIN_MAX_TASK_DURATION_SECS: u32 = 10;
+
     #[must_use]
     pub fn websocket_url(&self) -> &str {
         self.dashscope_websocket_url
             .as_deref()
             .unwrap_or(Self::DEFAULT_WEBSOCKET_URL)
     }
+
+    #[must_use]
+    pub fn max_task_duration_secs(&self) -> u32 {
+        self.max_task_duration_secs
+            .unwrap_or(Self::DEFAULT_MAX_TASK_DURATION_SECS)
+    }
 }
@@ -2,6 +2,9 @@ use std::io;
 
 use tokio_tungstenite::tungstenite::Error as WsError;
 
+use base_client::api_key::ApiKeyError;
+use base_client::run_task::RunTaskError;
+
 #[derive(Debug, thiserror::Error)]
 #[allow(dead_code)]
 pub enum ParaformerV2Error {
@@ -21,9 +24,49 @@ pub enum ParaformerV2Error {
     #[error("invalid header value for `{0}`")]
     InvalidHeaderValue(&'static str),
 
+    /// Errors raised by HTTP requests, e.g. to the customization API.
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// An error returned by an HTTP API of the service.
+    #[error("api error {code}: {message}")]
+    Api { code: String, message: String },
+
+    /// The API key could not be read from its configured source.
+    #[error("api key error: {0}")]
+    ApiKey(#[from] ApiKeyError),
+
+    /// The service failed the task, e.g. for an invalid API key or unsupported audio.
+    #[error("task failed ({code}): {message}")]
+    TaskFailed { code: String, message: String },
+
     #[error("connection error")]
     Connection,
 
     #[error("connection closed: {0}")]
     Closed(String),
+
+    #[error("invalid proxy `{0}`")]
+    InvalidProxy(String),
+
+    /// The proxy could not be reached or refused the tunnel.
+    #[error("proxy error: {0}")]
+    Proxy(String),
+}
+
+impl From<RunTaskError> for ParaformerV2Error {
+    fn from(error: RunTaskError) -> Self {
+        match error {
+            RunTaskError::WebSocket(error) => Self::WebSocket(error),
+            RunTaskError::Serialization(error) => Self::Serialization(error),
+            RunTaskError::Audio(error) => Self::Audio(error),
+            RunTaskError::InvalidHeaderValue(header) => Self::InvalidHeaderValue(header),
+            RunTaskError::ApiKey(error) => Self::ApiKey(error),
+            RunTaskError::TaskFailed { code, message } => Self::TaskFailed { code, message },
+            RunTaskError::Connection => Self::Connection,
+            RunTaskError::Closed(reason) => Self::Closed(reason),
+            RunTaskError::InvalidProxy(proxy) => Self::InvalidProxy(proxy),
+            RunTaskError::Proxy(error) => Self::Proxy(error),
+        }
+    }
 }
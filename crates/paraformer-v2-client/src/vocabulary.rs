@@ -0,0 +1,210 @@
+//! Hotword vocabularies, managed with the customization API.
+//!
+//! Read more: <https://help.aliyun.com/zh/model-studio/custom-hot-words>
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::trace;
+
+use base_client::api_key::ApiKey;
+
+use crate::error::ParaformerV2Error;
+
+const TARGET_MODEL: &str = "paraformer-realtime-v2";
+const DEFAULT_WEIGHT: u8 = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VocabularyEntry {
+    pub text: String,
+    /// `1..=5`; higher is more likely to be recognized.
+    pub weight: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+}
+
+impl VocabularyEntry {
+    #[must_use]
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            weight: DEFAULT_WEIGHT,
+            lang: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    output: Option<T>,
+    code: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateOutput {
+    vocabulary_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListedVocabulary {
+    vocabulary_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListOutput {
+    #[serde(default)]
+    vocabulary_list: Vec<ListedVocabulary>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryOutput {
+    #[serde(default)]
+    vocabulary: Vec<VocabularyEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmptyOutput {}
+
+#[derive(Debug, Clone)]
+pub struct VocabularyClient {
+    http: reqwest::Client,
+    api_key: ApiKey,
+    url: String,
+}
+
+impl VocabularyClient {
+    pub const DEFAULT_URL: &str =
+        "https://dashscope.aliyuncs.com/api/v1/services/audio/asr/customization";
+
+    pub fn new(api_key: impl Into<ApiKey>, url: Option<&str>) -> Result<Self, ParaformerV2Error> {
+        Ok(Self {
+            http: reqwest::Client::builder().build()?,
+            api_key: api_key.into(),
+            url: url.unwrap_or(Self::DEFAULT_URL).to_string(),
+        })
+    }
+
+    /// Creates a vocabulary and returns its id. `prefix` is up to 9 lowercase letters or digits.
+    pub async fn create(
+        &self,
+        prefix: &str,
+        vocabulary: &[VocabularyEntry],
+    ) -> Result<String, ParaformerV2Error> {
+        let output: CreateOutput = self
+            .call(json!({
+                "action": "create_vocabulary",
+                "target_model": TARGET_MODEL,
+                "prefix": prefix,
+                "vocabulary": vocabulary,
+            }))
+            .await?;
+        Ok(output.vocabulary_id)
+    }
+
+    /// Returns the ids of the vocabularies created with `prefix`.
+    pub async fn list(&self, prefix: &str) -> Result<Vec<String>, ParaformerV2Error> {
+        let output: ListOutput = self
+            .call(json!({
+                "action": "list_vocabulary",
+                "prefix": prefix,
+                "page_index": 0,
+                "page_size": 10,
+            }))
+            .await?;
+        Ok(output
+            .vocabulary_list
+            .into_iter()
+            .map(|vocabulary| vocabulary.vocabulary_id)
+            .collect())
+    }
+
+    pub async fn query(
+        &self,
+        vocabulary_id: &str,
+    ) -> Result<Vec<VocabularyEntry>, ParaformerV2Error> {
+        let output: QueryOutput = self
+            .call(json!({
+                "action": "query_vocabulary",
+                "vocabulary_id": vocabulary_id,
+            }))
+            .await?;
+        Ok(output.vocabulary)
+    }
+
+    pub async fn update(
+        &self,
+        vocabulary_id: &str,
+        vocabulary: &[VocabularyEntry],
+    ) -> Result<(), ParaformerV2Error> {
+        let _: EmptyOutput = self
+            .call(json!({
+                "action": "update_vocabulary",
+                "vocabulary_id": vocabulary_id,
+                "vocabulary": vocabulary,
+            }))
+            .await?;
+        Ok(())
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        input: serde_json::Value,
+    ) -> Result<T, ParaformerV2Error> {
+        trace!("customization request: {input}");
+        let response = self
+            .http
+            .post(&self.url)
+            .bearer_auth(self.api_key.expose())
+            .json(&json!({ "model": "speech-biasing", "input": input }))
+            .send()
+            .await?;
+        let status = response.status();
+        let body = response.json::<ApiResponse<T>>().await?;
+        match body.output {
+            Some(output) if status.is_success() => Ok(output),
+            _ => Err(ParaformerV2Error::Api {
+                code: body.code.unwrap_or_else(|| status.to_string()),
+                message: body.message.unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_serialize() {
+        let entry = serde_json::to_value(VocabularyEntry::new("Dictype")).unwrap();
+        assert_eq!(entry, json!({"text": "Dictype", "weight": 4}));
+    }
+
+    #[test]
+    fn query_deserialize() {
+        let data = r#"
+        {
+          "output": {
+            "gmt_create": "2024-08-21 15:19:09",
+            "vocabulary": [{"weight": 4, "text": "Dictype", "lang": "en"}],
+            "target_model": "paraformer-realtime-v2",
+            "gmt_modified": "2024-08-21 15:19:09",
+            "status": "OK"
+          },
+          "usage": {"count": 1},
+          "request_id": "d4a7ea8d-4a1b-4b53-8a6b-xxxxxxxxxxxx"
+        }
+        "#;
+        let response: ApiResponse<QueryOutput> = serde_json::from_str(data).unwrap();
+        assert_eq!(response.output.unwrap().vocabulary[0].text, "Dictype");
+    }
+
+    #[test]
+    fn error_deserialize() {
+        let data = r#"{"request_id": "x", "code": "InvalidParameter", "message": "bad prefix"}"#;
+        let response: ApiResponse<ListOutput> = serde_json::from_str(data).unwrap();
+        assert!(response.output.is_none());
+        assert_eq!(response.code.as_deref(), Some("InvalidParameter"));
+    }
+}
@@ -10,7 +10,7 @@ use tokio::time::{self, Interval};
 use tokio_util::bytes::Bytes;
 use tokio_util::sync::CancellationToken;
 
-use base_client::audio_stream::{AudioCapture, AudioStream};
+use base_client::audio_stream::{AudioCapture, AudioSource, AudioStream};
 
 const WAV_HEADER_SIZE: usize = 44;
 const BYTES_PER_SECOND_16K_MONO_PCM16: usize = 16_000 * 2;
@@ -56,7 +56,11 @@ impl AudioCapture for PcmPlaybackRecorder {
         Ok(Self { pcm, chunk_size })
     }
 
-    fn create(&self, cancellation_token: CancellationToken) -> io::Result<AudioStream> {
+    fn create(
+        &self,
+        _source: AudioSource,
+        cancellation_token: CancellationToken,
+    ) -> io::Result<AudioStream> {
         Ok(AudioStream(Box::pin(PcmPlaybackStream {
             pcm: self.pcm.clone(),
             offset: 0,
@@ -108,7 +112,9 @@ mod tests {
     async fn emits_pcm_chunks() {
         let recorder =
             PcmPlaybackRecorder::new(PcmPlaybackCaptureOption::new(test_wav_path())).unwrap();
-        let mut audio_stream = recorder.create(CancellationToken::new()).unwrap();
+        let mut audio_stream = recorder
+            .create(AudioSource::default(), CancellationToken::new())
+            .unwrap();
         let first = audio_stream.next().await.unwrap().unwrap();
         assert!(!first.is_empty());
     }
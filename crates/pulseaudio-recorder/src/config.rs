@@ -4,4 +4,5 @@ use serde::{Deserialize, Serialize};
 #[serde(deny_unknown_fields)]
 pub struct PulseAudioConfig {
     pub preferred_source_name: Option<String>,
+    pub preferred_sink_name: Option<String>,
 }
@@ -11,7 +11,7 @@ use tokio_util::bytes::Bytes;
 use tokio_util::sync::CancellationToken;
 use tracing::{Instrument, debug, info_span, trace, warn};
 
-use base_client::audio_stream::{AudioCapture, AudioStream};
+use base_client::audio_stream::{AudioCapture, AudioSource, AudioStream};
 
 use crate::PulseAudioConfig;
 use crate::error::PulseAudioRecorderError;
@@ -41,14 +41,19 @@ impl AudioCapture for PulseAudioRecorder {
         })
     }
 
-    fn create(&self, cancellation_token: CancellationToken) -> io::Result<AudioStream> {
+    fn create(
+        &self,
+        source: AudioSource,
+        cancellation_token: CancellationToken,
+    ) -> io::Result<AudioStream> {
         let (tx, rx) = mpsc::unbounded_channel::<io::Result<Bytes>>();
         let client = self.client.clone();
         let capture_option = self.capture_option.clone();
 
         tokio::spawn(
             async move {
-                if let Err(err) = capture_loop(tx, cancellation_token, client, capture_option).await
+                if let Err(err) =
+                    capture_loop(tx, source, cancellation_token, client, capture_option).await
                 {
                     debug!("capture loop ended with error: {err}");
                 }
@@ -72,11 +77,12 @@ impl Stream for PulseAudioRecorderStream {
 
 async fn capture_loop(
     tx: mpsc::UnboundedSender<io::Result<Bytes>>,
+    source: AudioSource,
     cancellation_token: CancellationToken,
     client: Client,
     capture_option: PulseAudioConfig,
 ) -> Result<(), PulseAudioRecorderError> {
-    let result = run_capture_loop(&tx, cancellation_token, &client, capture_option).await;
+    let result = run_capture_loop(&tx, source, cancellation_token, &client, capture_option).await;
     if let Err(error) = &result {
         let _ = tx.send(Err(io::Error::other(error.to_string())));
     }
@@ -113,14 +119,68 @@ async fn get_source_info(
         .await
 }
 
+async fn get_sink_info(
+    client: &Client,
+    preferred_sink: Option<&str>,
+) -> pulseaudio::Result<protocol::SinkInfo> {
+    if let Some(sink_name) = preferred_sink {
+        match CString::new(sink_name) {
+            Ok(sink_name_c) => {
+                if let Ok(sink_info) = client.sink_info_by_name(sink_name_c).await {
+                    return Ok(sink_info);
+                }
+                warn!(
+                    preferred_sink = sink_name,
+                    "preferred sink unavailable, falling back to default sink"
+                );
+            }
+            Err(err) => {
+                warn!(
+                    preferred_sink = sink_name,
+                    error = %err,
+                    "invalid preferred sink name, falling back to default sink"
+                );
+            }
+        }
+    }
+    client
+        .sink_info_by_name(protocol::DEFAULT_SINK.to_owned())
+        .await
+}
+
+/// Resolves the monitor source of the preferred (or default) sink, i.e. what is being played back.
+async fn get_monitor_source_info(
+    client: &Client,
+    preferred_sink: Option<&str>,
+) -> pulseaudio::Result<protocol::SourceInfo> {
+    let sink_info = get_sink_info(client, preferred_sink).await?;
+    if let Some(index) = sink_info.monitor_source_index {
+        return client.source_info(index).await;
+    }
+    warn!(
+        sink = ?sink_info.name,
+        "sink has no monitor source, falling back to default monitor"
+    );
+    client
+        .source_info_by_name(c"@DEFAULT_MONITOR@".to_owned())
+        .await
+}
+
 async fn run_capture_loop(
     tx: &mpsc::UnboundedSender<io::Result<Bytes>>,
+    source: AudioSource,
     cancellation_token: CancellationToken,
     client: &Client,
     capture_option: PulseAudioConfig,
 ) -> Result<(), PulseAudioRecorderError> {
-    let source_info =
-        get_source_info(client, capture_option.preferred_source_name.as_deref()).await?;
+    let source_info = match source {
+        AudioSource::Microphone => {
+            get_source_info(client, capture_option.preferred_source_name.as_deref()).await?
+        }
+        AudioSource::Monitor => {
+            get_monitor_source_info(client, capture_option.preferred_sink_name.as_deref()).await?
+        }
+    };
     trace!("selected source: {source_info:?}");
 
     let params = protocol::RecordStreamParams {
@@ -160,7 +220,7 @@ mod tests {
     use tokio_stream::StreamExt;
     use tokio_util::sync::CancellationToken;
 
-    use base_client::audio_stream::AudioCapture;
+    use base_client::audio_stream::{AudioCapture, AudioSource};
 
     use crate::{PulseAudioConfig, PulseAudioRecorder};
 
@@ -170,7 +230,24 @@ mod tests {
         let Ok(recorder) = PulseAudioRecorder::new(PulseAudioConfig::default()) else {
             return;
         };
-        let mut audio_stream = recorder.create(CancellationToken::new()).unwrap();
+        let mut audio_stream = recorder
+            .create(AudioSource::Microphone, CancellationToken::new())
+            .unwrap();
+        match audio_stream.next().await {
+            Some(Ok(_)) => {}
+            _ => panic!("expected audio chunk"),
+        }
+    }
+
+    #[tokio::test]
+    #[cfg_attr(not(has_pulseaudio), ignore = "PulseAudio is likely not available.")]
+    async fn emits_monitor_pcm_chunks() {
+        let Ok(recorder) = PulseAudioRecorder::new(PulseAudioConfig::default()) else {
+            return;
+        };
+        let mut audio_stream = recorder
+            .create(AudioSource::Monitor, CancellationToken::new())
+            .unwrap();
         match audio_stream.next().await {
             Some(Ok(_)) => {}
             _ => panic!("expected audio chunk"),
@@ -1,17 +1,21 @@
-use async_stream::stream;
-use futures_util::{SinkExt, Stream, StreamExt};
-use tokio::select;
-use tokio_tungstenite::connect_async;
-use tracing::{error, info, trace};
-use tungstenite::Message;
-use tungstenite::client::IntoClientRequest;
-use tungstenite::http::HeaderValue;
-use tungstenite::http::header::AUTHORIZATION;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::sync::Arc;
 
+use futures_util::{Sink, Stream, StreamExt};
+use tracing::{error, info, trace, warn};
+use tungstenite::{Bytes, Message};
+
+use base_client::api_key::ApiKey;
 use base_client::asr_client::AsrClient;
 use base_client::audio_stream::AudioStream;
 use base_client::grpc_server::TranscribeResponse;
+use base_client::prepared_connection::PreparedConnection;
+use base_client::reconnect::ReplayBuffer;
+use base_client::traffic_log::{Recorded, TrafficLog};
 use base_client::transcribe_stream::TranscribeStream;
+use base_client::vad::{VoiceActivityDetector, ms_to_samples, samples_to_ms};
+use base_client::websocket_session::{self, Output, Protocol, WebSocket};
 
 use crate::client_state::ClientState;
 use crate::config::QwenV3Config;
@@ -22,6 +26,7 @@ use crate::types;
 #[derive(Debug)]
 pub struct QwenV3Client {
     config: QwenV3Config,
+    prepared: PreparedConnection<WebSocket>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -31,179 +36,404 @@ enum Stage {
     AwaitTaskFinished,
 }
 
-fn transcribe<W>(
-    web_socket_stream: W,
-    mut audio_stream: AudioStream,
+/// Unfinished audio kept for replay after a reconnect.
+const MAX_REPLAY_MS: u32 = 60_000;
+
+/// Audio kept for replay when a turn completes, in case server VAD has not reported the start of
+/// the next one yet.
+const REPLAY_MARGIN_MS: u32 = 1_000;
+
+/// How long a turn may hold back a due rollover before the session is finished anyway.
+const ROLLOVER_GRACE_MS: u32 = 30_000;
+
+async fn connect(config: &QwenV3Config, api_key: &ApiKey) -> Result<WebSocket, QwenV3Error> {
+    Ok(websocket_session::connect(
+        config.websocket_url(),
+        api_key.expose(),
+        config.proxy.as_deref(),
+    )
+    .await?)
+}
+
+/// A log for the traffic of a new session, if `traffic_log_dir` is set.
+fn traffic_log(config: &QwenV3Config, api_key: &ApiKey) -> Option<Arc<TrafficLog>> {
+    let dir = config.traffic_log_dir.as_ref()?;
+    match TrafficLog::create(dir, "qwen-v3", [api_key.expose().to_string()]) {
+        Ok(log) => {
+            info!("recording websocket traffic to {}", log.path().display());
+            Some(Arc::new(log))
+        }
+        Err(e) => {
+            error!("failed to create traffic log in {dir}: {e}");
+            None
+        }
+    }
+}
+
+/// What is replayed to a new session after the connection was lost.
+#[derive(Clone)]
+enum Replay {
+    Audio(Bytes),
+    /// A turn committed by us, with its start time.
+    Commit(u32),
+}
+
+/// Streams the audio through one server session per connection. A session is finished and
+/// replaced by a new one when it nears `max_session_duration_secs`. When the connection is lost
+/// instead, whatever was sent since the last completed turn is sent again to the next session.
+/// Server timestamps of later sessions are rebased onto the overall timeline.
+struct Transcription {
     config: QwenV3Config,
-) -> impl Stream<Item = Result<TranscribeResponse, QwenV3Error>>
-where
-    W: Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
-        + StreamExt
-        + futures_util::Sink<Message>,
-    <W as futures_util::Sink<Message>>::Error: std::fmt::Debug,
-    QwenV3Error: From<<W as futures_util::Sink<Message>>::Error>,
-{
-    let (mut send, mut recv) = web_socket_stream.split();
-
-    let mut stage = Stage::SessionCreating;
-    let mut event_count = 0;
-
-    let mut client_state: Option<ClientState> = None;
-
-    stream! {
-        loop {
-            select! {
-                server_msg = recv.next() => {
-                    match server_msg {
-                        Some(Ok(Message::Text(text))) => {
-                            let server_event = serde_json::from_str::<types::ServerEvent>(&text);
-
-                            let server_event = match server_event {
-                                Ok(e) => e,
-                                Err(_e) => {
-                                    error!("failed to parse ServerEvent: {text}");
-                                    break;
-                                },
-                            };
-
-                            match server_event {
-                                types::ServerEvent::Error(err) => {
-                                    error!("err: {err:?}");
-                                    let _ = send.close().await;
-                                }
-                                types::ServerEvent::SessionCreated(response) => {
-                                    trace!("session created: {:?}", &response);
-                                    let session_update = types::session::update::request::Request::new(event_count, &config);
-                                    event_count+=1;
-                                    let body = Message::Text(serde_json::to_string(&session_update).expect("Json Stringify").into());
-                                    if let Err(_e) = send.send(body).await {
-                                        yield Err(QwenV3Error::Connection);
-                                        break;
-                                    }
-                                    stage = Stage::SessionCreated;
-                                },
-                                types::ServerEvent::SessionUpdated(event) => {
-                                    info!("SessionUpdated: {:?}", event);
-                                },
-                                types::ServerEvent::SessionFinished(response) => {
-                                    info!("SessionFinished: {response:?}");
-                                    let _ = send.close().await;
-                                },
-                                types::ServerEvent::ConversationItemCreated(response) => {
-                                    trace!("ConversationItemCreated: {:?}", &response.item);
-                                },
-                                types::ServerEvent::ConversationItemInputAudioTranscriptionTranscriptionText(response) => {
-                                    trace!("ConversationItemInputAudioTranscriptionTranscriptionText: {:?}", &response);
-                                    if response.text.is_empty() {
-                                        continue;
-                                    }
-                                    let existing = client_state.as_mut().expect("ClientState must exist");
-                                    existing.text = response.text;
-                                    yield Ok(TranscribeResponse {
-                                        begin_time: existing.start_time,
-                                        sentence_end: false,
-                                        text: existing.text.clone(),
-                                    });
-                                },
-                                types::ServerEvent::ConversationItemInputAudioTranscriptionCompleted(response) => {
-                                    trace!("ConversationItemInputAudioTranscriptionCompleted: {:?}", &response);
-                                    let existing = client_state.take();
-                                    match existing {
-                                        Some(client_state) => {
-                                            yield Ok(TranscribeResponse {
-                                                begin_time: client_state.start_time,
-                                                text: response.transcript,
-                                                sentence_end: true,
-                                            })
-                                        },
-                                        None => {
-                                            panic!("existing ClientState");
-                                        }
-                                    }
-                                },
-                                types::ServerEvent::InputAudioBufferSpeechStarted(response) => {
-                                    trace!("InputAudioBufferSpeechStarted: {:?}", &response);
-                                    let existing = client_state.replace(ClientState { start_time: response.audio_start_ms, text: String::new() });
-                                    assert!(existing.is_none(), "existing ClientState");
-                                },
-                                types::ServerEvent::InputAudioBufferSpeechStopped(response) => {
-                                    trace!("InputAudioBufferSpeechStopped: {:?}", &response);
-                                },
-                                types::ServerEvent::InputAudioBufferCommitted(response) => {
-                                    trace!("InputAudioBufferCommitted: {:?}", &response);
-                                },
-                            }
-                        }
-                        Some(Ok(Message::Close(frame))) => {
-                            match frame {
-                                Some(close_frame) => {
-                                    info!("close by server: {:?}", &close_frame);
-                                    yield Err(QwenV3Error::Closed(close_frame.reason.as_str().to_string()));
-                                },
-                                None => {
-                                    info!("close by server: {:?}", frame);
-                                }
+    max_session_duration_ms: u32,
+    stage: Stage,
+    event_count: u32,
+    /// Without server VAD, turns are committed whenever the local VAD detects a pause.
+    vad: Option<VoiceActivityDetector>,
+    /// Start times of the turns committed by us, in the order the server acknowledges them.
+    pending_turns: VecDeque<u32>,
+    // Audio sent in all sessions, and where the current session started in it.
+    sent_samples: usize,
+    session_start_samples: usize,
+    session_offset: u32,
+    replay: ReplayBuffer<Replay>,
+    /// Set when the connection was lost and the buffered audio has to be sent again.
+    replaying: bool,
+    audio_ended: bool,
+    items: BTreeMap<String, ClientState>,
+}
+
+impl Transcription {
+    fn new(config: QwenV3Config) -> Self {
+        Self {
+            max_session_duration_ms: config.max_session_duration_secs().saturating_mul(1000),
+            vad: config
+                .turn_detection
+                .is_none()
+                .then(|| VoiceActivityDetector::new(config.vad.clone().unwrap_or_default())),
+            config,
+            stage: Stage::SessionCreating,
+            event_count: 0,
+            pending_turns: VecDeque::new(),
+            sent_samples: 0,
+            session_start_samples: 0,
+            session_offset: 0,
+            replay: ReplayBuffer::new(MAX_REPLAY_MS),
+            replaying: false,
+            audio_ended: false,
+            items: BTreeMap::new(),
+        }
+    }
+
+    fn append(&mut self, chunk: Bytes, out: &mut Output<QwenV3Error>) {
+        let req = types::input_audio_buffer::append::request::Request::new(
+            format!("event_{}", self.event_count),
+            chunk,
+        );
+        self.event_count += 1;
+        out.send(&req);
+    }
+
+    fn commit(&mut self, out: &mut Output<QwenV3Error>) {
+        out.send(&types::input_audio_buffer::commit::request::Request::new(
+            self.event_count,
+        ));
+        self.event_count += 1;
+    }
+
+    fn finish(&mut self, out: &mut Output<QwenV3Error>) {
+        out.send(&types::session::finish::request::Request::new(
+            self.event_count,
+        ));
+        self.event_count += 1;
+        self.stage = Stage::AwaitTaskFinished;
+    }
+}
+
+impl Protocol for Transcription {
+    type Event = types::ServerEvent;
+    type Error = QwenV3Error;
+
+    fn open(&mut self, _out: &mut Output<Self::Error>) {
+        // Nothing is sent before the server created the session.
+        self.stage = Stage::SessionCreating;
+        self.session_offset = samples_to_ms(self.session_start_samples);
+    }
+
+    fn wants_audio(&self) -> bool {
+        self.stage == Stage::SessionCreated
+    }
+
+    fn audio(&mut self, chunk: Option<io::Result<Bytes>>, out: &mut Output<Self::Error>) {
+        match chunk {
+            Some(Ok(chunk)) => {
+                self.sent_samples += chunk.len() / 2;
+                let turns = self
+                    .vad
+                    .as_mut()
+                    .map(|vad| vad.push(&chunk))
+                    .unwrap_or_default();
+                self.replay
+                    .push(self.sent_samples, Replay::Audio(chunk.clone()));
+                self.append(chunk, out);
+                for turn in turns {
+                    trace!("pause after turn at {}ms", turn.begin_time);
+                    self.pending_turns.push_back(turn.begin_time);
+                    self.replay
+                        .push(self.sent_samples, Replay::Commit(turn.begin_time));
+                    self.commit(out);
+                }
+
+                let session_ms = samples_to_ms(self.sent_samples - self.session_start_samples);
+                let max_ms = self.max_session_duration_ms;
+                let in_turn = !self.items.is_empty()
+                    || !self.pending_turns.is_empty()
+                    || self.vad.as_ref().is_some_and(|vad| vad.current().is_some());
+                if session_ms >= max_ms
+                    && (!in_turn || session_ms >= max_ms.saturating_add(ROLLOVER_GRACE_MS))
+                {
+                    info!("rolling over session after {session_ms}ms.");
+                    out.rolling_over();
+                    self.finish(out);
+                }
+            }
+            Some(Err(err)) => {
+                error!("error: {:?}", err);
+                out.emit(Err(QwenV3Error::Audio(err)));
+            }
+            None => {
+                self.audio_ended = true;
+                if let Some(turn) = self.vad.as_mut().and_then(VoiceActivityDetector::finish) {
+                    self.pending_turns.push_back(turn.begin_time);
+                    self.replay
+                        .push(self.sent_samples, Replay::Commit(turn.begin_time));
+                    self.commit(out);
+                }
+                self.finish(out);
+            }
+        }
+    }
+
+    fn event(&mut self, event: Self::Event, out: &mut Output<Self::Error>) {
+        match event {
+            types::ServerEvent::Error(err) => {
+                error!("err: {err:?}");
+                out.fail(QwenV3Error::Server {
+                    code: err.error.code,
+                    message: err.error.message,
+                });
+            }
+            types::ServerEvent::SessionCreated(response) => {
+                trace!("session created: {:?}", &response);
+                out.started();
+                out.send(&types::session::update::request::Request::new(
+                    self.event_count,
+                    &self.config,
+                ));
+                self.event_count += 1;
+                self.stage = Stage::SessionCreated;
+                if std::mem::take(&mut self.replaying) {
+                    info!(
+                        "replaying {}ms of audio.",
+                        samples_to_ms(self.sent_samples - self.session_start_samples)
+                    );
+                    let entries: Vec<Replay> = self.replay.iter().cloned().collect();
+                    for entry in entries {
+                        match entry {
+                            Replay::Audio(chunk) => self.append(chunk, out),
+                            Replay::Commit(begin_time) => {
+                                self.pending_turns.push_back(begin_time);
+                                self.commit(out);
                             }
-                        },
-                        None => {
-                            info!("server disconnected.");
-                            return;
-                        }
-                        Some(Err(error)) => {
-                            error!("connection error: {:?}", error);
-                            yield Err(QwenV3Error::Connection);
-                            break;
-                        },
-                        Some(Ok(Message::Ping(data))) => {
-                            let _ = send.send(Message::Pong(data)).await;
-                        },
-                        Some(Ok(Message::Pong(_))) => {
-                            // ignore
-                        }
-                        Some(Ok(Message::Binary(_))) => {
-                            unreachable!("Unexpected binary");
-                        }
-                        Some(Ok(Message::Frame(_))) => {
-                            unreachable!("Unexpected frame");
                         }
                     }
-                },
-                chunk = audio_stream.next(), if matches!(stage, Stage::SessionCreated) => {
-                    if let Some(chunk) = chunk {
-                        match chunk {
-                            Ok(chunk) => {
-                                let req = types::input_audio_buffer::append::request::Request::new(format!("event_{event_count}"), chunk);
-                                event_count += 1;
-                                let body = Message::Text(serde_json::to_string(&req).expect("Json Stringify").into());
-                                if let Err(_e) = send.send(body).await {
-                                    yield Err(QwenV3Error::Connection);
-                                    break;
-                                }
-                            },
-                            Err(err) => {
-                                error!("error: {:?}", err);
-                                yield Err(QwenV3Error::Audio(err));
-                            }
-                        }
-                    } else {
-                        let finish_req = types::session::finish::request::Request::new(event_count);
-                        event_count += 1;
-                        let txt = match serde_json::to_string(&finish_req) {
-                            Ok(t) => t,
-                            Err(e) => {
-                                yield Err(e.into());
-                                break;
-                            }
-                        };
-                        if let Err(_e) = send.send(Message::Text(txt.into())).await {
-                            yield Err(QwenV3Error::Connection);
-                            break;
-                        }
-                        stage = Stage::AwaitTaskFinished;
+                    if self.audio_ended {
+                        self.finish(out);
                     }
                 }
             }
+            types::ServerEvent::SessionUpdated(event) => {
+                info!("SessionUpdated: {:?}", event);
+            }
+            types::ServerEvent::SessionFinished(response) => {
+                info!("SessionFinished: {response:?}");
+                if self.stage != Stage::AwaitTaskFinished {
+                    // Finished before we asked for it; carry on in a new session.
+                    warn!("session finished early, starting a new one.");
+                    out.rolling_over();
+                    self.stage = Stage::AwaitTaskFinished;
+                }
+                out.finished();
+            }
+            types::ServerEvent::ConversationItemCreated(response) => {
+                trace!("ConversationItemCreated: {:?}", &response.item);
+            }
+            types::ServerEvent::ConversationItemInputAudioTranscriptionTranscriptionText(
+                response,
+            ) => {
+                trace!(
+                    "ConversationItemInputAudioTranscriptionTranscriptionText: {:?}",
+                    &response
+                );
+                if response.text.is_empty() {
+                    return;
+                }
+                let sent_ms = samples_to_ms(self.sent_samples);
+                let existing = self
+                    .items
+                    .entry(response.item_id)
+                    .or_insert_with(|| ClientState {
+                        start_time: sent_ms,
+                        text: String::new(),
+                    });
+                existing.text = response.text;
+                out.emit(Ok(TranscribeResponse {
+                    begin_time: existing.start_time,
+                    sentence_end: false,
+                    translation: None,
+                    text: existing.text.clone(),
+                }));
+            }
+            types::ServerEvent::ConversationItemInputAudioTranscriptionCompleted(response) => {
+                trace!(
+                    "ConversationItemInputAudioTranscriptionCompleted: {:?}",
+                    &response
+                );
+                let sent_ms = samples_to_ms(self.sent_samples);
+                let start_time = self
+                    .items
+                    .remove(&response.item_id)
+                    .map_or(sent_ms, |state| state.start_time);
+                // Keep what may belong to turns still open, or to one not detected yet.
+                let keep_from = self
+                    .items
+                    .values()
+                    .map(|state| state.start_time)
+                    .chain(self.pending_turns.iter().copied())
+                    .chain(
+                        self.vad
+                            .as_ref()
+                            .and_then(VoiceActivityDetector::current)
+                            .map(|turn| turn.begin_time),
+                    )
+                    .fold(sent_ms.saturating_sub(REPLAY_MARGIN_MS), u32::min);
+                self.replay.discard_before(ms_to_samples(keep_from));
+                out.emit(Ok(TranscribeResponse {
+                    begin_time: start_time,
+                    text: response.transcript,
+                    sentence_end: true,
+                    translation: None,
+                }));
+            }
+            types::ServerEvent::InputAudioBufferSpeechStarted(response) => {
+                trace!("InputAudioBufferSpeechStarted: {:?}", &response);
+                self.items.insert(
+                    response.item_id,
+                    ClientState {
+                        start_time: self.session_offset.saturating_add(response.audio_start_ms),
+                        text: String::new(),
+                    },
+                );
+            }
+            types::ServerEvent::InputAudioBufferSpeechStopped(response) => {
+                trace!("InputAudioBufferSpeechStopped: {:?}", &response);
+            }
+            types::ServerEvent::InputAudioBufferCommitted(response) => {
+                trace!("InputAudioBufferCommitted: {:?}", &response);
+                let sent_ms = samples_to_ms(self.sent_samples);
+                let pending_turns = &mut self.pending_turns;
+                self.items
+                    .entry(response.item_id)
+                    .or_insert_with(|| ClientState {
+                        start_time: pending_turns.pop_front().unwrap_or(sent_ms),
+                        text: String::new(),
+                    });
+            }
+        }
+    }
+
+    fn rolled_over(&mut self) {
+        info!("session rolled over, starting a new one.");
+        self.replay.clear(self.sent_samples);
+        self.session_start_samples = self.sent_samples;
+    }
+
+    fn lost(&mut self, out: &mut Output<Self::Error>) {
+        // Turns in progress are recognized again from the replayed audio, and may change.
+        for (_, state) in std::mem::take(&mut self.items) {
+            if !state.text.is_empty() {
+                out.emit(Ok(TranscribeResponse {
+                    begin_time: state.start_time,
+                    text: String::new(),
+                    sentence_end: false,
+                    translation: None,
+                }));
+            }
         }
+        self.pending_turns.clear();
+        self.session_start_samples = self.replay.start();
+        self.replaying = true;
+    }
+}
+
+fn transcribe<C, F, W>(
+    connect: C,
+    web_socket_stream: W,
+    audio_stream: AudioStream,
+    config: QwenV3Config,
+) -> impl Stream<Item = Result<TranscribeResponse, QwenV3Error>>
+where
+    C: FnMut() -> F,
+    F: Future<Output = Result<W, QwenV3Error>>,
+    W: Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Sink<Message>,
+{
+    websocket_session::drive(
+        Transcription::new(config),
+        connect,
+        web_socket_stream,
+        audio_stream,
+    )
+}
+
+impl QwenV3Client {
+    #[must_use]
+    pub const fn config(&self) -> &QwenV3Config {
+        &self.config
+    }
+
+    /// Starts a session with settings that differ from the client's own, e.g. in `context`.
+    /// Connection settings are taken from `config` only when no connection was prepared.
+    pub async fn create_with_config(
+        &self,
+        audio_stream: AudioStream,
+        config: QwenV3Config,
+    ) -> Result<TranscribeStream<anyhow::Error>, anyhow::Error> {
+        let api_key = config.api_key.resolve().await.map_err(QwenV3Error::from)?;
+        let ws_stream = match self.prepared.take() {
+            Some(ws_stream) => ws_stream,
+            None => connect(&config, &api_key).await?,
+        };
+
+        let log = traffic_log(&config, &api_key);
+        let ws_stream = Recorded::new(ws_stream, config.websocket_url(), log.clone());
+
+        let connect_config = config.clone();
+        let transcribe_stream = transcribe(
+            move || {
+                let config = connect_config.clone();
+                let api_key = api_key.clone();
+                let log = log.clone();
+                async move {
+                    let ws_stream = connect(&config, &api_key).await?;
+                    Ok(Recorded::new(ws_stream, config.websocket_url(), log))
+                }
+            },
+            ws_stream,
+            audio_stream,
+            config,
+        )
+        .map(|item| item.map_err(anyhow::Error::from));
+
+        Ok(TranscribeStream::new(Box::pin(transcribe_stream)))
     }
 }
 
@@ -215,6 +445,7 @@ impl AsrClient for QwenV3Client {
     fn new(config: impl Into<Self::Config>) -> Self {
         Self {
             config: config.into(),
+            prepared: PreparedConnection::new(),
         }
     }
 
@@ -222,21 +453,21 @@ impl AsrClient for QwenV3Client {
         &self,
         audio_stream: AudioStream,
     ) -> Result<Self::TranscriptionStream, anyhow::Error> {
-        let config = self.config.clone();
-        let mut request = config.websocket_url().into_client_request()?;
-        let headers = request.headers_mut();
-
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", config.dashscope_api_key))
-                .map_err(|_| anyhow::anyhow!("invalid header value for `Authorization`"))?,
-        );
-
-        let (ws_stream, _resp) = connect_async(request).await?;
-        let transcribe_stream = transcribe(ws_stream, audio_stream, config)
-            .map(|item| item.map_err(anyhow::Error::from));
+        self.create_with_config(audio_stream, self.config.clone())
+            .await
+    }
 
-        Ok(TranscribeStream::new(Box::pin(transcribe_stream)))
+    async fn prepare(&self) -> Result<(), anyhow::Error> {
+        if !self.prepared.is_fresh() {
+            let api_key = self
+                .config
+                .api_key
+                .resolve()
+                .await
+                .map_err(QwenV3Error::from)?;
+            self.prepared.put(connect(&self.config, &api_key).await?);
+        }
+        Ok(())
     }
 }
 
@@ -245,13 +476,18 @@ mod tests {
     use std::time::Duration;
 
     use crate::types::Language;
+    use base_client::traffic_log::{Entry, Frame, Replay};
+    use dashscope_mock::{Connection, MockServer, Step, qwen};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
     use tokio::time::sleep;
     use tokio_stream::StreamExt;
     use tokio_util::sync::CancellationToken;
 
     use super::*;
+    use base_client::api_key::{ApiKey, DashscopeApiKey};
     use base_client::asr_client::AsrClient;
-    use base_client::audio_stream::AudioCapture;
+    use base_client::audio_stream::{AudioCapture, AudioSource};
     use pcm_playback_recorder::{PcmPlaybackCaptureOption, PcmPlaybackRecorder};
 
     #[cfg_attr(not(has_dashscope), ignore = "requires DASHSCOPE_API_KEY env var")]
@@ -261,24 +497,451 @@ mod tests {
         let wav_path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("../../assets/harvard.16k.mono.wav");
         let recorder = PcmPlaybackRecorder::new(PcmPlaybackCaptureOption::new(wav_path)).unwrap();
-        let audio_stream = recorder.create(cancellation.clone()).unwrap();
+        let audio_stream = recorder
+            .create(AudioSource::default(), cancellation.clone())
+            .unwrap();
         tokio::spawn(async move {
             sleep(Duration::from_secs(5)).await;
             cancellation.cancel();
         });
 
         let config = QwenV3Config {
-            dashscope_api_key: std::env::var("DASHSCOPE_API_KEY").unwrap(),
+            api_key: DashscopeApiKey {
+                dashscope_api_key_env: Some("DASHSCOPE_API_KEY".to_string()),
+                ..DashscopeApiKey::default()
+            },
             dashscope_websocket_url: None,
             language: Some(Language::English),
             turn_detection: None,
+            context: None,
+            vad: None,
+            max_session_duration_secs: None,
+            traffic_log_dir: None,
+            proxy: None,
         };
         let backend = QwenV3Client::new(config);
 
         let mut client = backend.create(audio_stream).await.unwrap();
 
-        while let (Some(event)) = client.next().await {
-            dbg!(event);
+        while let Some(event) = client.next().await {
+            tracing::debug!(?event, "transcription event");
+        }
+    }
+
+    fn pcm(ms: u32, amplitude: i16) -> Vec<u8> {
+        (0..base_client::vad::ms_to_samples(ms))
+            .flat_map(|i| {
+                let sample = if i % 2 == 0 { amplitude } else { -amplitude };
+                sample.to_le_bytes()
+            })
+            .collect()
+    }
+
+    /// Transcribes a turn the client committed.
+    fn turn(item_id: &str, transcript: &str) -> [Step; 3] {
+        [
+            Step::Send(qwen::committed(item_id)),
+            Step::Send(qwen::text(item_id, "turn")),
+            Step::Send(qwen::completed(item_id, transcript)),
+        ]
+    }
+
+    /// A session with server VAD that recognizes one turn at 100ms once it is finished.
+    fn session(transcript: &str) -> Vec<Step> {
+        vec![
+            Step::Send(qwen::session_created()),
+            Step::Expect("session.finish"),
+            Step::Send(qwen::speech_started("item_1", 100)),
+            Step::Send(qwen::completed("item_1", transcript)),
+            Step::Send(qwen::session_finished()),
+        ]
+    }
+
+    fn server_vad_client(server: &MockServer) -> QwenV3Client {
+        QwenV3Client::new(QwenV3Config {
+            api_key: ApiKey::new("sk-test").into(),
+            dashscope_websocket_url: Some(server.url()),
+            turn_detection: Some(crate::config::TurnDetection::default()),
+            ..QwenV3Config::default()
+        })
+    }
+
+    fn silence(chunks: usize) -> AudioStream {
+        let chunks: Vec<_> = (0..chunks)
+            .map(|_| Ok(tokio_util::bytes::Bytes::from(vec![0u8; 3200])))
+            .collect();
+        AudioStream(Box::pin(futures_util::stream::iter(chunks)))
+    }
+
+    async fn events(client: TranscribeStream<anyhow::Error>) -> Vec<(u32, String, bool)> {
+        client
+            .map(|event| {
+                let event = event.unwrap();
+                (event.begin_time, event.text, event.sentence_end)
+            })
+            .collect()
+            .await
+    }
+
+    fn audio_received(connections: &[Connection]) -> Vec<usize> {
+        connections.iter().map(|c| c.audio.len()).collect()
+    }
+
+    #[tokio::test]
+    async fn manual_turns() {
+        let script = [
+            vec![
+                Step::Send(qwen::session_created()),
+                Step::Expect("session.update"),
+                Step::Expect("input_audio_buffer.commit"),
+            ],
+            turn("item_1", "Turn 1.").to_vec(),
+            vec![Step::Expect("input_audio_buffer.commit")],
+            turn("item_2", "Turn 2.").to_vec(),
+            vec![
+                Step::Expect("session.finish"),
+                Step::Send(qwen::session_finished()),
+            ],
+        ]
+        .concat();
+        let server = MockServer::start(vec![script]).await;
+
+        let backend = QwenV3Client::new(QwenV3Config {
+            api_key: ApiKey::new("sk-test").into(),
+            dashscope_websocket_url: Some(server.url()),
+            ..QwenV3Config::default()
+        });
+        let audio = [pcm(990, 0), pcm(990, 8000), pcm(1200, 0), pcm(600, 8000)].concat();
+        let chunks: Vec<_> = audio
+            .chunks(3200)
+            .map(|c| Ok(tokio_util::bytes::Bytes::copy_from_slice(c)))
+            .collect();
+        let audio_stream = AudioStream(Box::pin(futures_util::stream::iter(chunks)));
+        let client = backend.create(audio_stream).await.unwrap();
+
+        assert_eq!(
+            events(client).await,
+            [
+                (690, "turn".to_string(), false),
+                (690, "Turn 1.".to_string(), true),
+                (2880, "turn".to_string(), false),
+                (2880, "Turn 2.".to_string(), true),
+            ]
+        );
+
+        let connections = server.connections().await;
+        assert!(connections[0].events[0]["session"]["turn_detection"].is_null());
+        assert_eq!(
+            connections[0].event_names(),
+            [
+                "session.update",
+                "input_audio_buffer.append",
+                "input_audio_buffer.commit",
+                "input_audio_buffer.append",
+                "input_audio_buffer.commit",
+                "session.finish",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rollover_on_max_duration() {
+        let server = MockServer::start(vec![
+            session("Session 1."),
+            session("Session 2."),
+            session("Session 3."),
+        ])
+        .await;
+
+        let backend = QwenV3Client::new(QwenV3Config {
+            api_key: ApiKey::new("sk-test").into(),
+            dashscope_websocket_url: Some(server.url()),
+            turn_detection: Some(crate::config::TurnDetection::default()),
+            max_session_duration_secs: Some(1),
+            ..QwenV3Config::default()
+        });
+        let client = backend.create(silence(25)).await.unwrap();
+
+        assert_eq!(
+            events(client).await,
+            [
+                (100, "Session 1.".to_string(), true),
+                (1100, "Session 2.".to_string(), true),
+                (2100, "Session 3.".to_string(), true),
+            ]
+        );
+        assert_eq!(
+            audio_received(&server.connections().await),
+            [32000, 32000, 16000]
+        );
+    }
+
+    /// A session that starts a turn after 500ms of audio, then drops the connection.
+    fn reset_session() -> Vec<Step> {
+        vec![
+            Step::Send(qwen::session_created()),
+            Step::ExpectAudio(5 * 3200),
+            Step::Send(qwen::speech_started("item_0", 100)),
+            Step::Send(qwen::text("item_0", "hel")),
+            Step::Reset,
+        ]
+    }
+
+    fn audio_channel() -> (
+        tokio::sync::mpsc::UnboundedSender<Result<tokio_util::bytes::Bytes, std::io::Error>>,
+        AudioStream,
+    ) {
+        let (audio, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let audio_stream = AudioStream(Box::pin(futures_util::stream::poll_fn(move |cx| {
+            rx.poll_recv(cx)
+        })));
+        (audio, audio_stream)
+    }
+
+    /// Streams 500ms of audio, takes the first two events, then streams another 500ms.
+    async fn interrupted_session<E: std::fmt::Debug>(
+        audio: tokio::sync::mpsc::UnboundedSender<Result<tokio_util::bytes::Bytes, std::io::Error>>,
+        mut client: impl Stream<Item = Result<TranscribeResponse, E>> + Unpin,
+    ) -> Vec<(u32, String, bool)> {
+        let chunk = || Ok(tokio_util::bytes::Bytes::from(vec![0u8; 3200]));
+        let mut events = Vec::new();
+        for _ in 0..5 {
+            audio.send(chunk()).unwrap();
+        }
+        for _ in 0..2 {
+            let event = client.next().await.unwrap().unwrap();
+            events.push((event.begin_time, event.text, event.sentence_end));
+        }
+        for _ in 0..5 {
+            audio.send(chunk()).unwrap();
+        }
+        drop(audio);
+        while let Some(event) = client.next().await {
+            let event = event.unwrap();
+            events.push((event.begin_time, event.text, event.sentence_end));
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn reconnect_replays_open_turn() {
+        let server = MockServer::start(vec![reset_session(), session("Session 2.")]).await;
+
+        let (audio, audio_stream) = audio_channel();
+        let client = server_vad_client(&server)
+            .create(audio_stream)
+            .await
+            .unwrap();
+
+        // The open turn is withdrawn and recognized again from the replayed audio.
+        assert_eq!(
+            interrupted_session(audio, client).await,
+            [
+                (100, "hel".to_string(), false),
+                (100, String::new(), false),
+                (100, "Session 2.".to_string(), true),
+            ]
+        );
+        assert_eq!(audio_received(&server.connections().await), [16000, 32000]);
+    }
+
+    #[tokio::test]
+    async fn recorded_session_replays() {
+        let dir = std::env::temp_dir().join(format!("qwen-traffic-{}", std::process::id()));
+        let server = MockServer::start(vec![reset_session(), session("Session 2.")]).await;
+        let config = QwenV3Config {
+            api_key: ApiKey::new("sk-test").into(),
+            dashscope_websocket_url: Some(server.url()),
+            turn_detection: Some(crate::config::TurnDetection::default()),
+            traffic_log_dir: Some(dir.to_string_lossy().into_owned()),
+            ..QwenV3Config::default()
+        };
+        let (audio, audio_stream) = audio_channel();
+        let client = QwenV3Client::new(config.clone())
+            .create(audio_stream)
+            .await
+            .unwrap();
+        let recorded = interrupted_session(audio, client).await;
+        server.connections().await;
+
+        let log = std::fs::read_dir(&dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let mut replay = Replay::load(&log).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let first = replay.next_connection().unwrap();
+        let (audio, audio_stream) = audio_channel();
+        let client = transcribe(
+            move || std::future::ready(replay.next_connection().ok_or(QwenV3Error::Connection)),
+            first,
+            audio_stream,
+            config,
+        );
+
+        assert_eq!(interrupted_session(audio, Box::pin(client)).await, recorded);
+    }
+
+    #[tokio::test]
+    async fn waits_for_late_transcript() {
+        let mut script = session("Late.");
+        script.insert(2, Step::Delay(Duration::from_millis(300)));
+        let server = MockServer::start(vec![script]).await;
+
+        let client = server_vad_client(&server).create(silence(5)).await.unwrap();
+
+        assert_eq!(events(client).await, [(100, "Late.".to_string(), true)]);
+        assert_eq!(audio_received(&server.connections().await), [16000]);
+    }
+
+    #[tokio::test]
+    async fn close_before_session_created_is_reported() {
+        let server =
+            MockServer::start(vec![vec![Step::Close(Some((1008, "Access denied.")))]]).await;
+
+        let client = server_vad_client(&server).create(silence(5)).await.unwrap();
+
+        let events: Vec<_> = client.collect().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].as_ref().unwrap_err().to_string(),
+            "connection closed: Access denied."
+        );
+        server.connections().await;
+    }
+
+    #[tokio::test]
+    async fn error_event_ends_session() {
+        let script = vec![
+            Step::Send(qwen::session_created()),
+            Step::Expect("session.update"),
+            Step::Send(qwen::error("InvalidApiKey", "Invalid API-key provided.")),
+        ];
+        let server = MockServer::start(vec![script]).await;
+
+        let client = server_vad_client(&server).create(silence(5)).await.unwrap();
+
+        let events: Vec<_> = client.collect().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].as_ref().unwrap_err().to_string(),
+            "server error (InvalidApiKey): Invalid API-key provided."
+        );
+        server.connections().await;
+    }
+
+    /// Replays `connections` to a session over `chunks` of audio. Returns its results, or `None`
+    /// if it did not end.
+    fn replayed_session(
+        connections: Vec<Vec<Entry>>,
+        chunks: Vec<Vec<u8>>,
+        config: QwenV3Config,
+    ) -> Option<Vec<Result<TranscribeResponse, QwenV3Error>>> {
+        let jsonl: String = connections
+            .into_iter()
+            .flat_map(|entries| {
+                let url = QwenV3Config::DEFAULT_WEBSOCKET_URL.to_string();
+                std::iter::once(Entry::Connect { url }).chain(entries)
+            })
+            .map(|entry| serde_json::to_string(&entry).unwrap() + "\n")
+            .collect();
+        let mut replay = Replay::parse(&jsonl).unwrap();
+        let first = replay.next_connection().unwrap();
+        let audio = chunks.into_iter().map(|chunk| Ok(Bytes::from(chunk)));
+        let session = transcribe(
+            move || std::future::ready(replay.next_connection().ok_or(QwenV3Error::Connection)),
+            first,
+            AudioStream(Box::pin(tokio_stream::iter(audio))),
+            config,
+        );
+
+        // Reconnects back off in paused time, which skips ahead whenever the session waits.
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            tokio::time::timeout(Duration::from_hours(24), session.collect())
+                .await
+                .ok()
+        })
+    }
+
+    fn received(event: &serde_json::Value) -> Entry {
+        Entry::Received(Frame::Text(event.to_string()))
+    }
+
+    #[test]
+    fn late_times_saturate() {
+        // Rolls over after a second of audio, so that the second session starts 1000ms in.
+        let sent = || Entry::Sent(Frame::Text(String::new()));
+        let first_session = std::iter::once(received(&qwen::session_created()))
+            .chain(std::iter::repeat_with(sent).take(12))
+            .chain([received(&qwen::session_finished())])
+            .collect();
+        let results = replayed_session(
+            vec![
+                first_session,
+                vec![
+                    received(&qwen::session_created()),
+                    received(&qwen::speech_started("item", u32::MAX)),
+                    received(&qwen::completed("item", "late")),
+                    received(&qwen::session_finished()),
+                ],
+            ],
+            vec![vec![0; 3200]; 10],
+            QwenV3Config {
+                max_session_duration_secs: Some(1),
+                ..QwenV3Config::default()
+            },
+        );
+        let results = results.unwrap();
+        assert_eq!(results[0].as_ref().unwrap().begin_time, u32::MAX);
+    }
+
+    /// A server frame of an arbitrary session: mostly events, in any order and with any values.
+    fn arbitrary_entry() -> impl Strategy<Value = Entry> {
+        let item = prop_oneof![Just("item_0"), Just("item_1"), Just("item_2")];
+        let event = prop_oneof![
+            Just(qwen::session_created()),
+            (
+                item.clone(),
+                prop_oneof![0..60_000u32, u32::MAX - 60_000..=u32::MAX]
+            )
+                .prop_map(|(item, ms)| qwen::speech_started(item, ms)),
+            item.clone().prop_map(qwen::committed),
+            (item.clone(), "[a-z ]{0,8}").prop_map(|(item, text)| qwen::text(item, &text)),
+            (item, "[a-z ]{0,8}").prop_map(|(item, text)| qwen::completed(item, &text)),
+            Just(qwen::session_finished()),
+            Just(qwen::error("InvalidParameter", "bad request")),
+        ]
+        .prop_map(|event| received(&event));
+        prop_oneof![
+            8 => event,
+            1 => any::<String>().prop_map(|text| Entry::Received(Frame::Text(text))),
+            1 => Just(Entry::Received(Frame::Binary("AAA=".to_string()))),
+            1 => Just(Entry::Received(Frame::Close(None))),
+            1 => Just(Entry::Error("connection reset".to_string())),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_server_frames_end_the_session(
+            connections in vec(vec(arbitrary_entry(), 0..12), 1..4),
+            chunks in vec(vec(any::<u8>(), 0..4000), 0..8),
+            server_vad in any::<bool>(),
+            max_session_duration_secs in proptest::option::of(1..3u32),
+        ) {
+            let config = QwenV3Config {
+                turn_detection: server_vad.then(crate::config::TurnDetection::default),
+                max_session_duration_secs,
+                ..QwenV3Config::default()
+            };
+            prop_assert!(replayed_session(connections, chunks, config).is_some());
         }
     }
 }
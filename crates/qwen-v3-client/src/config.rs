@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 
+use base_client::api_key::DashscopeApiKey;
+use base_client::vad::VadConfig;
+
 use crate::types::Language;
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -21,20 +24,45 @@ impl Default for TurnDetection {
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct QwenV3Config {
-    pub dashscope_api_key: String,
+    #[serde(flatten)]
+    pub api_key: DashscopeApiKey,
     pub dashscope_websocket_url: Option<String>,
     pub language: Option<Language>,
+    /// Server VAD. Without it, server VAD is turned off (`turn_detection` is sent as `null`) and
+    /// turns are committed on pauses detected by `vad`.
     pub turn_detection: Option<TurnDetection>,
+    /// Domain terms, names or related text that bias recognition.
+    pub context: Option<String>,
+    pub vad: Option<VadConfig>,
+    /// A session is rolled over to a new one after this long, between turns. At least 10 seconds.
+    pub max_session_duration_secs: Option<u32>,
+    /// Each session's websocket traffic is written to a JSONL file in this directory, for
+    /// debugging. The API key is redacted.
+    pub traffic_log_dir: Option<String>,
+    /// `http://` or `socks5://` proxy for the websocket, with optional `user:password@`. Without
+    /// it, `HTTPS_PROXY` or `ALL_PROXY` is used, unless `NO_PROXY` lists the host.
+    pub proxy: Option<String>,
 }
 
 impl QwenV3Config {
     pub const DEFAULT_WEBSOCKET_URL: &str =
         "wss://dashscope.aliyuncs.com/api-ws/v1/realtime?model=qwen3-asr-flash-realtime";
 
+    pub const DEFAULT_MAX_SESSION_DURATION_SECS: u32 = 30 * 60;
+
+    /// Shorter sessions would open a connection every few seconds.
+    pub const MIN_MAX_SESSION_DURATION_SECS: u32 = 10;
+
     #[must_use]
     pub fn websocket_url(&self) -> &str {
         self.dashscope_websocket_url
             .as_deref()
             .unwrap_or(Self::DEFAULT_WEBSOCKET_URL)
     }
+
+    #[must_use]
+    pub fn max_session_duration_secs(&self) -> u32 {
+        self.max_session_duration_secs
+            .unwrap_or(Self::DEFAULT_MAX_SESSION_DURATION_SECS)
+    }
 }
@@ -2,6 +2,9 @@ use std::io;
 
 use tokio_tungstenite::tungstenite::Error as WsError;
 
+use base_client::api_key::ApiKeyError;
+use base_client::websocket_session::SessionError;
+
 #[derive(Debug, thiserror::Error)]
 #[allow(dead_code)]
 pub enum QwenV3Error {
@@ -21,9 +24,37 @@ pub enum QwenV3Error {
     #[error("invalid header value for `{0}`")]
     InvalidHeaderValue(&'static str),
 
+    /// The API key could not be read from its configured source.
+    #[error("api key error: {0}")]
+    ApiKey(#[from] ApiKeyError),
+
+    /// An error event from the service, e.g. for an invalid API key or unsupported audio.
+    #[error("server error ({code}): {message}")]
+    Server { code: String, message: String },
+
     #[error("connection error")]
     Connection,
 
     #[error("connection closed: {0}")]
     Closed(String),
+
+    #[error("invalid proxy `{0}`")]
+    InvalidProxy(String),
+
+    /// The proxy could not be reached or refused the tunnel.
+    #[error("proxy error: {0}")]
+    Proxy(String),
+}
+
+impl From<SessionError> for QwenV3Error {
+    fn from(error: SessionError) -> Self {
+        match error {
+            SessionError::WebSocket(error) => Self::WebSocket(error),
+            SessionError::InvalidHeaderValue(header) => Self::InvalidHeaderValue(header),
+            SessionError::Connection => Self::Connection,
+            SessionError::Closed(reason) => Self::Closed(reason),
+            SessionError::InvalidProxy(proxy) => Self::InvalidProxy(proxy),
+            SessionError::Proxy(error) => Self::Proxy(error),
+        }
+    }
 }
@@ -91,10 +91,10 @@ pub mod error {
     }
 
     #[derive(Debug, serde::Deserialize)]
-    struct Error {
+    pub struct Error {
         r#type: Type,
-        code: String,
-        message: String,
+        pub code: String,
+        pub message: String,
         param: String,
         event_id: String,
     }
@@ -103,7 +103,7 @@ pub mod error {
     pub struct Response {
         event_id: String,
         r#type: Type,
-        error: Error,
+        pub error: Error,
     }
 }
 
@@ -180,9 +180,17 @@ pub mod session {
             use crate::config::QwenV3Config;
             use crate::types::Language;
 
+            #[derive(Debug, serde::Serialize)]
+            struct Corpus {
+                text: String,
+            }
+
             #[derive(Debug, serde::Serialize)]
             struct InputAudioTranscription {
-                language: Language,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                language: Option<Language>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                corpus: Option<Corpus>,
             }
 
             #[derive(Debug, serde::Serialize)]
@@ -191,7 +199,7 @@ pub mod session {
                 sample_rate: u32,
                 #[serde(skip_serializing_if = "Option::is_none")]
                 input_audio_transcription: Option<InputAudioTranscription>,
-                #[serde(skip_serializing_if = "Option::is_none")]
+                /// `null` disables server VAD; turns are then committed by the client.
                 turn_detection: Option<super::super::TurnDetection>,
             }
 
@@ -210,9 +218,19 @@ pub mod session {
                         sample_rate: 16000,
                         turn_detection: None,
                     };
-                    if let Some(language) = config.language {
-                        session.input_audio_transcription =
-                            InputAudioTranscription { language }.into();
+                    let corpus = config
+                        .context
+                        .as_ref()
+                        .filter(|context| !context.is_empty())
+                        .map(|context| Corpus {
+                            text: context.clone(),
+                        });
+                    if config.language.is_some() || corpus.is_some() {
+                        session.input_audio_transcription = InputAudioTranscription {
+                            language: config.language,
+                            corpus,
+                        }
+                        .into();
                     }
 
                     if let Some(turn_detection) = &config.turn_detection {
@@ -358,6 +376,31 @@ pub mod input_audio_buffer {
         }
     }
 
+    pub mod commit {
+        pub mod request {
+            #[derive(Debug, serde::Serialize)]
+            enum Type {
+                #[serde(rename = "input_audio_buffer.commit")]
+                InputAudioBufferCommit,
+            }
+
+            #[derive(Debug, serde::Serialize)]
+            pub struct Request {
+                event_id: String,
+                r#type: Type,
+            }
+
+            impl Request {
+                pub fn new(event_id: u32) -> Self {
+                    Self {
+                        event_id: format!("input_audio_buffer_commit_{event_id}"),
+                        r#type: Type::InputAudioBufferCommit,
+                    }
+                }
+            }
+        }
+    }
+
     pub mod speech_started {
         pub mod response {
             #[derive(Debug, serde::Deserialize)]
@@ -370,7 +413,7 @@ pub mod input_audio_buffer {
                 event_id: String,
                 r#type: InputAudioBufferSpeechStarted,
                 pub audio_start_ms: u32,
-                item_id: String,
+                pub item_id: String,
             }
         }
     }
@@ -387,7 +430,7 @@ pub mod input_audio_buffer {
                 event_id: String,
                 r#type: InputAudioBufferSpeechStopped,
                 audio_end_ms: u32,
-                item_id: String,
+                pub item_id: String,
             }
         }
     }
@@ -404,7 +447,7 @@ pub mod input_audio_buffer {
             pub struct Response {
                 event_id: String,
                 r#type: InputAudioBufferCommitted,
-                item_id: String,
+                pub item_id: String,
             }
         }
     }
@@ -448,7 +491,7 @@ pub mod conversation {
                 pub struct Response {
                     event_id: String,
                     r#type: Type,
-                    item_id: String,
+                    pub item_id: String,
                     content_index: usize,
                     pub transcript: String,
                     language: Language,
@@ -469,7 +512,7 @@ pub mod conversation {
                 pub struct Response {
                     event_id: String,
                     r#type: Type,
-                    item_id: String,
+                    pub item_id: String,
                     content_index: u32,
                     pub text: String,
                     pub language: Language,
@@ -558,3 +601,63 @@ pub enum ServerEvent {
     InputAudioBufferCommitted(input_audio_buffer::committed::response::Response),
     Error(error::Response),
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::QwenV3Config;
+
+    use super::*;
+
+    #[test]
+    fn session_update_without_turn_detection() {
+        // An explicit `null`: leaving `turn_detection` out would keep the server's VAD on.
+        let request = session::update::request::Request::new(0, &QwenV3Config::default());
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "event_id": "session_update_0",
+                "type": "session.update",
+                "session": {
+                    "input_audio_format": "pcm",
+                    "sample_rate": 16000,
+                    "turn_detection": null,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn session_update_with_turn_detection() {
+        let config = QwenV3Config {
+            turn_detection: Some(crate::config::TurnDetection {
+                threshold: 0.25,
+                silence_duration_ms: 900,
+            }),
+            ..QwenV3Config::default()
+        };
+        let request = session::update::request::Request::new(0, &config);
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["session"]["turn_detection"],
+            serde_json::json!({
+                "type": "server_vad",
+                "silence_duration_ms": 900,
+                "threshold": 0.25,
+            })
+        );
+    }
+
+    #[test]
+    fn session_update_with_context() {
+        let config = QwenV3Config {
+            language: Some(Language::English),
+            context: Some("Dictype, fcitx".to_string()),
+            ..QwenV3Config::default()
+        };
+        let request = session::update::request::Request::new(0, &config);
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            value["session"]["input_audio_transcription"],
+            serde_json::json!({"language": "en", "corpus": {"text": "Dictype, fcitx"}})
+        );
+    }
+}
@@ -0,0 +1,8 @@
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(has_sherpa_onnx_model)");
+    println!("cargo:rerun-if-env-changed=SHERPA_ONNX_MODEL_DIR");
+
+    if std::env::var("SHERPA_ONNX_MODEL_DIR").is_ok() {
+        println!("cargo:rustc-cfg=has_sherpa_onnx_model");
+    }
+}
@@ -0,0 +1,239 @@
+use std::sync::Arc;
+
+use async_stream::stream;
+use futures_util::{Stream, StreamExt};
+use tokio::sync::OnceCell;
+use tracing::{info, trace};
+
+use base_client::asr_client::AsrClient;
+use base_client::audio_stream::AudioStream;
+use base_client::grpc_server::TranscribeResponse;
+use base_client::transcribe_stream::TranscribeStream;
+use base_client::vad::samples_to_ms;
+
+use crate::config::SherpaOnnxConfig;
+use crate::error::SherpaOnnxError;
+use crate::ffi::{OnlineStream, Recognizer};
+
+/// Partial text is revised as decoding proceeds; the endpointing rules of sherpa-onnx decide when
+/// an utterance ends.
+pub struct SherpaOnnxClient {
+    config: SherpaOnnxConfig,
+    // The model is loaded on first use and kept for later sessions.
+    recognizer: Arc<OnceCell<Arc<Recognizer>>>,
+}
+
+impl std::fmt::Debug for SherpaOnnxClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SherpaOnnxClient")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+struct Decoded {
+    text: String,
+    endpoint: bool,
+}
+
+fn pcm_to_samples(pending_byte: &mut Option<u8>, pcm: &[u8]) -> Vec<f32> {
+    let mut bytes = pending_byte.take().into_iter().chain(pcm.iter().copied());
+    let mut samples = Vec::with_capacity(pcm.len() / 2);
+    while let Some(low) = bytes.next() {
+        let Some(high) = bytes.next() else {
+            *pending_byte = Some(low);
+            break;
+        };
+        samples.push(f32::from(i16::from_le_bytes([low, high])) / f32::from(i16::MAX));
+    }
+    samples
+}
+
+fn transcribe(
+    mut online_stream: OnlineStream,
+    mut audio_stream: AudioStream,
+) -> impl Stream<Item = Result<TranscribeResponse, SherpaOnnxError>> {
+    stream! {
+        let mut pending_byte = None;
+        // Samples fed so far, and where the utterance in progress started.
+        let mut position = 0;
+        let mut begin_time = 0;
+        let mut last_text = String::new();
+
+        loop {
+            let chunk = audio_stream.next().await;
+            let finished = chunk.is_none();
+            let samples = match chunk {
+                Some(Ok(chunk)) => pcm_to_samples(&mut pending_byte, &chunk),
+                Some(Err(err)) => {
+                    yield Err(SherpaOnnxError::Audio(err));
+                    continue;
+                }
+                None => Vec::new(),
+            };
+            position += samples.len();
+
+            let decoded = tokio::task::spawn_blocking(move || {
+                if finished {
+                    online_stream.input_finished();
+                } else {
+                    online_stream.accept_waveform(&samples);
+                }
+                let decoded = Decoded {
+                    text: online_stream.text(),
+                    endpoint: finished || online_stream.is_endpoint(),
+                };
+                if decoded.endpoint {
+                    online_stream.reset();
+                }
+                (online_stream, decoded)
+            })
+            .await;
+            let decoded = match decoded {
+                Ok((stream, decoded)) => {
+                    online_stream = stream;
+                    decoded
+                }
+                Err(err) => {
+                    yield Err(err.into());
+                    return;
+                }
+            };
+
+            if decoded.endpoint {
+                if !decoded.text.is_empty() || !last_text.is_empty() {
+                    trace!("endpoint at {begin_time}ms: {}", &decoded.text);
+                    yield Ok(TranscribeResponse {
+                        text: decoded.text,
+                        begin_time,
+                        sentence_end: true,
+                        translation: None,
+                    });
+                }
+                last_text.clear();
+                begin_time = samples_to_ms(position);
+            } else if !decoded.text.is_empty() && decoded.text != last_text {
+                last_text.clone_from(&decoded.text);
+                yield Ok(TranscribeResponse {
+                    text: decoded.text,
+                    begin_time,
+                    sentence_end: false,
+                    translation: None,
+                });
+            }
+
+            if finished {
+                info!("audio stream ended.");
+                return;
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AsrClient for SherpaOnnxClient {
+    type Config = SherpaOnnxConfig;
+    type TranscriptionStream = TranscribeStream<anyhow::Error>;
+
+    fn new(config: impl Into<Self::Config>) -> Self {
+        Self {
+            config: config.into(),
+            recognizer: Arc::new(OnceCell::new()),
+        }
+    }
+
+    async fn create(
+        &self,
+        audio_stream: AudioStream,
+    ) -> Result<Self::TranscriptionStream, anyhow::Error> {
+        let config = self.config.clone();
+        let recognizer = self
+            .recognizer
+            .get_or_try_init(|| async {
+                info!("loading sherpa-onnx model {}", config.encoder.display());
+                let recognizer = tokio::task::spawn_blocking(move || Recognizer::load(&config))
+                    .await
+                    .map_err(SherpaOnnxError::from)??;
+                Ok::<_, SherpaOnnxError>(Arc::new(recognizer))
+            })
+            .await?
+            .clone();
+        let online_stream = OnlineStream::new(recognizer)?;
+
+        let transcribe_stream =
+            transcribe(online_stream, audio_stream).map(|item| item.map_err(anyhow::Error::from));
+
+        Ok(TranscribeStream::new(Box::pin(transcribe_stream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use futures_util::StreamExt;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use base_client::asr_client::AsrClient;
+    use base_client::audio_stream::{AudioCapture, AudioSource};
+    use pcm_playback_recorder::{PcmPlaybackCaptureOption, PcmPlaybackRecorder};
+
+    #[test]
+    fn pcm_to_samples_keeps_odd_byte() {
+        let mut pending_byte = None;
+        let first = pcm_to_samples(&mut pending_byte, &[0x00, 0x40, 0xff]);
+        assert_eq!(first.len(), 1);
+        assert_eq!(pending_byte, Some(0xff));
+
+        let second = pcm_to_samples(&mut pending_byte, &[0x7f]);
+        assert_eq!(second, vec![1.0]);
+        assert_eq!(pending_byte, None);
+    }
+
+    #[tokio::test]
+    async fn missing_library() {
+        let backend = SherpaOnnxClient::new(SherpaOnnxConfig {
+            library_path: Some(PathBuf::from("/nonexistent/libsherpa-onnx-c-api.so")),
+            ..SherpaOnnxConfig::default()
+        });
+        let audio_stream = AudioStream(Box::pin(futures_util::stream::empty()));
+        let Err(err) = backend.create(audio_stream).await else {
+            panic!("must fail")
+        };
+        assert!(err.to_string().contains("sherpa-onnx library error"));
+    }
+
+    #[cfg_attr(
+        not(has_sherpa_onnx_model),
+        ignore = "requires SHERPA_ONNX_MODEL_DIR env var"
+    )]
+    #[tokio::test]
+    async fn transcribe_wav() {
+        let wav_path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/harvard.16k.mono.wav");
+        let recorder = PcmPlaybackRecorder::new(PcmPlaybackCaptureOption::new(wav_path)).unwrap();
+        let audio_stream = recorder
+            .create(AudioSource::default(), CancellationToken::new())
+            .unwrap();
+
+        let model_dir = PathBuf::from(std::env::var("SHERPA_ONNX_MODEL_DIR").unwrap());
+        let backend = SherpaOnnxClient::new(SherpaOnnxConfig {
+            encoder: model_dir.join("encoder.onnx"),
+            decoder: model_dir.join("decoder.onnx"),
+            joiner: model_dir.join("joiner.onnx"),
+            tokens: model_dir.join("tokens.txt"),
+            ..SherpaOnnxConfig::default()
+        });
+        let mut client = backend.create(audio_stream).await.unwrap();
+
+        let mut finals = 0;
+        while let Some(event) = client.next().await {
+            let event = event.unwrap();
+            if event.sentence_end {
+                finals += 1;
+            }
+        }
+        assert!(finals > 0);
+    }
+}
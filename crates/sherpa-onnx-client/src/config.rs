@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecodingMethod {
+    #[default]
+    #[serde(rename = "greedy_search")]
+    GreedySearch,
+    #[serde(rename = "modified_beam_search")]
+    ModifiedBeamSearch,
+}
+
+impl DecodingMethod {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::GreedySearch => "greedy_search",
+            Self::ModifiedBeamSearch => "modified_beam_search",
+        }
+    }
+}
+
+/// A streaming transducer model, as published at
+/// <https://k2-fsa.github.io/sherpa/onnx/pretrained_models/online-transducer/index.html>.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SherpaOnnxConfig {
+    pub encoder: PathBuf,
+    pub decoder: PathBuf,
+    pub joiner: PathBuf,
+    pub tokens: PathBuf,
+    pub library_path: Option<PathBuf>,
+    pub threads: Option<u32>,
+    pub decoding_method: Option<DecodingMethod>,
+    /// Trailing silence that ends an utterance when nothing was recognized yet.
+    pub rule1_min_trailing_silence: Option<f32>,
+    /// Trailing silence that ends an utterance after something was recognized.
+    pub rule2_min_trailing_silence: Option<f32>,
+    /// Utterances are cut when they grow longer than this, in seconds.
+    pub rule3_min_utterance_length: Option<f32>,
+}
+
+impl SherpaOnnxConfig {
+    pub const DEFAULT_LIBRARY_PATH: &str = "libsherpa-onnx-c-api.so";
+
+    #[must_use]
+    pub fn library_path(&self) -> PathBuf {
+        self.library_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(Self::DEFAULT_LIBRARY_PATH))
+    }
+
+    #[must_use]
+    pub fn threads(&self) -> u32 {
+        self.threads.unwrap_or(2)
+    }
+}
@@ -0,0 +1,28 @@
+use std::io;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SherpaOnnxError {
+    /// `libsherpa-onnx-c-api` could not be loaded, or lacks an expected symbol.
+    #[error("sherpa-onnx library error: {0}")]
+    Library(#[from] libloading::Error),
+
+    /// The library is another sherpa-onnx version than the one its structs are mirrored from.
+    #[error(
+        "unsupported sherpa-onnx version {0}, expected {supported}",
+        supported = crate::ffi::SUPPORTED_VERSION
+    )]
+    Version(String),
+
+    #[error("failed to create recognizer: {0}")]
+    Recognizer(String),
+
+    #[error("failed to create stream")]
+    Stream,
+
+    // Audio error
+    #[error("audio error: {0}")]
+    Audio(#[from] io::Error),
+
+    #[error("decoding task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
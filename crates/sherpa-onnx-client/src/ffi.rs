@@ -0,0 +1,329 @@
+//! Minimal runtime binding of the sherpa-onnx C API (`c-api.h`).
+//!
+//! The library is loaded with `dlopen` rather than linked, so dictyped builds and runs without it
+//! for users who do not use this backend. The struct layouts mirror sherpa-onnx 1.10.46, and other
+//! versions of the library are refused, as their layouts may differ.
+
+use std::ffi::{CStr, CString, c_char, c_float, c_void};
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+use libloading::Library;
+
+use crate::config::SherpaOnnxConfig;
+use crate::error::SherpaOnnxError;
+
+const SAMPLE_RATE: i32 = 16_000;
+
+/// The sherpa-onnx version whose struct layouts are mirrored here.
+pub const SUPPORTED_VERSION: &str = "1.10.46";
+
+#[repr(C)]
+struct OnlineTransducerModelConfig {
+    encoder: *const c_char,
+    decoder: *const c_char,
+    joiner: *const c_char,
+}
+
+#[repr(C)]
+struct OnlineParaformerModelConfig {
+    encoder: *const c_char,
+    decoder: *const c_char,
+}
+
+#[repr(C)]
+struct OnlineZipformer2CtcModelConfig {
+    model: *const c_char,
+}
+
+#[repr(C)]
+struct OnlineModelConfig {
+    transducer: OnlineTransducerModelConfig,
+    paraformer: OnlineParaformerModelConfig,
+    zipformer2_ctc: OnlineZipformer2CtcModelConfig,
+    tokens: *const c_char,
+    num_threads: i32,
+    provider: *const c_char,
+    debug: i32,
+    model_type: *const c_char,
+    modeling_unit: *const c_char,
+    bpe_vocab: *const c_char,
+    tokens_buf: *const c_char,
+    tokens_buf_size: i32,
+}
+
+#[repr(C)]
+struct FeatureConfig {
+    sample_rate: i32,
+    feature_dim: i32,
+}
+
+#[repr(C)]
+struct OnlineCtcFstDecoderConfig {
+    graph: *const c_char,
+    max_active: i32,
+}
+
+#[repr(C)]
+struct OnlineRecognizerConfig {
+    feat_config: FeatureConfig,
+    model_config: OnlineModelConfig,
+    decoding_method: *const c_char,
+    max_active_paths: i32,
+    enable_endpoint: i32,
+    rule1_min_trailing_silence: c_float,
+    rule2_min_trailing_silence: c_float,
+    rule3_min_utterance_length: c_float,
+    hotwords_file: *const c_char,
+    hotwords_score: c_float,
+    ctc_fst_decoder_config: OnlineCtcFstDecoderConfig,
+    rule_fsts: *const c_char,
+    rule_fars: *const c_char,
+    blank_penalty: c_float,
+    hotwords_buf: *const c_char,
+    hotwords_buf_size: i32,
+}
+
+/// Only the leading field of `SherpaOnnxOnlineRecognizerResult` is read.
+#[repr(C)]
+struct OnlineRecognizerResult {
+    text: *const c_char,
+}
+
+type GetVersionStr = unsafe extern "C" fn() -> *const c_char;
+type CreateOnlineRecognizer = unsafe extern "C" fn(*const OnlineRecognizerConfig) -> *mut c_void;
+type DestroyOnlineRecognizer = unsafe extern "C" fn(*mut c_void);
+type CreateOnlineStream = unsafe extern "C" fn(*mut c_void) -> *mut c_void;
+type DestroyOnlineStream = unsafe extern "C" fn(*mut c_void);
+type OnlineStreamAcceptWaveform = unsafe extern "C" fn(*mut c_void, i32, *const c_float, i32);
+type OnlineStreamInputFinished = unsafe extern "C" fn(*mut c_void);
+type IsOnlineStreamReady = unsafe extern "C" fn(*mut c_void, *mut c_void) -> i32;
+type DecodeOnlineStream = unsafe extern "C" fn(*mut c_void, *mut c_void);
+type GetOnlineStreamResult =
+    unsafe extern "C" fn(*mut c_void, *mut c_void) -> *const OnlineRecognizerResult;
+type DestroyOnlineRecognizerResult = unsafe extern "C" fn(*const OnlineRecognizerResult);
+type OnlineStreamIsEndpoint = unsafe extern "C" fn(*mut c_void, *mut c_void) -> i32;
+type OnlineStreamReset = unsafe extern "C" fn(*mut c_void, *mut c_void);
+
+struct Functions {
+    destroy_online_recognizer: DestroyOnlineRecognizer,
+    create_online_stream: CreateOnlineStream,
+    destroy_online_stream: DestroyOnlineStream,
+    accept_waveform: OnlineStreamAcceptWaveform,
+    input_finished: OnlineStreamInputFinished,
+    is_ready: IsOnlineStreamReady,
+    decode: DecodeOnlineStream,
+    get_result: GetOnlineStreamResult,
+    destroy_result: DestroyOnlineRecognizerResult,
+    is_endpoint: OnlineStreamIsEndpoint,
+    reset: OnlineStreamReset,
+}
+
+/// A loaded model, shared by the streams of all sessions.
+pub struct Recognizer {
+    ptr: *mut c_void,
+    functions: Functions,
+    // Must outlive the function pointers above.
+    _library: Library,
+}
+
+// The recognizer is immutable once created; streams are driven from one thread at a time.
+unsafe impl Send for Recognizer {}
+unsafe impl Sync for Recognizer {}
+
+fn c_path(path: &Path) -> Result<CString, SherpaOnnxError> {
+    CString::new(path.to_string_lossy().as_bytes())
+        .map_err(|err| SherpaOnnxError::Recognizer(err.to_string()))
+}
+
+impl Recognizer {
+    pub fn load(config: &SherpaOnnxConfig) -> Result<Self, SherpaOnnxError> {
+        let encoder = c_path(&config.encoder)?;
+        let decoder = c_path(&config.decoder)?;
+        let joiner = c_path(&config.joiner)?;
+        let tokens = c_path(&config.tokens)?;
+        let decoding_method = CString::new(config.decoding_method.unwrap_or_default().as_str())
+            .map_err(|err| SherpaOnnxError::Recognizer(err.to_string()))?;
+
+        let recognizer_config = OnlineRecognizerConfig {
+            feat_config: FeatureConfig {
+                sample_rate: SAMPLE_RATE,
+                feature_dim: 80,
+            },
+            model_config: OnlineModelConfig {
+                transducer: OnlineTransducerModelConfig {
+                    encoder: encoder.as_ptr(),
+                    decoder: decoder.as_ptr(),
+                    joiner: joiner.as_ptr(),
+                },
+                paraformer: OnlineParaformerModelConfig {
+                    encoder: ptr::null(),
+                    decoder: ptr::null(),
+                },
+                zipformer2_ctc: OnlineZipformer2CtcModelConfig { model: ptr::null() },
+                tokens: tokens.as_ptr(),
+                num_threads: i32::try_from(config.threads()).unwrap_or(1),
+                provider: c"cpu".as_ptr(),
+                debug: 0,
+                model_type: ptr::null(),
+                modeling_unit: ptr::null(),
+                bpe_vocab: ptr::null(),
+                tokens_buf: ptr::null(),
+                tokens_buf_size: 0,
+            },
+            decoding_method: decoding_method.as_ptr(),
+            max_active_paths: 4,
+            enable_endpoint: 1,
+            rule1_min_trailing_silence: config.rule1_min_trailing_silence.unwrap_or(2.4),
+            rule2_min_trailing_silence: config.rule2_min_trailing_silence.unwrap_or(1.2),
+            rule3_min_utterance_length: config.rule3_min_utterance_length.unwrap_or(20.0),
+            hotwords_file: ptr::null(),
+            hotwords_score: 1.5,
+            ctc_fst_decoder_config: OnlineCtcFstDecoderConfig {
+                graph: ptr::null(),
+                max_active: 3000,
+            },
+            rule_fsts: ptr::null(),
+            rule_fars: ptr::null(),
+            blank_penalty: 0.0,
+            hotwords_buf: ptr::null(),
+            hotwords_buf_size: 0,
+        };
+
+        unsafe {
+            let library = Library::new(config.library_path())?;
+
+            let version = *library.get::<GetVersionStr>(b"SherpaOnnxGetVersionStr\0")?;
+            let version = CStr::from_ptr(version()).to_string_lossy().into_owned();
+            if version != SUPPORTED_VERSION {
+                return Err(SherpaOnnxError::Version(version));
+            }
+
+            let create_online_recognizer =
+                *library.get::<CreateOnlineRecognizer>(b"SherpaOnnxCreateOnlineRecognizer\0")?;
+            let functions = Functions {
+                destroy_online_recognizer: *library
+                    .get::<DestroyOnlineRecognizer>(b"SherpaOnnxDestroyOnlineRecognizer\0")?,
+                create_online_stream: *library
+                    .get::<CreateOnlineStream>(b"SherpaOnnxCreateOnlineStream\0")?,
+                destroy_online_stream: *library
+                    .get::<DestroyOnlineStream>(b"SherpaOnnxDestroyOnlineStream\0")?,
+                accept_waveform: *library
+                    .get::<OnlineStreamAcceptWaveform>(b"SherpaOnnxOnlineStreamAcceptWaveform\0")?,
+                input_finished: *library
+                    .get::<OnlineStreamInputFinished>(b"SherpaOnnxOnlineStreamInputFinished\0")?,
+                is_ready: *library
+                    .get::<IsOnlineStreamReady>(b"SherpaOnnxIsOnlineStreamReady\0")?,
+                decode: *library.get::<DecodeOnlineStream>(b"SherpaOnnxDecodeOnlineStream\0")?,
+                get_result: *library
+                    .get::<GetOnlineStreamResult>(b"SherpaOnnxGetOnlineStreamResult\0")?,
+                destroy_result: *library.get::<DestroyOnlineRecognizerResult>(
+                    b"SherpaOnnxDestroyOnlineRecognizerResult\0",
+                )?,
+                is_endpoint: *library
+                    .get::<OnlineStreamIsEndpoint>(b"SherpaOnnxOnlineStreamIsEndpoint\0")?,
+                reset: *library.get::<OnlineStreamReset>(b"SherpaOnnxOnlineStreamReset\0")?,
+            };
+
+            let ptr = create_online_recognizer(&raw const recognizer_config);
+            if ptr.is_null() {
+                return Err(SherpaOnnxError::Recognizer(
+                    config.encoder.display().to_string(),
+                ));
+            }
+
+            Ok(Self {
+                ptr,
+                functions,
+                _library: library,
+            })
+        }
+    }
+}
+
+impl Drop for Recognizer {
+    fn drop(&mut self) {
+        unsafe { (self.functions.destroy_online_recognizer)(self.ptr) };
+    }
+}
+
+/// The decoding state of one session.
+pub struct OnlineStream {
+    recognizer: Arc<Recognizer>,
+    ptr: *mut c_void,
+}
+
+// A stream is owned by a single session and never used concurrently.
+unsafe impl Send for OnlineStream {}
+
+impl OnlineStream {
+    pub fn new(recognizer: Arc<Recognizer>) -> Result<Self, SherpaOnnxError> {
+        let ptr = unsafe { (recognizer.functions.create_online_stream)(recognizer.ptr) };
+        if ptr.is_null() {
+            return Err(SherpaOnnxError::Stream);
+        }
+        Ok(Self { recognizer, ptr })
+    }
+
+    /// Feeds 16 kHz mono samples in `-1.0..=1.0` and decodes as far as possible.
+    pub fn accept_waveform(&mut self, samples: &[f32]) {
+        let n = i32::try_from(samples.len()).unwrap_or(i32::MAX);
+        unsafe {
+            (self.recognizer.functions.accept_waveform)(self.ptr, SAMPLE_RATE, samples.as_ptr(), n);
+        }
+        self.decode();
+    }
+
+    /// Signals the end of audio and decodes what is left.
+    pub fn input_finished(&mut self) {
+        unsafe { (self.recognizer.functions.input_finished)(self.ptr) };
+        self.decode();
+    }
+
+    fn decode(&mut self) {
+        let functions = &self.recognizer.functions;
+        unsafe {
+            while (functions.is_ready)(self.recognizer.ptr, self.ptr) != 0 {
+                (functions.decode)(self.recognizer.ptr, self.ptr);
+            }
+        }
+    }
+
+    /// The text of the utterance in progress, revised as more audio is decoded.
+    pub fn text(&self) -> String {
+        let functions = &self.recognizer.functions;
+        unsafe {
+            let result = (functions.get_result)(self.recognizer.ptr, self.ptr);
+            if result.is_null() {
+                return String::new();
+            }
+            let text = if (*result).text.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr((*result).text)
+                    .to_string_lossy()
+                    .trim()
+                    .to_string()
+            };
+            (functions.destroy_result)(result);
+            text
+        }
+    }
+
+    /// Whether the endpointing rules consider the utterance in progress finished.
+    pub fn is_endpoint(&self) -> bool {
+        unsafe { (self.recognizer.functions.is_endpoint)(self.recognizer.ptr, self.ptr) != 0 }
+    }
+
+    /// Starts a new utterance.
+    pub fn reset(&mut self) {
+        unsafe { (self.recognizer.functions.reset)(self.recognizer.ptr, self.ptr) };
+    }
+}
+
+impl Drop for OnlineStream {
+    fn drop(&mut self) {
+        unsafe { (self.recognizer.functions.destroy_online_stream)(self.ptr) };
+    }
+}
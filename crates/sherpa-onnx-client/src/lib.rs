@@ -0,0 +1,9 @@
+#![cfg_attr(test, allow(warnings))]
+
+//! Offline streaming speech recognition with [sherpa-onnx](https://github.com/k2-fsa/sherpa-onnx)
+//! transducer models, e.g. streaming Zipformer.
+
+pub mod client;
+pub mod config;
+pub mod error;
+mod ffi;
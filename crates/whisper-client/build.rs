@@ -0,0 +1,8 @@
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(has_whisper_model)");
+    println!("cargo:rerun-if-env-changed=WHISPER_MODEL");
+
+    if std::env::var("WHISPER_MODEL").is_ok() {
+        println!("cargo:rustc-cfg=has_whisper_model");
+    }
+}
@@ -0,0 +1,293 @@
+use std::ffi::CString;
+use std::sync::{Arc, Mutex};
+
+use async_stream::stream;
+use futures_util::{Stream, StreamExt};
+use tokio::sync::OnceCell;
+use tracing::{info, trace};
+
+use base_client::asr_client::AsrClient;
+use base_client::audio_stream::AudioStream;
+use base_client::grpc_server::TranscribeResponse;
+use base_client::transcribe_stream::TranscribeStream;
+use base_client::vad::{VoiceActivityDetector, ms_to_samples};
+
+use crate::config::WhisperConfig;
+use crate::error::WhisperError;
+use crate::ffi::Whisper;
+
+/// whisper.cpp is not a streaming model: audio is cut into utterances by VAD, and the utterance in
+/// progress is re-transcribed as it grows to preview partial results.
+pub struct WhisperClient {
+    config: WhisperConfig,
+    // The model is loaded on first use and kept for later sessions.
+    whisper: Arc<OnceCell<Arc<Mutex<Whisper>>>>,
+}
+
+impl std::fmt::Debug for WhisperClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WhisperClient")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+fn infer(
+    whisper: Arc<Mutex<Whisper>>,
+    config: &WhisperConfig,
+    samples: &[i16],
+) -> impl Future<Output = Result<String, WhisperError>> + use<> {
+    let samples: Vec<f32> = samples
+        .iter()
+        .map(|&s| f32::from(s) / f32::from(i16::MAX))
+        .collect();
+    let language = config
+        .language
+        .as_deref()
+        .and_then(|language| CString::new(language).ok());
+    let threads = config.threads();
+
+    async move {
+        tokio::task::spawn_blocking(move || {
+            whisper.lock().expect("whisper poisoned").transcribe(
+                &samples,
+                language.as_deref(),
+                threads,
+            )
+        })
+        .await?
+    }
+}
+
+/// Transcribes with `infer`, which turns the samples of an utterance into text.
+fn transcribe<I, F>(
+    mut infer: I,
+    mut audio_stream: AudioStream,
+    config: &WhisperConfig,
+) -> impl Stream<Item = Result<TranscribeResponse, WhisperError>> + use<I, F>
+where
+    I: FnMut(&[i16]) -> F,
+    F: Future<Output = Result<String, WhisperError>>,
+{
+    let mut vad = VoiceActivityDetector::new(config.vad.clone().unwrap_or_default());
+    let partial_interval = ms_to_samples(config.partial_interval_ms());
+
+    stream! {
+        // Length of the utterance in progress when it was last transcribed.
+        let mut transcribed_len = 0;
+        // Start of the utterance whose partial result is shown.
+        let mut previewed = None;
+
+        let mut ended = false;
+        while !ended {
+            let segments = match audio_stream.next().await {
+                Some(Ok(chunk)) => vad.push(&chunk),
+                Some(Err(err)) => {
+                    yield Err(WhisperError::Audio(err));
+                    continue;
+                }
+                None => {
+                    ended = true;
+                    vad.finish().into_iter().collect()
+                }
+            };
+
+            for segment in segments {
+                transcribed_len = 0;
+                if previewed == Some(segment.begin_time) {
+                    previewed = None;
+                }
+                trace!("segment at {}ms, {}ms long", segment.begin_time, segment.duration_ms());
+                match infer(&segment.samples).await {
+                    Ok(text) => yield Ok(TranscribeResponse {
+                        text,
+                        begin_time: segment.begin_time,
+                        sentence_end: true,
+                        translation: None,
+                    }),
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                }
+            }
+
+            // An utterance dropped by VAD as too short has no final result to replace its
+            // preview, so the preview is cleared.
+            let current_begin_time = vad.current().map(|current| current.begin_time);
+            if let Some(begin_time) = previewed.filter(|b| current_begin_time != Some(*b)) {
+                previewed = None;
+                yield Ok(TranscribeResponse {
+                    text: String::new(),
+                    begin_time,
+                    sentence_end: true,
+                    translation: None,
+                });
+            }
+
+            let Some(current) = vad.current() else {
+                continue;
+            };
+            if current.samples.len() < transcribed_len + partial_interval {
+                continue;
+            }
+            transcribed_len = current.samples.len();
+            let begin_time = current.begin_time;
+            let samples = current.samples.clone();
+            match infer(&samples).await {
+                Ok(text) if text.is_empty() => {}
+                Ok(text) => {
+                    previewed = Some(begin_time);
+                    yield Ok(TranscribeResponse {
+                        text,
+                        begin_time,
+                        sentence_end: false,
+                        translation: None,
+                    });
+                }
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            }
+        }
+        info!("audio stream ended.");
+    }
+}
+
+#[async_trait::async_trait]
+impl AsrClient for WhisperClient {
+    type Config = WhisperConfig;
+    type TranscriptionStream = TranscribeStream<anyhow::Error>;
+
+    fn new(config: impl Into<Self::Config>) -> Self {
+        Self {
+            config: config.into(),
+            whisper: Arc::new(OnceCell::new()),
+        }
+    }
+
+    async fn create(
+        &self,
+        audio_stream: AudioStream,
+    ) -> Result<Self::TranscriptionStream, anyhow::Error> {
+        let config = self.config.clone();
+        let whisper = self
+            .whisper
+            .get_or_try_init(|| async {
+                let library_path = config.library_path();
+                let model_path = config.model_path.clone();
+                info!("loading whisper model {}", model_path.display());
+                let whisper =
+                    tokio::task::spawn_blocking(move || Whisper::load(&library_path, &model_path))
+                        .await
+                        .map_err(WhisperError::from)??;
+                Ok::<_, WhisperError>(Arc::new(Mutex::new(whisper)))
+            })
+            .await?
+            .clone();
+
+        let infer_config = config.clone();
+        let transcribe_stream = transcribe(
+            move |samples: &[i16]| infer(whisper.clone(), &infer_config, samples),
+            audio_stream,
+            &config,
+        )
+        .map(|item| item.map_err(anyhow::Error::from));
+
+        Ok(TranscribeStream::new(Box::pin(transcribe_stream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use futures_util::StreamExt;
+    use tokio_util::bytes::Bytes;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use base_client::asr_client::AsrClient;
+    use base_client::audio_stream::{AudioCapture, AudioSource};
+    use base_client::vad::VadConfig;
+    use pcm_playback_recorder::{PcmPlaybackCaptureOption, PcmPlaybackRecorder};
+
+    #[tokio::test]
+    async fn missing_library() {
+        let backend = WhisperClient::new(WhisperConfig {
+            model_path: PathBuf::from("/nonexistent/ggml-base.bin"),
+            library_path: Some(PathBuf::from("/nonexistent/libwhisper.so")),
+            ..WhisperConfig::default()
+        });
+        let audio_stream = AudioStream(Box::pin(futures_util::stream::empty()));
+        let Err(err) = backend.create(audio_stream).await else {
+            panic!("must fail")
+        };
+        assert!(err.to_string().contains("whisper library error"));
+    }
+
+    #[tokio::test]
+    async fn dropped_utterance_clears_its_preview() {
+        let config = WhisperConfig {
+            partial_interval_ms: Some(300),
+            vad: Some(VadConfig {
+                min_speech_duration_ms: 5000,
+                ..VadConfig::default()
+            }),
+            ..WhisperConfig::default()
+        };
+        let pcm = |sample: i16, ms: usize| {
+            let bytes: Vec<u8> = std::iter::repeat_n(sample.to_le_bytes(), ms * 16)
+                .flatten()
+                .collect();
+            Ok(Bytes::from(bytes))
+        };
+        let audio_stream = AudioStream(Box::pin(futures_util::stream::iter([
+            pcm(10_000, 600),
+            pcm(0, 1500),
+        ])));
+
+        let infer = |_: &[i16]| async { Ok("hello".to_string()) };
+        let responses: Vec<_> = transcribe(infer, audio_stream, &config)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        let [preview, clear] = responses.as_slice() else {
+            panic!("unexpected responses {responses:?}")
+        };
+        assert_eq!(preview.text, "hello");
+        assert!(!preview.sentence_end);
+        assert!(clear.text.is_empty());
+        assert!(clear.sentence_end);
+        assert_eq!(clear.begin_time, preview.begin_time);
+    }
+
+    #[cfg_attr(not(has_whisper_model), ignore = "requires WHISPER_MODEL env var")]
+    #[tokio::test]
+    async fn transcribe_wav() {
+        let wav_path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../assets/harvard.16k.mono.wav");
+        let recorder = PcmPlaybackRecorder::new(PcmPlaybackCaptureOption::new(wav_path)).unwrap();
+        let audio_stream = recorder
+            .create(AudioSource::default(), CancellationToken::new())
+            .unwrap();
+
+        let backend = WhisperClient::new(WhisperConfig {
+            model_path: PathBuf::from(std::env::var("WHISPER_MODEL").unwrap()),
+            language: Some("en".to_string()),
+            ..WhisperConfig::default()
+        });
+        let mut client = backend.create(audio_stream).await.unwrap();
+
+        let mut finals = 0;
+        while let Some(event) = client.next().await {
+            let event = event.unwrap();
+            if event.sentence_end {
+                finals += 1;
+            }
+        }
+        assert!(finals > 0);
+    }
+}
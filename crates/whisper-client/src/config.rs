@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use base_client::vad::VadConfig;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WhisperConfig {
+    /// A ggml model file, e.g. `ggml-base.bin`.
+    pub model_path: PathBuf,
+    pub library_path: Option<PathBuf>,
+    /// A whisper language code such as `en` or `zh`; detected automatically when absent.
+    pub language: Option<String>,
+    pub threads: Option<u32>,
+    /// How much new speech to wait for before re-transcribing the utterance in progress.
+    pub partial_interval_ms: Option<u32>,
+    pub vad: Option<VadConfig>,
+}
+
+impl WhisperConfig {
+    pub const DEFAULT_LIBRARY_PATH: &str = "libwhisper.so";
+    pub const DEFAULT_PARTIAL_INTERVAL_MS: u32 = 1000;
+
+    #[must_use]
+    pub fn library_path(&self) -> PathBuf {
+        self.library_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(Self::DEFAULT_LIBRARY_PATH))
+    }
+
+    #[must_use]
+    pub fn threads(&self) -> u32 {
+        self.threads.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map_or(4, |n| u32::try_from(n.get()).unwrap_or(4))
+                .min(8)
+        })
+    }
+
+    #[must_use]
+    pub fn partial_interval_ms(&self) -> u32 {
+        self.partial_interval_ms
+            .unwrap_or(Self::DEFAULT_PARTIAL_INTERVAL_MS)
+    }
+}
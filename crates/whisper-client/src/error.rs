@@ -0,0 +1,28 @@
+use std::io;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WhisperError {
+    /// `libwhisper` could not be loaded, or lacks an expected symbol.
+    #[error("whisper library error: {0}")]
+    Library(#[from] libloading::Error),
+
+    /// The library is another whisper.cpp version than the one its structs are mirrored from.
+    #[error(
+        "unsupported whisper.cpp version {0}, expected {supported}",
+        supported = crate::ffi::SUPPORTED_VERSION
+    )]
+    Version(String),
+
+    #[error("failed to load whisper model: {0}")]
+    Model(String),
+
+    #[error("whisper inference failed with code {0}")]
+    Inference(i32),
+
+    // Audio error
+    #[error("audio error: {0}")]
+    Audio(#[from] io::Error),
+
+    #[error("inference task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
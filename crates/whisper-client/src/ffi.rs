@@ -0,0 +1,265 @@
+//! Minimal runtime binding of the whisper.cpp C API.
+//!
+//! `libwhisper` is loaded with `dlopen` rather than linked, so dictyped builds and runs without it
+//! for users who do not use this backend. The struct layouts mirror `whisper.h` of whisper.cpp
+//! 1.7.6, and other versions of the library are refused, as their layouts may differ.
+
+use std::ffi::{CStr, CString, c_char, c_float, c_int, c_void};
+use std::path::Path;
+use std::ptr;
+
+use libloading::Library;
+
+use crate::error::WhisperError;
+
+const WHISPER_SAMPLING_GREEDY: c_int = 0;
+
+/// The whisper.cpp version whose struct layouts are mirrored here.
+pub const SUPPORTED_VERSION: &str = "1.7.6";
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct WhisperAheads {
+    n_heads: usize,
+    heads: *const c_void,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct WhisperContextParams {
+    use_gpu: bool,
+    flash_attn: bool,
+    gpu_device: c_int,
+    dtw_token_timestamps: bool,
+    dtw_aheads_preset: c_int,
+    dtw_n_top: c_int,
+    dtw_aheads: WhisperAheads,
+    dtw_mem_size: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct WhisperGreedyParams {
+    best_of: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct WhisperBeamSearchParams {
+    beam_size: c_int,
+    patience: c_float,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct WhisperVadParams {
+    threshold: c_float,
+    min_speech_duration_ms: c_int,
+    min_silence_duration_ms: c_int,
+    max_speech_duration_s: c_float,
+    speech_pad_ms: c_int,
+    samples_overlap: c_float,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(clippy::struct_excessive_bools)]
+struct WhisperFullParams {
+    strategy: c_int,
+
+    n_threads: c_int,
+    n_max_text_ctx: c_int,
+    offset_ms: c_int,
+    duration_ms: c_int,
+
+    translate: bool,
+    no_context: bool,
+    no_timestamps: bool,
+    single_segment: bool,
+    print_special: bool,
+    print_progress: bool,
+    print_realtime: bool,
+    print_timestamps: bool,
+
+    token_timestamps: bool,
+    thold_pt: c_float,
+    thold_ptsum: c_float,
+    max_len: c_int,
+    split_on_word: bool,
+    max_tokens: c_int,
+
+    debug_mode: bool,
+    audio_ctx: c_int,
+
+    tdrz_enable: bool,
+
+    suppress_regex: *const c_char,
+
+    initial_prompt: *const c_char,
+    prompt_tokens: *const c_int,
+    prompt_n_tokens: c_int,
+
+    language: *const c_char,
+    detect_language: bool,
+
+    suppress_blank: bool,
+    suppress_nst: bool,
+
+    temperature: c_float,
+    max_initial_ts: c_float,
+    length_penalty: c_float,
+
+    temperature_inc: c_float,
+    entropy_thold: c_float,
+    logprob_thold: c_float,
+    no_speech_thold: c_float,
+
+    greedy: WhisperGreedyParams,
+    beam_search: WhisperBeamSearchParams,
+
+    new_segment_callback: *const c_void,
+    new_segment_callback_user_data: *mut c_void,
+    progress_callback: *const c_void,
+    progress_callback_user_data: *mut c_void,
+    encoder_begin_callback: *const c_void,
+    encoder_begin_callback_user_data: *mut c_void,
+    abort_callback: *const c_void,
+    abort_callback_user_data: *mut c_void,
+    logits_filter_callback: *const c_void,
+    logits_filter_callback_user_data: *mut c_void,
+
+    grammar_rules: *const *const c_void,
+    n_grammar_rules: usize,
+    i_start_rule: usize,
+    grammar_penalty: c_float,
+
+    vad: bool,
+    vad_model_path: *const c_char,
+    vad_params: WhisperVadParams,
+}
+
+type Version = unsafe extern "C" fn() -> *const c_char;
+type ContextDefaultParamsByRef = unsafe extern "C" fn() -> *mut WhisperContextParams;
+type FreeContextParams = unsafe extern "C" fn(*mut WhisperContextParams);
+type InitFromFileWithParams =
+    unsafe extern "C" fn(*const c_char, WhisperContextParams) -> *mut c_void;
+type Free = unsafe extern "C" fn(*mut c_void);
+type FullDefaultParamsByRef = unsafe extern "C" fn(c_int) -> *mut WhisperFullParams;
+type FreeParams = unsafe extern "C" fn(*mut WhisperFullParams);
+type Full = unsafe extern "C" fn(*mut c_void, WhisperFullParams, *const c_float, c_int) -> c_int;
+type FullNSegments = unsafe extern "C" fn(*mut c_void) -> c_int;
+type FullGetSegmentText = unsafe extern "C" fn(*mut c_void, c_int) -> *const c_char;
+
+/// A loaded whisper.cpp model. Not thread safe: callers serialize access.
+pub struct Whisper {
+    ctx: *mut c_void,
+    default_params: WhisperFullParams,
+    free: Free,
+    full: Full,
+    full_n_segments: FullNSegments,
+    full_get_segment_text: FullGetSegmentText,
+    // Must outlive the function pointers above.
+    _library: Library,
+}
+
+// The context is only ever used by one thread at a time, guarded by a mutex in the client.
+unsafe impl Send for Whisper {}
+
+impl Whisper {
+    pub fn load(library_path: &Path, model_path: &Path) -> Result<Self, WhisperError> {
+        let model_path_c = CString::new(model_path.to_string_lossy().as_bytes())
+            .map_err(|err| WhisperError::Model(err.to_string()))?;
+
+        unsafe {
+            let library = Library::new(library_path)?;
+
+            let version = *library.get::<Version>(b"whisper_version\0")?;
+            let version = CStr::from_ptr(version()).to_string_lossy().into_owned();
+            if version != SUPPORTED_VERSION {
+                return Err(WhisperError::Version(version));
+            }
+
+            let context_default_params_by_ref = *library
+                .get::<ContextDefaultParamsByRef>(b"whisper_context_default_params_by_ref\0")?;
+            let free_context_params =
+                *library.get::<FreeContextParams>(b"whisper_free_context_params\0")?;
+            let init_from_file_with_params =
+                *library.get::<InitFromFileWithParams>(b"whisper_init_from_file_with_params\0")?;
+            let full_default_params_by_ref =
+                *library.get::<FullDefaultParamsByRef>(b"whisper_full_default_params_by_ref\0")?;
+            let free_params = *library.get::<FreeParams>(b"whisper_free_params\0")?;
+            let free = *library.get::<Free>(b"whisper_free\0")?;
+            let full = *library.get::<Full>(b"whisper_full\0")?;
+            let full_n_segments = *library.get::<FullNSegments>(b"whisper_full_n_segments\0")?;
+            let full_get_segment_text =
+                *library.get::<FullGetSegmentText>(b"whisper_full_get_segment_text\0")?;
+
+            let context_params_ptr = context_default_params_by_ref();
+            let mut context_params = *context_params_ptr;
+            free_context_params(context_params_ptr);
+            context_params.use_gpu = false;
+
+            let ctx = init_from_file_with_params(model_path_c.as_ptr(), context_params);
+            if ctx.is_null() {
+                return Err(WhisperError::Model(model_path.display().to_string()));
+            }
+
+            let params_ptr = full_default_params_by_ref(WHISPER_SAMPLING_GREEDY);
+            let default_params = *params_ptr;
+            free_params(params_ptr);
+
+            Ok(Self {
+                ctx,
+                default_params,
+                free,
+                full,
+                full_n_segments,
+                full_get_segment_text,
+                _library: library,
+            })
+        }
+    }
+
+    /// Transcribes 16 kHz mono samples in `-1.0..=1.0` as a single utterance.
+    pub fn transcribe(
+        &mut self,
+        samples: &[f32],
+        language: Option<&CStr>,
+        threads: u32,
+    ) -> Result<String, WhisperError> {
+        let mut params = self.default_params;
+        params.n_threads = c_int::try_from(threads).unwrap_or(1);
+        params.no_context = true;
+        params.single_segment = true;
+        params.print_special = false;
+        params.print_progress = false;
+        params.print_realtime = false;
+        params.print_timestamps = false;
+        params.language = language.map_or(c"auto".as_ptr(), CStr::as_ptr);
+        params.detect_language = false;
+        params.initial_prompt = ptr::null();
+
+        let n_samples = c_int::try_from(samples.len()).unwrap_or(c_int::MAX);
+        unsafe {
+            let code = (self.full)(self.ctx, params, samples.as_ptr(), n_samples);
+            if code != 0 {
+                return Err(WhisperError::Inference(code));
+            }
+
+            let mut text = String::new();
+            for i in 0..(self.full_n_segments)(self.ctx) {
+                let segment = (self.full_get_segment_text)(self.ctx, i);
+                if !segment.is_null() {
+                    text.push_str(&CStr::from_ptr(segment).to_string_lossy());
+                }
+            }
+            Ok(text.trim().to_string())
+        }
+    }
+}
+
+impl Drop for Whisper {
+    fn drop(&mut self) {
+        unsafe { (self.free)(self.ctx) };
+    }
+}
@@ -0,0 +1,8 @@
+#![cfg_attr(test, allow(warnings))]
+
+//! Offline speech recognition with [whisper.cpp](https://github.com/ggml-org/whisper.cpp).
+
+pub mod client;
+pub mod config;
+pub mod error;
+mod ffi;